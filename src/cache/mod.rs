@@ -0,0 +1,112 @@
+use crate::write_output_file;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Rendered HTML kept in a [`RenderCache`], with the word count reported when it is written out
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedRender {
+    pub html: String,
+    pub word_count: u32,
+}
+
+/// Content-addressed store of rendered HTML. Entries are keyed by a hash of everything the render
+/// depends on, rather than file paths or modification times, so moved files and clock skew still
+/// hit, while any change to the source or options misses.
+#[derive(Clone, Debug)]
+pub struct RenderCache {
+    directory: PathBuf,
+}
+
+impl RenderCache {
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        RenderCache {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Hex SHA-256 digest of `parts`. Each part is prefixed with its length, so moving bytes from
+    /// one part to the next changes the key.
+    pub fn key(parts: &[&[u8]]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        hasher
+            .finalize()
+            .iter()
+            .fold(String::with_capacity(64), |mut result, byte| {
+                let _ = write!(result, "{byte:02x}");
+                result
+            })
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{key}.json"))
+    }
+
+    /// Cached render for `key`. Missing, unreadable and corrupt entries are all misses.
+    pub fn get(&self, key: &str) -> Option<CachedRender> {
+        let content = fs::read_to_string(self.entry_path(key)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Stores `value` under `key`, creating the cache directory if needed
+    ///
+    /// # Errors
+    /// Errors if the cache directory or entry cannot be written
+    pub fn insert(&self, key: &str, value: &CachedRender) -> io::Result<()> {
+        let content = serde_json::to_string(value).map_err(io::Error::other)?;
+        write_output_file(&self.entry_path(key), content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachedRender, RenderCache};
+    use std::fs;
+
+    #[test]
+    fn key_changes_with_any_part_and_part_boundaries() {
+        // act
+        let key = RenderCache::key(&[b"# Title", b"{\"byline\":false}"]);
+        let same_key = RenderCache::key(&[b"# Title", b"{\"byline\":false}"]);
+        let changed_key = RenderCache::key(&[b"# Title", b"{\"byline\":true}"]);
+        let moved_boundary_key = RenderCache::key(&[b"# Title{", b"\"byline\":false}"]);
+
+        // assert
+        assert_eq!(key, same_key);
+        assert_eq!(key.len(), 64);
+        assert_ne!(key, changed_key);
+        assert_ne!(key, moved_boundary_key);
+    }
+
+    #[test]
+    fn get_returns_inserted_render_and_misses_corrupt_entries() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let cache = RenderCache::new(temp_dir.path().join("cache"));
+        let render = CachedRender {
+            html: String::from("<h1>Title</h1>"),
+            word_count: 1,
+        };
+
+        // act
+        cache
+            .insert("abc", &render)
+            .expect("Error writing cache entry");
+        fs::write(temp_dir.path().join("cache").join("def.json"), "{")
+            .expect("Error writing corrupt cache entry");
+
+        // assert
+        assert_eq!(cache.get("abc"), Some(render));
+        assert_eq!(cache.get("def"), None);
+        assert_eq!(cache.get("missing"), None);
+    }
+}
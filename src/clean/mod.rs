@@ -0,0 +1,106 @@
+use crate::{default_output_path, utilities::files::markdown_files};
+use std::{
+    fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Extensions of files generated alongside the HTML output, which `clean` also removes
+const SIDECAR_EXTENSIONS: [&str; 4] = ["html.gz", "json", "txt", "stats.json"];
+
+/// Paths of generated output files which would be written for the markdown source at `path`
+fn generated_output_paths(path: &Path) -> Vec<PathBuf> {
+    let html_path = default_output_path(path);
+    let mut result = vec![html_path.clone()];
+    result.extend(
+        SIDECAR_EXTENSIONS
+            .iter()
+            .map(|extension| html_path.with_extension(extension)),
+    );
+    result
+}
+
+/// Removes generated outputs for each markdown file in `directory`, never removing markdown
+/// sources.  With `dry_run`, files are listed but left in place.  Returns the paths which were (or,
+/// for a dry run, would be) removed.
+///
+/// # Errors
+/// Errors if the directory cannot be read or a generated file cannot be removed
+pub fn clean_generated_outputs<P: AsRef<Path>>(
+    directory: P,
+    dry_run: bool,
+    stdout_handle: &mut impl Write,
+) -> io::Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for markdown_path in markdown_files(directory.as_ref())? {
+        for output_path in generated_output_paths(&markdown_path) {
+            if !output_path.is_file() || output_path == markdown_path {
+                continue;
+            }
+            let display_path = output_path.display().to_string();
+            if dry_run {
+                writeln!(stdout_handle, "[ INFO ] Would remove {display_path}.")?;
+            } else {
+                fs::remove_file(&output_path)?;
+                writeln!(stdout_handle, "[ INFO ] Removed {display_path}.")?;
+            }
+            removed.push(output_path);
+        }
+    }
+    stdout_handle.flush()?;
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clean_generated_outputs;
+    use std::fs;
+
+    #[test]
+    fn clean_generated_outputs_removes_html_and_keeps_markdown() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("post.md");
+        let html_path = temp_dir.path().join("post.html");
+        let stats_path = temp_dir.path().join("post.stats.json");
+        let unrelated_path = temp_dir.path().join("notes.txt");
+        fs::write(&markdown_path, "# Post").expect("Error writing markdown");
+        fs::write(&html_path, "<h1>Post</h1>").expect("Error writing HTML");
+        fs::write(&stats_path, "{}").expect("Error writing statistics");
+        fs::write(&unrelated_path, "Notes").expect("Error writing unrelated file");
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        let result = clean_generated_outputs(temp_dir.path(), false, &mut handle)
+            .expect("Error cleaning outputs");
+
+        // assert
+        assert_eq!(result, vec![html_path.clone(), stats_path.clone()]);
+        assert!(markdown_path.exists());
+        assert!(unrelated_path.exists());
+        assert!(!html_path.exists());
+        assert!(!stats_path.exists());
+    }
+
+    #[test]
+    fn clean_generated_outputs_leaves_files_in_place_for_dry_run() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let nested_directory = temp_dir.path().join("posts");
+        fs::create_dir(&nested_directory).expect("Error creating nested directory");
+        let html_path = nested_directory.join("post.html");
+        fs::write(nested_directory.join("post.md"), "# Post").expect("Error writing markdown");
+        fs::write(&html_path, "<h1>Post</h1>").expect("Error writing HTML");
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        let result = clean_generated_outputs(temp_dir.path(), true, &mut handle)
+            .expect("Error cleaning outputs");
+
+        // assert
+        assert_eq!(result, vec![html_path.clone()]);
+        assert!(html_path.exists());
+        let output = String::from_utf8(handle).expect("Expected valid UTF-8 output");
+        assert!(output.contains("Would remove"));
+    }
+}
@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod tests;
 
+use crate::utilities::sentences::{
+    sentence_ends, strip_trailing_sentence_stub, trailing_sentences_start,
+};
 use log::trace;
 use owo_colors::{
     colors::{BrightBlue, BrightCyan, White},
@@ -8,20 +11,202 @@ use owo_colors::{
 };
 use reqwest::header::{HeaderMap, HeaderValue, ACCEPT};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Write};
+use std::{
+    cell::RefCell,
+    cmp,
+    collections::HashSet,
+    fmt::Write,
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::Semaphore;
+
+/// Grammar server check endpoint used when none is configured
+pub const DEFAULT_LANGUAGETOOL_URL: &str = "https://api.languagetoolplus.com/v2/check";
+
+/// Language requested from the grammar server when a region does not set one
+pub const DEFAULT_LANGUAGE: &str = "en-GB";
+
+/// Language requested for grammar server language detection
+pub const AUTO_LANGUAGE: &str = "auto";
+
+/// Category of spelling results, the only results auto-fixed
+pub const SPELLING_CATEGORY: &str = "TYPOS";
+
+/// Default maximum number of replacement suggestions kept for each match
+pub const DEFAULT_REPLACEMENT_LIMIT: usize = 5;
+
+/// Default detected language confidence, below which a check raises a warning
+pub const DEFAULT_MIN_LANGUAGE_CONFIDENCE: f64 = 0.5;
+
+/// Default maximum number of grammar server requests in flight at once, across a whole run
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Output format for grammar check results
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// Colourised text for reading in a terminal
+    #[default]
+    Human,
+
+    /// JSON array of results
+    Json,
+
+    /// SARIF 2.1.0 log, understood by code review tools
+    Sarif,
+}
+
+/// Offset `chunk_size` bytes after `start` in `text`, moved back to a character boundary, so
+/// multibyte characters are never split, though always taking at least one character
+fn chunk_end_limit(text: &str, start: usize, chunk_size: usize) -> usize {
+    let mut result = cmp::min(text.len(), start + chunk_size);
+    while !text.is_char_boundary(result) {
+        result -= 1;
+    }
+    if result <= start {
+        result = text[start..]
+            .chars()
+            .next()
+            .map_or(text.len(), |character| start + character.len_utf8());
+    }
+    result
+}
+
+/// How grammar check chunks find the sentence end they are trimmed back to
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum SentenceSegmentation {
+    /// Trim back to the last full stop, question or exclamation mark followed by whitespace, or
+    /// blank line
+    #[default]
+    Heuristic,
+
+    /// Split the text into sentences first, also handling closing quotes, initials and lowercase
+    /// continuations, falling back to the heuristic for sentences longer than a chunk
+    Local,
+}
+
+/// Splits `plain_text` into chunks for grammar checking, returning each with its offset in
+/// `plain_text`. Each chunk adds at most `chunk_size` bytes of new text, and all but the last are
+/// trimmed back to a sentence or paragraph end, found using `segmentation`, with sentences ended by
+/// `terminators`. The last chunk is never trimmed, so a final, incomplete sentence is still checked
+/// along with the text before it. Chunks after the first start by repeating the last
+/// `overlap_sentences` sentences of the chunk before, unless those take up more than half of
+/// `chunk_size`, so issues spanning chunks are not missed.
+pub fn grammar_check_chunks<'a>(
+    plain_text: &'a str,
+    chunk_size: usize,
+    overlap_sentences: usize,
+    segmentation: SentenceSegmentation,
+    terminators: &[char],
+) -> Vec<(usize, &'a str)> {
+    let sentence_ends = match segmentation {
+        SentenceSegmentation::Heuristic => Vec::new(),
+        SentenceSegmentation::Local => sentence_ends(plain_text, terminators),
+    };
+    let mut result = Vec::new();
+    let mut chunk_start: usize = 0;
+    let mut start: usize = 0;
+    let plain_text_length = plain_text.len();
+    let mut end = chunk_end_limit(plain_text, 0, chunk_size);
+    while start < plain_text_length {
+        let last_sentence_end = sentence_ends
+            .iter()
+            .rev()
+            .find(|sentence_end| (start + 1..=end).contains(*sentence_end));
+        let trimmed_chunk_end = if end == plain_text_length {
+            end - start
+        } else if let Some(value) = last_sentence_end {
+            value - start
+        } else {
+            strip_trailing_sentence_stub(&plain_text[start..end], terminators).1
+        };
+        // With no sentence end to trim back to, split hard at the chunk size, so each chunk always
+        // moves the start forward, even through long runs of text with no terminators
+        let chunk_end = if trimmed_chunk_end == 0 {
+            end
+        } else {
+            start + trimmed_chunk_end
+        };
+        result.push((chunk_start, &plain_text[chunk_start..chunk_end]));
+
+        let overlap_start = chunk_start
+            + trailing_sentences_start(
+                &plain_text[chunk_start..chunk_end],
+                overlap_sentences,
+                terminators,
+            );
+        chunk_start = if chunk_end - overlap_start > chunk_size / 2 {
+            chunk_end
+        } else {
+            overlap_start
+        };
+        start = chunk_end;
+        end = chunk_end_limit(plain_text, start, chunk_size);
+    }
+    result
+}
+
+/// Line and column, both counting from 1, of text flagged by a check in the markdown source
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SourcePosition {
+    line: usize,
+    column: usize,
+}
+
+impl SourcePosition {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
 
 #[derive(Debug)]
 pub struct CheckResult {
+    category_id: String,
     context_length: u32,
     context_offset: u32,
     message: String,
+    rule_id: String,
     sentence: String,
     short_message: String,
     text: String,
+
+    /// Byte offset of the flagged text in the checked text, relative to the region text once
+    /// chunk results are combined
+    text_offset: usize,
+
+    /// Length of the flagged text in bytes
+    text_length: usize,
+
+    /// Byte range of the flagged text in the markdown source, once mapped back from the plaintext
+    source_range: Option<Range<usize>>,
     replacements: Vec<String>,
+
+    /// The replacement, when the server suggested exactly one, whatever the replacement limit
+    sole_replacement: Option<String>,
+
+    /// Whether the flagged text is image alt text, rather than the body text
+    alt_text: bool,
 }
 
 impl CheckResult {
+    /// Rule category reported by the grammar server, for example `TYPOS` or `TYPOGRAPHY`
+    #[must_use]
+    pub fn category_id(&self) -> &str {
+        &self.category_id
+    }
+
+    /// Context text around the flagged text, with the flagged text highlighted
+    ///
+    /// # Panics
+    /// Panics if the context offset or length does not fit in a `usize`
+    #[must_use]
     pub fn context(&self) -> String {
         let CheckResult {
             context_length,
@@ -45,10 +230,47 @@ impl CheckResult {
         )
     }
 
+    /// Text the check flagged, picked out of the context
+    #[must_use]
+    pub fn flagged_text(&self) -> Option<&str> {
+        let start = usize::try_from(self.context_offset).ok()?;
+        let end = start + usize::try_from(self.context_length).ok()?;
+        self.text.get(start..end)
+    }
+
+    /// Replacement for a spelling result with a single suggestion, which is safe to apply without
+    /// review. Results with several suggestions are ambiguous, so give `None`.
+    #[must_use]
+    pub fn autofix_replacement(&self) -> Option<&str> {
+        if self.category_id == SPELLING_CATEGORY {
+            self.sole_replacement.as_deref()
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
     pub fn message(&self) -> &str {
         &self.message
     }
 
+    #[must_use]
+    pub fn is_alt_text(&self) -> bool {
+        self.alt_text
+    }
+
+    /// Labels the result as coming from image alt text
+    pub(crate) fn set_alt_text(&mut self) {
+        self.alt_text = true;
+    }
+
+    /// Grammar server rule id, for example `MORFOLOGIK_RULE_EN_GB`
+    #[must_use]
+    pub fn rule_id(&self) -> &str {
+        &self.rule_id
+    }
+
+    #[must_use]
     pub fn replacements_string(&self) -> Option<String> {
         if self.replacements.is_empty() {
             None
@@ -61,7 +283,7 @@ impl CheckResult {
                             output,
                             "        {} {}",
                             "-".to_string().fg::<White>(),
-                            val.to_string().fg::<BrightCyan>(),
+                            val.clone().fg::<BrightCyan>(),
                         );
                         output
                     }),
@@ -69,10 +291,80 @@ impl CheckResult {
         }
     }
 
+    /// Byte offset of the flagged text in the plaintext of the region it was found in
+    #[must_use]
+    pub fn text_offset(&self) -> usize {
+        self.text_offset
+    }
+
+    /// Length of the flagged text in bytes
+    #[must_use]
+    pub fn text_length(&self) -> usize {
+        self.text_length
+    }
+
+    /// Byte range of the flagged text in the markdown source, for placing editor markers. `None`
+    /// until mapped back from the plaintext, or when the flagged text has no source of its own.
+    #[must_use]
+    pub fn source_range(&self) -> Option<Range<usize>> {
+        self.source_range.clone()
+    }
+
+    pub(crate) fn set_source_range(&mut self, value: Option<Range<usize>>) {
+        self.source_range = value;
+    }
+
+    /// Moves the flagged text byte offset on by `value`, the byte offset in the region text of
+    /// the chunk the result was found in, so the offset is relative to the region text
+    pub(crate) fn shift_text_offset(&mut self, value: usize) {
+        self.text_offset += value;
+    }
+
+    /// Replaces the context the grammar server returned with `width` characters either side of
+    /// the flagged text, taken from `text`, the plaintext the offset is relative to. The server
+    /// context is kept when the flagged text is not found at the offset.
+    pub(crate) fn expand_context(&mut self, text: &str, width: usize) {
+        let start = self.text_offset;
+        let end = start + self.text_length;
+        let Some(flagged_text) = text.get(start..end) else {
+            return;
+        };
+        if self.flagged_text() != Some(flagged_text) {
+            return;
+        }
+        let (Ok(context_offset), Ok(context_length)) = (
+            u32::try_from(
+                text[..start]
+                    .chars()
+                    .rev()
+                    .take(width)
+                    .map(char::len_utf8)
+                    .sum::<usize>(),
+            ),
+            u32::try_from(flagged_text.len()),
+        ) else {
+            return;
+        };
+        let context_start = start - context_offset as usize;
+        let context_end = end
+            + text[end..]
+                .chars()
+                .take(width)
+                .map(char::len_utf8)
+                .sum::<usize>();
+
+        // Keep the context on one line, like the server does
+        self.text = text[context_start..context_end].replace('\n', " ");
+        self.context_offset = context_offset;
+        self.context_length = context_length;
+    }
+
+    #[must_use]
     pub fn sentence(&self) -> &str {
         &self.sentence
     }
 
+    #[must_use]
     pub fn short_message(&self) -> &str {
         &self.short_message
     }
@@ -81,13 +373,8 @@ impl CheckResult {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct LanguageToolsCheckResponseDetectedLanguage {
-    #[allow(dead_code)]
     name: String,
-
-    #[allow(dead_code)]
     code: String,
-
-    #[allow(dead_code)]
     confidence: f64,
 
     #[allow(dead_code)]
@@ -103,7 +390,6 @@ struct LanguageToolsCheckResponseLanguage {
     #[allow(dead_code)]
     code: String,
 
-    #[allow(dead_code)]
     detected_language: LanguageToolsCheckResponseDetectedLanguage,
 }
 
@@ -201,54 +487,358 @@ struct LanguageToolsCheckResponse {
     #[allow(dead_code)]
     warnings: LanguageToolsCheckResponseWarnings,
 
-    #[allow(dead_code)]
     language: LanguageToolsCheckResponseLanguage,
     matches: Vec<LanguageToolsCheckResponseMatch>,
     sentence_ranges: Vec<Vec<u32>>,
 }
 
-pub struct Checker<'a> {
-    url: &'a str,
+/// Language supported by the grammar server, as listed by its `/v2/languages` endpoint
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageInfo {
+    name: String,
+    code: String,
+    long_code: String,
+}
+
+impl LanguageInfo {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    #[must_use]
+    pub fn long_code(&self) -> &str {
+        &self.long_code
+    }
+}
+
+/// Checks `language` is one of `supported_languages`, matching either the short (`en`) or long
+/// (`en-GB`) code, or is `auto` for server-side detection.
+///
+/// # Errors
+/// Errors with a message listing the supported language codes, when `language` is not supported
+pub fn check_language_supported(
+    language: &str,
+    supported_languages: &[LanguageInfo],
+) -> Result<(), String> {
+    let is_supported = language == AUTO_LANGUAGE
+        || supported_languages.iter().any(|value| {
+            value.code.eq_ignore_ascii_case(language)
+                || value.long_code.eq_ignore_ascii_case(language)
+        });
+    if is_supported {
+        return Ok(());
+    }
+    let mut allowed_codes: Vec<&str> = supported_languages
+        .iter()
+        .map(|value| value.long_code.as_str())
+        .collect();
+    allowed_codes.sort_unstable();
+    allowed_codes.dedup();
+    Err(format!(
+        "Grammar server does not support language `{language}`, supported languages: {}",
+        allowed_codes.join(", ")
+    ))
+}
+
+pub struct Checker {
+    url: String,
+
+    /// HTTP client reused for every request, so connections can be kept alive between checks
+    client: reqwest::Client,
+
+    /// Grammar server account username and API key, sent with each check when set
+    api_credentials: Option<(String, String)>,
+
+    /// Grammar server rule category ids (for example `TYPOGRAPHY`) to skip checks for
+    disabled_categories: Vec<String>,
+
+    /// Grammar server rule ids (for example `ELLIPSIS`), or category ids, whose matches are
+    /// dropped from results
+    ignored_rules: Vec<String>,
+
+    /// Language variants (for example `en-US`) preferred when the grammar server detects the
+    /// language. Only valid with detection, so regions without a language are checked as `auto`
+    /// when these are set.
+    preferred_variants: Vec<String>,
+
+    /// Maximum number of replacement suggestions kept for each match, zero hides suggestions
+    replacement_limit: usize,
+
+    /// Permits shared by every checker in a run, bounding concurrent grammar server requests
+    request_permits: Option<Arc<Semaphore>>,
+
+    /// Most check requests sent in a run, with the count sent so far, shared by every checker
+    request_budget: Option<(usize, Arc<AtomicUsize>)>,
+
+    /// Detected language confidence (0 to 1) below which a warning is raised
+    min_language_confidence: f64,
+
+    /// Words spelled as intended, whose spelling results are dropped
+    dictionary: HashSet<String>,
+
+    warnings: RefCell<Vec<String>>,
 }
 
-impl Checker<'_> {
+impl Checker {
+    #[must_use]
     pub fn new(url: Option<&str>) -> Checker {
         let actual_url: &str = match url {
             Some(value) => value,
-            None => "https://api.languagetoolplus.com/v2/check",
+            None => DEFAULT_LANGUAGETOOL_URL,
+        };
+        Checker {
+            url: actual_url.to_string(),
+            client: reqwest::Client::new(),
+            api_credentials: None,
+            disabled_categories: Vec::new(),
+            ignored_rules: Vec::new(),
+            preferred_variants: Vec::new(),
+            replacement_limit: DEFAULT_REPLACEMENT_LIMIT,
+            request_permits: None,
+            request_budget: None,
+            min_language_confidence: DEFAULT_MIN_LANGUAGE_CONFIDENCE,
+            dictionary: HashSet::new(),
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn api_credentials(&mut self, username: &str, api_key: &str) -> &mut Self {
+        self.api_credentials = Some((username.to_string(), api_key.to_string()));
+        self
+    }
+
+    pub fn disabled_categories(&mut self, value: Vec<String>) -> &mut Self {
+        self.disabled_categories = value;
+        self
+    }
+
+    pub fn ignored_rules(&mut self, value: Vec<String>) -> &mut Self {
+        self.ignored_rules = value;
+        self
+    }
+
+    pub fn preferred_variants(&mut self, value: Vec<String>) -> &mut Self {
+        self.preferred_variants = value;
+        self
+    }
+
+    pub fn replacement_limit(&mut self, value: usize) -> &mut Self {
+        self.replacement_limit = value;
+        self
+    }
+
+    pub fn request_permits(&mut self, value: Arc<Semaphore>) -> &mut Self {
+        self.request_permits = Some(value);
+        self
+    }
+
+    pub fn request_budget(&mut self, limit: usize, requests_sent: Arc<AtomicUsize>) -> &mut Self {
+        self.request_budget = Some((limit, requests_sent));
+        self
+    }
+
+    /// Counts a check request against the budget, returning `false`, with a warning, once the
+    /// budget is used up
+    fn take_budgeted_request(&self) -> bool {
+        let Some((limit, requests_sent)) = &self.request_budget else {
+            return true;
         };
-        Checker { url: actual_url }
+        let within_budget = requests_sent
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |sent| {
+                (sent < *limit).then_some(sent + 1)
+            })
+            .is_ok();
+        if !within_budget {
+            self.warn(format!(
+                "Grammar request budget of {limit} reached, skipping further spelling and grammar checks"
+            ));
+        }
+        within_budget
     }
 
+    pub fn min_language_confidence(&mut self, value: f64) -> &mut Self {
+        self.min_language_confidence = value;
+        self
+    }
+
+    pub fn dictionary(&mut self, value: HashSet<String>) -> &mut Self {
+        self.dictionary = value;
+        self
+    }
+
+    /// Words spelled as intended, whose spelling results are dropped
+    #[must_use]
+    pub fn dictionary_words(&self) -> &HashSet<String> {
+        &self.dictionary
+    }
+
+    /// Warnings raised by checks so far, for example a low detected language confidence
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings.take()
+    }
+
+    fn warn(&self, message: String) {
+        let mut warnings = self.warnings.borrow_mut();
+        if !warnings.contains(&message) {
+            warnings.push(message);
+        }
+    }
+
+    /// URL of the grammar server languages endpoint, alongside the configured check endpoint
+    fn languages_url(&self) -> String {
+        match self.url.rsplit_once('/') {
+            Some((base, _)) => format!("{base}/languages"),
+            None => format!("{}/languages", self.url),
+        }
+    }
+
+    /// Languages the configured grammar server can check
+    ///
+    /// # Errors
+    /// Returns an error if the grammar server cannot be reached or its response is not valid
+    pub async fn supported_languages(
+        &self,
+    ) -> Result<Vec<LanguageInfo>, Box<dyn std::error::Error>> {
+        let response = match self
+            .client
+            .get(self.languages_url())
+            .header(ACCEPT, "application/json")
+            .send()
+            .await
+        {
+            Ok(value) => value,
+            Err(error) => {
+                eprintln!("[ ERROR ] no response from remote grammar check server: {error:?}.");
+                return Err(error.into());
+            }
+        };
+        match response.json::<Vec<LanguageInfo>>().await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                eprintln!("[ ERROR ] error parsing remote grammar server languages: {error:?}.");
+                Err(error.into())
+            }
+        }
+    }
+
+    /// Form fields posted to check `text`, in `language` when given, otherwise the default
+    /// language, or the detected language when preferred variants are set
+    ///
+    /// # Errors
+    /// Errors when preferred variants are set and `language` is not `auto`, since the grammar
+    /// server only applies them to detected languages
+    fn request_form(
+        &self,
+        text: &str,
+        language: Option<&str>,
+    ) -> Result<Vec<(&'static str, String)>, String> {
+        let language = match language {
+            Some(value) => value,
+            None if self.preferred_variants.is_empty() => DEFAULT_LANGUAGE,
+            None => AUTO_LANGUAGE,
+        };
+        let mut form = vec![
+            ("text", text.to_string()),
+            ("language", language.to_string()),
+            ("level", String::from("picky")),
+        ];
+        if !self.preferred_variants.is_empty() {
+            if language != AUTO_LANGUAGE {
+                return Err(format!(
+                    "Preferred variants ({}) can only be used with language `{AUTO_LANGUAGE}`, not `{language}`",
+                    self.preferred_variants.join(", ")
+                ));
+            }
+            form.push(("preferredVariants", self.preferred_variants.join(",")));
+        }
+        if !self.disabled_categories.is_empty() {
+            form.push(("disabledCategories", self.disabled_categories.join(",")));
+        }
+        if let Some((username, api_key)) = &self.api_credentials {
+            form.push(("username", username.clone()));
+            form.push(("apiKey", api_key.clone()));
+        }
+        Ok(form)
+    }
+
+    /// Adds matches from `response`, the grammar server reply for checking `text`, to `results`,
+    /// with positions converted from UTF-16 code units, which the server counts, to bytes
     fn process_language_tools_results(
+        &self,
+        text: &str,
         response: &LanguageToolsCheckResponse,
         results: &mut Vec<CheckResult>,
     ) {
         let LanguageToolsCheckResponse {
+            language,
             matches,
             sentence_ranges,
             ..
         } = response;
+        let LanguageToolsCheckResponseDetectedLanguage {
+            name,
+            code,
+            confidence,
+            ..
+        } = &language.detected_language;
+        if *confidence < self.min_language_confidence {
+            self.warn(format!(
+                "Detected language {name} ({code}) with low confidence ({confidence:.2}), text may be mixed-language or misdetected"
+            ));
+        }
 
         for results_match in matches {
             let LanguageToolsCheckResponseMatch {
                 context,
                 message,
+                offset: text_offset,
+                length: text_length,
                 replacements,
+                rule,
                 sentence,
                 short_message,
                 ..
             } = &results_match;
-            let LanguageToolsCheckResponseMatchContext {
-                length,
-                offset,
-                text,
-            } = context;
-            let replacements = if replacements.len() < 5 {
-                replacements
-            } else {
-                &replacements[0..5]
+
+            // The server should already skip these, but filter in case any slip through
+            if self.disabled_categories.contains(&rule.category.id) {
+                continue;
+            }
+            if self.ignored_rules.contains(&rule.id)
+                || self.ignored_rules.contains(&rule.category.id)
+            {
+                continue;
+            }
+            let LanguageToolsCheckResponseMatchContext { length, offset, .. } = context;
+            let context_text = &context.text;
+            let (Some(text_range), Some(context_range)) = (
+                utf16_byte_range(text, *text_offset as usize, *text_length as usize),
+                utf16_byte_range(context_text, *offset as usize, *length as usize),
+            ) else {
+                continue;
+            };
+            if rule.category.id == SPELLING_CATEGORY
+                && self
+                    .dictionary
+                    .contains(&context_text[context_range.clone()])
+            {
+                continue;
+            }
+            let (Ok(context_offset), Ok(context_length)) = (
+                u32::try_from(context_range.start),
+                u32::try_from(context_range.len()),
+            ) else {
+                continue;
             };
+            let replacements_all = replacements;
+            let replacements = &replacements[..replacements.len().min(self.replacement_limit)];
             let replacements_vec = replacements
                 .iter()
                 .map(|val| {
@@ -261,16 +851,26 @@ impl Checker<'_> {
                 &serde_json::to_string_pretty(&results_match).unwrap()
             );
             results.push(CheckResult {
-                context_length: *length,
-                context_offset: *offset,
-                message: message.to_string(),
-                short_message: short_message.to_string(),
-                text: text.to_string(),
+                category_id: rule.category.id.clone(),
+                context_length,
+                context_offset,
+                message: message.clone(),
+                rule_id: rule.id.clone(),
+                short_message: short_message.clone(),
+                text: context_text.clone(),
+                text_offset: text_range.start,
+                text_length: text_range.len(),
+                source_range: None,
                 replacements: replacements_vec
                     .iter()
                     .map(std::string::ToString::to_string)
                     .collect(),
-                sentence: sentence.to_string(),
+                sentence: sentence.clone(),
+                sole_replacement: match &replacements_all[..] {
+                    [LanguageToolsCheckResponseMatchReplacement { value }] => Some(value.clone()),
+                    _ => None,
+                },
+                alt_text: false,
             });
         }
         trace!(
@@ -279,26 +879,46 @@ impl Checker<'_> {
         );
     }
 
+    /// Checks `text` with the grammar server, in `language` if given, otherwise using the server's
+    /// language detection
+    ///
+    /// # Errors
+    /// Returns an error if the grammar server cannot be reached or its response is not valid
+    ///
+    /// # Panics
+    /// Panics if the request accept header is not valid
     pub async fn check_chunk(
         &self,
         text: &str,
+        language: Option<&str>,
     ) -> Result<Vec<CheckResult>, Box<dyn std::error::Error>> {
         let mut results = Vec::new();
-        let client = reqwest::Client::new();
         let mut headers = HeaderMap::new();
         headers.insert(
             ACCEPT,
             HeaderValue::from_str("application/json").expect("Expected valid accept header value"),
         );
-        let mut body_data_map = HashMap::new();
-        body_data_map.insert("text", text);
-        body_data_map.insert("language", "en-GB");
-        body_data_map.insert("level", "picky");
+        let form = match self.request_form(text, language) {
+            Ok(value) => value,
+            Err(message) => {
+                eprintln!("[ ERROR ] {message}.");
+                return Err(message.into());
+            }
+        };
+        if !self.take_budgeted_request() {
+            return Ok(results);
+        }
 
-        let languagetool_response_data = match client
-            .post(self.url)
+        // Hold a permit, when limiting is configured, until the response body is read
+        let _permit = match &self.request_permits {
+            Some(request_permits) => Some(request_permits.acquire().await?),
+            None => None,
+        };
+        let languagetool_response_data = match self
+            .client
+            .post(&self.url)
             .headers(headers)
-            .form(&body_data_map)
+            .form(&form)
             .send()
             .await
         {
@@ -311,16 +931,172 @@ impl Checker<'_> {
                     );
                         return Err(error.into());
                     }
-                    eprintln!("[ ERROR ] error parsing remote grammar server response: {error:?}.",);
+                    eprintln!("[ ERROR ] error parsing remote grammar server response: {error:?}.");
                     return Err(error.into());
                 }
             },
             Err(error) => {
-                eprintln!("[ ERROR ] no response from remote grammar check server: {error:?}.",);
+                eprintln!("[ ERROR ] no response from remote grammar check server: {error:?}.");
                 return Err(error.into());
             }
         };
-        Self::process_language_tools_results(&languagetool_response_data, &mut results);
+        self.process_language_tools_results(text, &languagetool_response_data, &mut results);
         Ok(results)
     }
 }
+
+/// Byte range in `text` of the `length` UTF-16 code units starting `offset` units in, or `None`
+/// if the range does not fall on character boundaries within `text`
+fn utf16_byte_range(text: &str, offset: usize, length: usize) -> Option<Range<usize>> {
+    let byte_offset = |text: &str, units: usize| {
+        let mut counted = 0;
+        for (index, character) in text.char_indices() {
+            if counted >= units {
+                return (counted == units).then_some(index);
+            }
+            counted += character.len_utf16();
+        }
+        (counted == units).then_some(text.len())
+    };
+    let start = byte_offset(text, offset)?;
+    let end = start + byte_offset(&text[start..], length)?;
+    Some(start..end)
+}
+
+/// Line and column in `source` of each result's flagged text, from its source range. Results
+/// whose flagged text could not be mapped back to the source have no position.
+pub fn source_positions(results: &[CheckResult], source: &str) -> Vec<Option<SourcePosition>> {
+    results
+        .iter()
+        .map(|result| {
+            let preceding = source.get(..result.source_range.as_ref()?.start)?;
+            let line_start = preceding.rfind('\n').map_or(0, |value| value + 1);
+            Some(SourcePosition {
+                line: preceding.matches('\n').count() + 1,
+                column: preceding[line_start..].chars().count() + 1,
+            })
+        })
+        .collect()
+}
+
+/// Spelling fixes for `source`: the source range of each result's flagged text, with its
+/// [`CheckResult::autofix_replacement`], in order. Results are skipped when the flagged text is in
+/// `dictionary`, when their source range does not hold the flagged text verbatim, for example
+/// because it spans markup, or when it overlaps any of `protected_ranges`, such as code, or an
+/// earlier fix.
+pub fn autofixes<'a, S: ::std::hash::BuildHasher>(
+    results: &'a [CheckResult],
+    source: &str,
+    protected_ranges: &[Range<usize>],
+    dictionary: &HashSet<String, S>,
+) -> Vec<(Range<usize>, &'a str)> {
+    let mut fixes: Vec<(Range<usize>, &str)> = results
+        .iter()
+        .filter_map(|result| {
+            let replacement = result.autofix_replacement()?;
+            let range = result.source_range()?;
+            let flagged_text = source.get(range.clone())?;
+            (!flagged_text.is_empty()
+                && result.flagged_text() == Some(flagged_text)
+                && !dictionary.contains(flagged_text)
+                && !protected_ranges
+                    .iter()
+                    .any(|protected| protected.start < range.end && range.start < protected.end))
+            .then_some((range, replacement))
+        })
+        .collect();
+    fixes.sort_by_key(|(range, _)| range.start);
+    fixes.dedup_by(|(range, _), (previous, _)| range.start < previous.end);
+    fixes
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonReportResult {
+    path: String,
+    line: Option<usize>,
+    column: Option<usize>,
+    rule_id: String,
+    category_id: String,
+    message: String,
+    short_message: String,
+    sentence: String,
+    replacements: Vec<String>,
+    alt_text: bool,
+
+    /// Byte offset of the flagged text in the markdown source, frontmatter included, when it
+    /// could be mapped back from the plaintext checked
+    text_offset: Option<usize>,
+    text_length: Option<usize>,
+}
+
+/// Results as a JSON array, with positions from [`source_positions`]
+pub fn json_report(
+    results: &[CheckResult],
+    positions: &[Option<SourcePosition>],
+    path: &str,
+) -> String {
+    let report: Vec<JsonReportResult> = results
+        .iter()
+        .zip(positions)
+        .map(|(result, position)| JsonReportResult {
+            path: path.to_string(),
+            line: position.map(|value| value.line),
+            column: position.map(|value| value.column),
+            rule_id: result.rule_id().to_string(),
+            category_id: result.category_id.clone(),
+            message: result.message.clone(),
+            short_message: result.short_message.clone(),
+            sentence: result.sentence.clone(),
+            replacements: result.replacements.clone(),
+            alt_text: result.alt_text,
+            text_offset: result.source_range.as_ref().map(|range| range.start),
+            text_length: result.source_range.as_ref().map(Range::len),
+        })
+        .collect();
+    serde_json::to_string_pretty(&report).expect("Expected grammar results to serialise")
+}
+
+/// Results as a SARIF 2.1.0 log, with positions from [`source_positions`]
+pub fn sarif_report(
+    results: &[CheckResult],
+    positions: &[Option<SourcePosition>],
+    path: &str,
+) -> String {
+    let sarif_results: Vec<serde_json::Value> = results
+        .iter()
+        .zip(positions)
+        .map(|(result, position)| {
+            let mut physical_location = serde_json::json!({
+                "artifactLocation": { "uri": path }
+            });
+            if let Some(SourcePosition { line, column }) = position {
+                physical_location["region"] = serde_json::json!({
+                    "startLine": line,
+                    "startColumn": column,
+                });
+            }
+            serde_json::json!({
+                "ruleId": result.rule_id(),
+                "level": "warning",
+                "message": { "text": result.message },
+                "locations": [{ "physicalLocation": physical_location }],
+                "properties": { "category": result.category_id, "altText": result.alt_text },
+            })
+        })
+        .collect();
+    let report = serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }
+            },
+            "results": sarif_results,
+        }],
+    });
+    serde_json::to_string_pretty(&report).expect("Expected grammar results to serialise")
+}
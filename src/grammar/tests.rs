@@ -1,15 +1,51 @@
-use crate::grammar::CheckResult as GrammarCheckResult;
+use crate::{
+    grammar::{
+        autofixes, check_language_supported, grammar_check_chunks, json_report, sarif_report,
+        source_positions, CheckResult as GrammarCheckResult, Checker as GrammarChecker,
+        JsonReportResult, LanguageInfo, LanguageToolsCheckResponse, SentenceSegmentation,
+        SourcePosition,
+    },
+    load_grammar_ignore_file,
+    markdown::{parse_markdown_to_plaintext_regions, ParseMarkdownOptions},
+    DEFAULT_SENTENCE_TERMINATORS,
+};
+use std::{
+    collections::HashSet,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use tokio::sync::Semaphore;
+
+/// Text checked for the `LanguageTool` response fixture
+const CHECKED_TEXT: &str = "The quick brown foox jumps over the lazy dog...";
+
+fn languagetool_response() -> LanguageToolsCheckResponse {
+    serde_json::from_str(include_str!("../../fixtures/languagetool_response.json"))
+        .expect("Expected valid LanguageTool response fixture")
+}
 
 #[test]
 fn test_context() {
     //arrange
     let grammar_check_result = GrammarCheckResult {
+        category_id: "TYPOS".into(),
         context_length: 4,
         context_offset: 16,
         message: "Possible spelling mistake found.".into(),
+        rule_id: "MORFOLOGIK_RULE_EN_GB".into(),
+        alt_text: false,
         sentence: "The quick brown foox jumps over the lazy dog".into(),
         short_message: "Spelling mistake".into(),
         text: "The quick brown foox jumps over the lazy dog".into(),
+        text_offset: 16,
+        text_length: 4,
+        source_range: None,
         replacements: vec![
             "food".into(),
             "foot".into(),
@@ -17,6 +53,7 @@ fn test_context() {
             "fox".into(),
             "foo".into(),
         ],
+        sole_replacement: None,
     };
 
     // act
@@ -26,3 +63,713 @@ fn test_context() {
     let expected = "The quick brown \u{1b}[94mfoox\u{1b}[39m jumps over the lazy dog";
     assert_eq!(result, expected);
 }
+
+#[test]
+fn request_form_includes_disabled_categories() {
+    // arrange
+    let mut checker = GrammarChecker::new(None);
+    checker.disabled_categories(vec!["TYPOGRAPHY".into(), "CASING".into()]);
+
+    // act
+    let result = checker
+        .request_form("Some text.", None)
+        .expect("Expected valid request form");
+
+    // assert
+    assert!(result.contains(&("disabledCategories", String::from("TYPOGRAPHY,CASING"))));
+    assert!(result.contains(&("text", String::from("Some text."))));
+}
+
+#[test]
+fn request_form_includes_api_credentials_when_set() {
+    // arrange
+    let mut checker = GrammarChecker::new(None);
+    checker.api_credentials("writer@example.com", "secret-key");
+
+    // act
+    let result = checker
+        .request_form("Some text.", None)
+        .expect("Expected valid request form");
+
+    // assert
+    assert!(result.contains(&("username", String::from("writer@example.com"))));
+    assert!(result.contains(&("apiKey", String::from("secret-key"))));
+}
+
+#[test]
+fn request_form_omits_disabled_categories_by_default() {
+    // arrange
+    let checker = GrammarChecker::new(None);
+
+    // act
+    let result = checker
+        .request_form("Some text.", None)
+        .expect("Expected valid request form");
+
+    // assert
+    assert!(!result.iter().any(|(key, _)| *key == "disabledCategories"));
+}
+
+#[test]
+fn request_form_uses_language_set_by_marker_for_following_region() {
+    // arrange
+    let checker = GrammarChecker::new(None);
+    let markdown = "An English introduction.
+
+<!-- markwrite:lang=fr -->
+
+> Une citation en français.
+";
+    let regions = parse_markdown_to_plaintext_regions(markdown, &ParseMarkdownOptions::default());
+
+    // act
+    let forms = regions
+        .iter()
+        .map(|region| {
+            checker
+                .request_form(region.text(), region.language())
+                .expect("Expected valid request form")
+        })
+        .collect::<Vec<_>>();
+
+    // assert
+    assert_eq!(forms.len(), 2);
+    assert!(forms[0].contains(&("text", String::from("An English introduction.\n"))));
+    assert!(forms[0].contains(&("language", String::from("en-GB"))));
+    assert!(forms[1].contains(&("text", String::from("Une citation en français.\n"))));
+    assert!(forms[1].contains(&("language", String::from("fr"))));
+}
+
+#[test]
+fn process_language_tools_results_filters_disabled_categories() {
+    // arrange
+    let mut checker = GrammarChecker::new(None);
+    checker.disabled_categories(vec!["TYPOGRAPHY".into()]);
+    let response = languagetool_response();
+    let mut results = Vec::new();
+
+    // act
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+
+    // assert
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].category_id(), "TYPOS");
+}
+
+#[test]
+fn process_language_tools_results_drops_rules_listed_in_ignore_file() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    let ignore_path = temp_dir.path().join(".markwrite-grammar-ignore");
+    std::fs::write(&ignore_path, "# House style uses three dots\nELLIPSIS\n\n")
+        .expect("Error writing ignore file");
+    let mut checker = GrammarChecker::new(None);
+    checker.ignored_rules(load_grammar_ignore_file(&ignore_path).expect("Expected ignore file"));
+    let response = languagetool_response();
+    let mut results = Vec::new();
+
+    // act
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+
+    // assert
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].rule_id(), "MORFOLOGIK_RULE_EN_GB");
+}
+
+#[test]
+fn process_language_tools_results_applies_replacement_limit() {
+    // arrange
+    let mut checker = GrammarChecker::new(None);
+    checker.replacement_limit(2);
+    let response = languagetool_response();
+    let mut results = Vec::new();
+
+    // act
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+
+    // assert
+    assert_eq!(results[0].replacements, vec!["food", "foot"]);
+}
+
+#[test]
+fn replacements_string_is_none_when_replacement_limit_is_zero() {
+    // arrange
+    let mut checker = GrammarChecker::new(None);
+    checker.replacement_limit(0);
+    let response = languagetool_response();
+    let mut results = Vec::new();
+
+    // act
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+
+    // assert
+    assert!(results
+        .iter()
+        .all(|val| val.replacements_string().is_none()));
+}
+
+#[test]
+fn process_language_tools_results_warns_on_low_language_confidence() {
+    // arrange
+    let checker = GrammarChecker::new(None);
+    let mut response = languagetool_response();
+    response.language.detected_language.confidence = 0.32;
+    let mut results = Vec::new();
+
+    // act
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+
+    // assert
+    assert_eq!(
+        checker.take_warnings(),
+        vec![String::from(
+            "Detected language English (GB) (en-GB) with low confidence (0.32), text may be mixed-language or misdetected"
+        )]
+    );
+}
+
+#[test]
+fn process_language_tools_results_does_not_warn_on_high_language_confidence() {
+    // arrange
+    let checker = GrammarChecker::new(None);
+    let response = languagetool_response();
+    let mut results = Vec::new();
+
+    // act
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+
+    // assert
+    assert!(checker.take_warnings().is_empty());
+}
+
+#[test]
+fn process_language_tools_results_converts_utf16_offsets_to_bytes() {
+    // arrange
+    let checker = GrammarChecker::new(None);
+    let text = "Un café 😀 foox ici.";
+    let mut response = languagetool_response();
+    response.matches.truncate(1);
+    response.matches[0].offset = 11;
+    response.matches[0].context.text = text.into();
+    response.matches[0].context.offset = 11;
+    let mut results = Vec::new();
+
+    // act
+    checker.process_language_tools_results(text, &response, &mut results);
+
+    // assert
+    assert_eq!(results[0].text_offset(), 14);
+    assert_eq!(results[0].text_length(), 4);
+    assert_eq!(results[0].flagged_text(), Some("foox"));
+}
+
+#[test]
+fn source_positions_gives_line_and_column_of_source_ranges() {
+    // arrange
+    let checker = GrammarChecker::new(None);
+    let response = languagetool_response();
+    let mut results = Vec::new();
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+    let source =
+        "---\ntitle: foox\n---\n\n# Animals\n\nThe quick brown foox jumps over\nthe lazy dog...\n";
+    let foox_start = source
+        .find("foox jumps")
+        .expect("Expected flagged text in source");
+    let ellipsis_start = source.find("...").expect("Expected flagged text in source");
+    results[0].set_source_range(Some(foox_start..foox_start + 4));
+    results[1].set_source_range(Some(ellipsis_start..ellipsis_start + 3));
+
+    // act
+    let result = source_positions(&results, source);
+
+    // assert
+    assert_eq!(
+        result,
+        vec![
+            Some(SourcePosition {
+                line: 7,
+                column: 17
+            }),
+            Some(SourcePosition {
+                line: 8,
+                column: 13
+            }),
+        ]
+    );
+}
+
+#[test]
+fn source_positions_are_none_for_results_without_source_ranges() {
+    // arrange
+    let checker = GrammarChecker::new(None);
+    let response = languagetool_response();
+    let mut results = Vec::new();
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+
+    // act
+    let result = source_positions(
+        &results,
+        "The quick brown foox jumps over the lazy dog...\n",
+    );
+
+    // assert
+    assert_eq!(result, vec![None, None]);
+}
+
+#[test]
+fn autofixes_skip_dictionary_words_and_protected_ranges() {
+    // arrange
+    let checker = GrammarChecker::new(None);
+    let mut response = languagetool_response();
+    response.matches.truncate(1);
+    response.matches[0].replacements.truncate(1);
+    let mut results = Vec::new();
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+    let source = "# Animals\n\nThe quick brown foox jumps over the lazy dog...\n";
+    let foox_start = source
+        .find("foox")
+        .expect("Expected flagged text in source");
+    results[0].set_source_range(Some(foox_start..foox_start + 4));
+    let dictionary = HashSet::from([String::from("foox")]);
+    let code_range = foox_start - 1..foox_start + 1;
+
+    // act
+    let fixes = autofixes(&results, source, &[], &HashSet::new());
+    let dictionary_fixes = autofixes(&results, source, &[], &dictionary);
+    let protected_fixes = autofixes(&results, source, &[code_range], &HashSet::new());
+
+    // assert
+    assert_eq!(fixes, vec![(foox_start..foox_start + 4, "food")]);
+    assert!(dictionary_fixes.is_empty());
+    assert!(protected_fixes.is_empty());
+}
+
+#[test]
+fn json_report_deserialises_to_results_with_messages() {
+    // arrange
+    let checker = GrammarChecker::new(None);
+    let response = languagetool_response();
+    let mut results = Vec::new();
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+    let positions = vec![None; results.len()];
+
+    // act
+    let report = json_report(&results, &positions, "post.md");
+
+    // assert
+    let result: Vec<JsonReportResult> =
+        serde_json::from_str(&report).expect("Expected valid JSON report");
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].message, "Possible spelling mistake found.");
+    assert_eq!(result[0].rule_id, "MORFOLOGIK_RULE_EN_GB");
+    assert_eq!(
+        result[1].message,
+        "Consider using the typographical ellipsis character here instead."
+    );
+    assert_eq!(result[1].path, "post.md");
+    assert_eq!(result[1].line, None);
+}
+
+#[test]
+fn sarif_report_includes_rule_and_location() {
+    // arrange
+    let checker = GrammarChecker::new(None);
+    let response = languagetool_response();
+    let mut results = Vec::new();
+    checker.process_language_tools_results(CHECKED_TEXT, &response, &mut results);
+    let positions = vec![
+        Some(SourcePosition {
+            line: 3,
+            column: 17,
+        }),
+        None,
+    ];
+
+    // act
+    let report = sarif_report(&results, &positions, "post.md");
+
+    // assert
+    let result: serde_json::Value =
+        serde_json::from_str(&report).expect("Expected valid SARIF report");
+    assert_eq!(result["version"], "2.1.0");
+    let sarif_results = &result["runs"][0]["results"];
+    assert_eq!(sarif_results.as_array().map(Vec::len), Some(2));
+    assert_eq!(sarif_results[0]["ruleId"], "MORFOLOGIK_RULE_EN_GB");
+    let location = &sarif_results[0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "post.md");
+    assert_eq!(location["region"]["startLine"], 3);
+    assert_eq!(location["region"]["startColumn"], 17);
+    assert!(sarif_results[1]["locations"][0]["physicalLocation"]["region"].is_null());
+}
+
+/// Reads one HTTP request, returning the request line
+fn read_http_request(reader: &mut BufReader<TcpStream>) -> String {
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .expect("Error reading request");
+    let mut content_length = 0;
+    let mut line = String::new();
+    while reader.read_line(&mut line).expect("Error reading request") > 0 && line != "\r\n" {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().expect("Expected valid content length");
+            }
+        }
+        line.clear();
+    }
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .expect("Error reading request body");
+    request_line
+}
+
+fn write_json_response(stream: &mut TcpStream, response_body: &str) {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response_body}",
+        response_body.len()
+    )
+    .expect("Error writing response");
+}
+
+/// Reads one HTTP request, then, after a short delay, replies with the response fixture, tracking
+/// the peak number of requests handled at once
+fn respond_to_grammar_request(
+    stream: TcpStream,
+    in_flight: &AtomicUsize,
+    max_in_flight: &AtomicUsize,
+) {
+    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+    max_in_flight.fetch_max(current, Ordering::SeqCst);
+
+    let mut reader = BufReader::new(stream);
+    read_http_request(&mut reader);
+
+    thread::sleep(Duration::from_millis(50));
+    let response_body = include_str!("../../fixtures/languagetool_response.json");
+    in_flight.fetch_sub(1, Ordering::SeqCst);
+    write_json_response(reader.get_mut(), response_body);
+}
+
+#[tokio::test]
+async fn supported_languages_returns_languages_listed_by_server() {
+    // arrange
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Error binding test server");
+    let url = format!(
+        "http://{}/v2/check",
+        listener.local_addr().expect("Expected test server address")
+    );
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("Error accepting connection");
+        let mut reader = BufReader::new(stream);
+        let request_line = read_http_request(&mut reader);
+        let response_body = include_str!("../../fixtures/languagetool_languages.json");
+        write_json_response(reader.get_mut(), response_body);
+        request_line
+    });
+    let checker = GrammarChecker::new(Some(&url));
+
+    // act
+    let result = checker
+        .supported_languages()
+        .await
+        .expect("Expected supported languages");
+
+    // assert
+    let request_line = server.join().expect("Error joining test server");
+    assert!(request_line.starts_with("GET /v2/languages "));
+    assert_eq!(result.len(), 4);
+    assert_eq!(result[0].name(), "English (GB)");
+    assert_eq!(result[0].code(), "en");
+    assert_eq!(result[0].long_code(), "en-GB");
+    assert_eq!(result[2].long_code(), "fr");
+}
+
+#[test]
+fn check_language_supported_accepts_short_and_long_codes() {
+    // arrange
+    let supported_languages: Vec<LanguageInfo> =
+        serde_json::from_str(include_str!("../../fixtures/languagetool_languages.json"))
+            .expect("Expected valid languages fixture");
+
+    // act
+    let long_code_result = check_language_supported("en-US", &supported_languages);
+    let short_code_result = check_language_supported("de", &supported_languages);
+    let auto_result = check_language_supported("auto", &supported_languages);
+
+    // assert
+    assert_eq!(long_code_result, Ok(()));
+    assert_eq!(short_code_result, Ok(()));
+    assert_eq!(auto_result, Ok(()));
+}
+
+#[test]
+fn check_language_supported_lists_supported_languages_on_error() {
+    // arrange
+    let supported_languages: Vec<LanguageInfo> =
+        serde_json::from_str(include_str!("../../fixtures/languagetool_languages.json"))
+            .expect("Expected valid languages fixture");
+
+    // act
+    let result = check_language_supported("xx-YY", &supported_languages);
+
+    // assert
+    assert_eq!(
+        result,
+        Err(String::from(
+            "Grammar server does not support language `xx-YY`, supported languages: de-DE, en-GB, en-US, fr"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn check_chunk_requests_never_overlap_with_single_permit() {
+    // arrange
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Error binding test server");
+    let url = format!(
+        "http://{}/v2/check",
+        listener.local_addr().expect("Expected test server address")
+    );
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    {
+        let in_flight = Arc::clone(&in_flight);
+        let max_in_flight = Arc::clone(&max_in_flight);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let in_flight = Arc::clone(&in_flight);
+                let max_in_flight = Arc::clone(&max_in_flight);
+                thread::spawn(move || {
+                    respond_to_grammar_request(stream, &in_flight, &max_in_flight);
+                });
+            }
+        });
+    }
+    let request_permits = Arc::new(Semaphore::new(1));
+    let mut checker_a = GrammarChecker::new(Some(&url));
+    checker_a.request_permits(Arc::clone(&request_permits));
+    let mut checker_b = GrammarChecker::new(Some(&url));
+    checker_b.request_permits(Arc::clone(&request_permits));
+
+    // act
+    let (result_a, result_b, result_c) = tokio::join!(
+        checker_a.check_chunk(CHECKED_TEXT, None),
+        checker_b.check_chunk(CHECKED_TEXT, None),
+        checker_a.check_chunk(CHECKED_TEXT, None),
+    );
+
+    // assert
+    assert_eq!(result_a.expect("Expected grammar check result").len(), 2);
+    assert_eq!(result_b.expect("Expected grammar check result").len(), 2);
+    assert_eq!(result_c.expect("Expected grammar check result").len(), 2);
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn request_form_includes_preferred_variants_with_auto_language() {
+    // arrange
+    let mut checker = GrammarChecker::new(None);
+    checker.preferred_variants(vec!["en-US".into(), "de-AT".into()]);
+
+    // act
+    let default_result = checker
+        .request_form("Some text.", None)
+        .expect("Expected valid request form");
+    let auto_result = checker
+        .request_form("Some text.", Some("auto"))
+        .expect("Expected valid request form");
+
+    // assert
+    for result in [default_result, auto_result] {
+        assert!(result.contains(&("language", String::from("auto"))));
+        assert!(result.contains(&("preferredVariants", String::from("en-US,de-AT"))));
+    }
+}
+
+#[test]
+fn request_form_rejects_preferred_variants_with_concrete_language() {
+    // arrange
+    let mut checker = GrammarChecker::new(None);
+    checker.preferred_variants(vec!["en-US".into()]);
+
+    // act
+    let result = checker.request_form("Une phrase.", Some("fr"));
+
+    // assert
+    assert_eq!(
+        result,
+        Err(String::from(
+            "Preferred variants (en-US) can only be used with language `auto`, not `fr`"
+        ))
+    );
+}
+
+#[test]
+fn grammar_check_chunks_keeps_final_sentence_stub_in_single_chunk() {
+    // arrange
+    let text = "The first sentence is complete. The second one trails off without";
+
+    // act
+    let result = grammar_check_chunks(
+        text,
+        1500,
+        0,
+        SentenceSegmentation::Heuristic,
+        &DEFAULT_SENTENCE_TERMINATORS,
+    );
+
+    // assert
+    assert_eq!(result, vec![(0, text)]);
+}
+
+#[test]
+fn grammar_check_chunks_trims_all_but_last_chunk() {
+    // arrange
+    let text = "One short sentence. Another short sentence. The last one trails off";
+
+    // act
+    let result = grammar_check_chunks(
+        text,
+        30,
+        0,
+        SentenceSegmentation::Heuristic,
+        &DEFAULT_SENTENCE_TERMINATORS,
+    );
+
+    // assert
+    assert_eq!(
+        result,
+        vec![
+            (0, "One short sentence. "),
+            (20, "Another short sentence. "),
+            (44, "The last one trails off")
+        ]
+    );
+}
+
+#[test]
+fn grammar_check_chunks_repeats_overlap_sentences() {
+    // arrange
+    let text =
+        "One. Two. Three. Four is here. Five is, e.g. also here. Six is next. The last trails off";
+
+    // act
+    let result = grammar_check_chunks(
+        text,
+        80,
+        2,
+        SentenceSegmentation::Heuristic,
+        &DEFAULT_SENTENCE_TERMINATORS,
+    );
+
+    // assert
+    assert_eq!(
+        result,
+        vec![
+            (
+                0,
+                "One. Two. Three. Four is here. Five is, e.g. also here. Six is next. "
+            ),
+            (
+                31,
+                "Five is, e.g. also here. Six is next. The last trails off"
+            )
+        ]
+    );
+}
+
+#[test]
+fn grammar_check_chunks_ends_chunks_on_segmented_sentence_ends() {
+    // arrange
+    let text = "The report by J. R. Smith arrived late. She said \"Read it!\" Everyone agreed. \
+        It was long (over ninety pages.) The ending trails off";
+
+    // act
+    let result = grammar_check_chunks(
+        text,
+        60,
+        0,
+        SentenceSegmentation::Local,
+        &DEFAULT_SENTENCE_TERMINATORS,
+    );
+
+    // assert
+    assert_eq!(
+        result,
+        vec![
+            (
+                0,
+                "The report by J. R. Smith arrived late. She said \"Read it!\" "
+            ),
+            (60, "Everyone agreed. It was long (over ninety pages.) "),
+            (110, "The ending trails off")
+        ]
+    );
+}
+
+#[test]
+fn grammar_check_chunks_splits_text_with_no_terminators_at_chunk_size() {
+    // arrange
+    let text = "abcdéfghi ".repeat(300);
+
+    // act
+    let result = grammar_check_chunks(
+        &text,
+        1500,
+        2,
+        SentenceSegmentation::Heuristic,
+        &DEFAULT_SENTENCE_TERMINATORS,
+    );
+    let local_result = grammar_check_chunks(
+        &text,
+        1500,
+        2,
+        SentenceSegmentation::Local,
+        &DEFAULT_SENTENCE_TERMINATORS,
+    );
+
+    // assert
+    assert_eq!(text.chars().count(), 3000);
+    assert_eq!(result, local_result);
+    assert_eq!(result.len(), 3);
+    let mut covered = 0;
+    for (offset, chunk) in result {
+        assert_eq!(offset, covered);
+        assert!(chunk.len() <= 1500);
+        covered += chunk.len();
+    }
+    assert_eq!(covered, text.len());
+}
+
+#[test]
+fn grammar_check_chunks_splits_at_full_width_terminators() {
+    // arrange
+    let text = "今日は晴れです。明日は雨ですか？はい、そうです。";
+
+    // act
+    let result = grammar_check_chunks(
+        text,
+        40,
+        0,
+        SentenceSegmentation::Heuristic,
+        &DEFAULT_SENTENCE_TERMINATORS,
+    );
+    let local_result = grammar_check_chunks(
+        text,
+        40,
+        0,
+        SentenceSegmentation::Local,
+        &DEFAULT_SENTENCE_TERMINATORS,
+    );
+
+    // assert
+    let expected = vec![
+        (0, "今日は晴れです。"),
+        (24, "明日は雨ですか？"),
+        (48, "はい、そうです。"),
+    ];
+    assert_eq!(result, expected);
+    assert_eq!(local_result, expected);
+}
@@ -26,9 +26,16 @@
 mod tests;
 
 mod dom;
-use crate::url_utility::relative_url;
+mod pretty;
+mod responsive;
+use crate::{
+    url_utility::{external_url, relative_url},
+    utilities::glob::glob_match,
+};
 use aho_corasick::AhoCorasickBuilder;
-use dom::{Handle, Node, NodeData, RcDom, SerializableHandle};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use dom::SerializableHandle;
+pub use dom::{Handle, Node, NodeData, RcDom};
 use html5ever::{
     driver,
     interface::tree_builder::{AppendNode, NodeOrText, TreeSink},
@@ -37,28 +44,342 @@ use html5ever::{
     tendril::{format_tendril, StrTendril, TendrilSink},
     Attribute, QualName,
 };
+pub use pretty::pretty_print_html;
+pub use responsive::{DownscaleImages, ResponsiveImages, RESPONSIVE_IMAGE_SIZES};
 use std::{
     cell::RefCell,
     fmt::{self, Display},
-    mem,
+    fs, mem,
+    path::{Path, PathBuf},
     rc::Rc,
+    sync::Arc,
 };
 
+/// Transformation of the processed DOM, such as adding classes, supplied by an embedder. Hooks run
+/// after all the built-in processing passes, in the order they were added, and before the DOM is
+/// serialized.
+#[derive(Clone)]
+pub struct DomHook(Arc<dyn Fn(&RcDom) + Send + Sync>);
+
+impl DomHook {
+    pub fn new<F: Fn(&RcDom) + Send + Sync + 'static>(hook: F) -> Self {
+        DomHook(Arc::new(hook))
+    }
+}
+
+impl fmt::Debug for DomHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DomHook")
+    }
+}
+
+/// Configuration for rewriting relative links to local markdown files so they point to the
+/// corresponding HTML output
+#[derive(Clone, Debug)]
+pub struct InternalLinks {
+    /// Directory relative link targets are resolved against
+    base_path: PathBuf,
+
+    /// Links are only rewritten when their target resolves within this directory
+    project_root: PathBuf,
+
+    /// Globs for link paths rewritten whether or not their target exists locally
+    patterns: Vec<String>,
+}
+
+impl InternalLinks {
+    pub fn new<P1: AsRef<Path>, P2: AsRef<Path>>(base_path: P1, project_root: P2) -> Self {
+        InternalLinks {
+            base_path: base_path.as_ref().to_path_buf(),
+            project_root: project_root.as_ref().to_path_buf(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Also rewrite links whose path, without any leading `./` or `/`, matches one of the globs
+    /// in `value`, such as `docs/**/*.md`, even when the target is not a local file
+    pub fn patterns(mut self, value: &[String]) -> Self {
+        self.patterns = value.to_vec();
+        self
+    }
+
+    /// Path and suffix of `href`, split at any query or fragment, when it is a relative link to a
+    /// markdown file, which may be rewritten
+    fn markdown_link(href: &str) -> Option<(&str, &str)> {
+        let pathname_end = href.find(['?', '#']).unwrap_or(href.len());
+        let (pathname, suffix) = href.split_at(pathname_end);
+        let stem = pathname
+            .strip_suffix(".md")
+            .or_else(|| pathname.strip_suffix(".MD"))?;
+        if stem.is_empty() || !relative_url(pathname) {
+            return None;
+        }
+        Some((pathname, suffix))
+    }
+
+    /// Whether `pathname`, without any leading `./` or `/`, matches one of the patterns
+    fn matches_pattern(&self, pathname: &str) -> bool {
+        let link_path = pathname.trim_start_matches("./").trim_start_matches('/');
+        self.patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, link_path))
+    }
+
+    /// Whether rewriting `href` depends on whether its target file exists, so output linking to it
+    /// can change while the document itself does not
+    pub fn depends_on_target(&self, href: &str) -> bool {
+        Self::markdown_link(href).is_some_and(|(pathname, _)| !self.matches_pattern(pathname))
+    }
+
+    /// Returns `href` with a `.md` target swapped for `.html`, keeping any query and fragment, if
+    /// the target is an existing file within the project root, or the path matches a pattern
+    fn rewrite(&self, href: &str) -> Option<String> {
+        let (pathname, suffix) = Self::markdown_link(href)?;
+        let stem = &pathname[..pathname.len() - ".md".len()];
+        if self.matches_pattern(pathname) {
+            return Some(format!("{stem}.html{suffix}"));
+        }
+        let target_path = if let Some(root_relative_pathname) = pathname.strip_prefix('/') {
+            self.project_root.join(root_relative_pathname)
+        } else {
+            self.base_path.join(pathname)
+        };
+        let target_path = fs::canonicalize(target_path).ok()?;
+        let project_root = fs::canonicalize(&self.project_root).ok()?;
+        if !target_path.is_file() || !target_path.starts_with(project_root) {
+            return None;
+        }
+        Some(format!("{stem}.html{suffix}"))
+    }
+}
+
+/// Default maximum size of a local image file to inline as a `data:` URI (1 MiB)
+pub const DEFAULT_EMBED_IMAGES_MAX_SIZE: u64 = 1_048_576;
+
+/// Configuration for inlining local images into the output as base64 `data:` URIs
+#[derive(Clone, Debug)]
+pub struct EmbedImages {
+    /// Input document directory, which relative image sources are read from
+    base_path: PathBuf,
+
+    /// Root-relative image sources, starting `/`, are read from this directory, as for links
+    project_root: PathBuf,
+
+    /// Images larger than this, in bytes, are left as links
+    max_size: u64,
+}
+
+impl EmbedImages {
+    pub fn new<P1: AsRef<Path>, P2: AsRef<Path>>(
+        base_path: P1,
+        project_root: P2,
+        max_size: u64,
+    ) -> Self {
+        EmbedImages {
+            base_path: base_path.as_ref().to_path_buf(),
+            project_root: project_root.as_ref().to_path_buf(),
+            max_size,
+        }
+    }
+}
+
+fn image_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "avif" => Some("image/avif"),
+        "bmp" => Some("image/bmp"),
+        "gif" => Some("image/gif"),
+        "ico" => Some("image/x-icon"),
+        "jpeg" | "jpg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        "svg" => Some("image/svg+xml"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// GitHub alert markers, for example `[!NOTE]`, with the class suffix and title of their callout
+const ALERT_KINDS: [(&str, &str, &str); 5] = [
+    ("NOTE", "note", "Note"),
+    ("TIP", "tip", "Tip"),
+    ("IMPORTANT", "important", "Important"),
+    ("WARNING", "warning", "Warning"),
+    ("CAUTION", "caution", "Caution"),
+];
+
+fn is_element(node: &Handle, local_name: &str) -> bool {
+    matches!(&node.data, NodeData::Element { name, .. } if &*name.local == local_name)
+}
+
+fn element(local_name: &str, attrs: Vec<Attribute>) -> Handle {
+    Node::new(NodeData::Element {
+        name: QualName::new(None, ns!(html), local_name.into()),
+        attrs: RefCell::new(attrs),
+        template_contents: RefCell::new(None),
+        mathml_annotation_xml_integration_point: false,
+    })
+}
+
+/// Adds each space separated value in `rel` to the `rel` attribute in `attrs`, keeping any values
+/// already there, or adds a `rel` attribute when there is none
+fn merge_rel_attribute(attrs: &mut Vec<Attribute>, rel: &StrTendril) {
+    let Some(attr) = attrs.iter_mut().find(|attr| &*attr.name.local == "rel") else {
+        attrs.push(Attribute {
+            name: QualName::new(None, ns!(), local_name!("rel")),
+            value: rel.clone(),
+        });
+        return;
+    };
+    let mut values: Vec<&str> = attr.value.split_ascii_whitespace().collect();
+    for value in rel.split_ascii_whitespace() {
+        if !values
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(value))
+        {
+            values.push(value);
+        }
+    }
+    let merged = values.join(" ");
+    attr.value = merged.into();
+}
+
+fn class_attribute(value: &str) -> Attribute {
+    Attribute {
+        name: QualName::new(None, ns!(), local_name!("class")),
+        value: value.into(),
+    }
+}
+
+/// For a blockquote opening with a GitHub alert marker on its own line, removes the marker and
+/// returns the callout class suffix and title
+fn take_alert_marker(blockquote: &Handle) -> Option<(&'static str, &'static str)> {
+    let mut children = blockquote.children.borrow_mut();
+    let paragraph_index = children
+        .iter()
+        .position(|child| matches!(child.data, NodeData::Element { .. }))?;
+    let paragraph = children[paragraph_index].clone();
+    if !is_element(&paragraph, "p") {
+        return None;
+    }
+    let mut paragraph_children = paragraph.children.borrow_mut();
+    let NodeData::Text { contents } = &paragraph_children.first()?.data else {
+        return None;
+    };
+    let text = contents.borrow().to_string();
+    let (marker, rest) = text.trim_start().strip_prefix("[!")?.split_once(']')?;
+    let rest = rest.trim_start_matches([' ', '\t']);
+    let marker_only = rest.is_empty() && paragraph_children.len() == 1;
+    if !marker_only && !rest.starts_with('\n') {
+        return None;
+    }
+    let (_, class_suffix, title) = ALERT_KINDS
+        .iter()
+        .find(|(kind, ..)| kind.eq_ignore_ascii_case(marker))?;
+
+    let rest = rest.trim_start();
+    if rest.is_empty() {
+        paragraph_children.remove(0);
+    } else {
+        *contents.borrow_mut() = rest.into();
+    }
+    if paragraph_children.is_empty() {
+        drop(paragraph_children);
+        children.remove(paragraph_index);
+    }
+    Some((class_suffix, title))
+}
+
+/// Prefix of comments holding markwrite directives, which are kept when stripping comments
+const DIRECTIVE_COMMENT_PREFIX: &str = "markwrite:";
+
+/// Removes comments from `node` and its descendants, keeping markwrite directive comments
+fn remove_comments(node: &Handle) {
+    node.children
+        .borrow_mut()
+        .retain(|child| match &child.data {
+            NodeData::Comment { contents } => {
+                contents.trim_start().starts_with(DIRECTIVE_COMMENT_PREFIX)
+            }
+            _ => true,
+        });
+    for child in node.children.borrow().iter() {
+        remove_comments(child);
+    }
+}
+
+/// Replaces blockquotes opening with a GitHub alert marker, such as `> [!WARNING]`, with a titled
+/// callout `div`
+fn render_alert_callouts(node: &Handle) {
+    let child_count = node.children.borrow().len();
+    for index in 0..child_count {
+        let child = node.children.borrow()[index].clone();
+        if is_element(&child, "blockquote") {
+            if let Some((class_suffix, title)) = take_alert_marker(&child) {
+                let callout = element(
+                    "div",
+                    vec![class_attribute(&format!("callout callout-{class_suffix}"))],
+                );
+                let callout_title = element("p", vec![class_attribute("callout-title")]);
+                let title_text = Node::new(NodeData::Text {
+                    contents: RefCell::new(title.into()),
+                });
+                title_text.parent.set(Some(Rc::downgrade(&callout_title)));
+                callout_title.children.borrow_mut().push(title_text);
+
+                let mut callout_children = vec![callout_title];
+                callout_children.extend(mem::take(&mut *child.children.borrow_mut()));
+                for callout_child in &callout_children {
+                    callout_child.parent.set(Some(Rc::downgrade(&callout)));
+                }
+                *callout.children.borrow_mut() = callout_children;
+                callout.parent.set(Some(Rc::downgrade(node)));
+                node.children.borrow_mut()[index] = callout.clone();
+                render_alert_callouts(&callout);
+                continue;
+            }
+        }
+        render_alert_callouts(&child);
+    }
+}
+
 #[derive(Debug)]
 pub struct Builder<'a> {
+    /// Site subdirectory, for example `/blog`, prepended to root-relative link and image URLs
+    base_path: Option<&'a str>,
     canonical_root_url: Option<&'a str>,
+    dom_hooks: &'a [DomHook],
+    downscale_images: Option<&'a DownscaleImages>,
+    embed_images: Option<&'a EmbedImages>,
+
+    /// Base URL, for example a CDN, for relative image sources, leaving relative links as they are
+    images_base_url: Option<&'a str>,
+    internal_links: Option<&'a InternalLinks>,
     link_rel: Option<&'a str>,
     link_target: Option<&'a str>,
+    responsive_images: Option<&'a ResponsiveImages>,
     search_term: Option<&'a str>,
+
+    /// Remove HTML comments, other than markwrite directives, from the output
+    strip_comments: bool,
+    warnings: RefCell<Vec<String>>,
 }
 
 impl Default for Builder<'_> {
     fn default() -> Self {
         Builder {
+            base_path: None,
             canonical_root_url: None,
+            dom_hooks: &[],
+            downscale_images: None,
+            embed_images: None,
+            images_base_url: None,
+            internal_links: None,
             link_rel: Some("noopener noreferrer"),
             link_target: Some("_blank"),
+            responsive_images: None,
             search_term: None,
+            strip_comments: false,
+            warnings: RefCell::new(Vec::new()),
         }
     }
 }
@@ -68,27 +389,175 @@ impl<'a> Builder<'a> {
         Self::default()
     }
 
+    pub fn base_path(&mut self, value: Option<&'a str>) -> &mut Self {
+        self.base_path = value;
+        self
+    }
+
+    /// Returns `url` with the base path prepended when it is root-relative, like `/about`
+    fn with_base_path(&self, url: &str) -> Option<String> {
+        let base_path = self.base_path?.trim_end_matches('/');
+        if base_path.is_empty() || !url.starts_with('/') || url.starts_with("//") {
+            return None;
+        }
+        if base_path.starts_with('/') {
+            Some(format!("{base_path}{url}"))
+        } else {
+            Some(format!("/{base_path}{url}"))
+        }
+    }
+
     pub fn canonical_root_url(&mut self, value: Option<&'a str>) -> &mut Self {
         self.canonical_root_url = value;
         self
     }
 
+    /// Hooks run on the DOM once built-in processing is complete
+    pub fn dom_hooks(&mut self, value: &'a [DomHook]) -> &mut Self {
+        self.dom_hooks = value;
+        self
+    }
+
+    pub fn downscale_images(&mut self, value: Option<&'a DownscaleImages>) -> &mut Self {
+        self.downscale_images = value;
+        self
+    }
+
+    pub fn strip_comments(&mut self, value: bool) -> &mut Self {
+        self.strip_comments = value;
+        self
+    }
+
+    pub fn embed_images(&mut self, value: Option<&'a EmbedImages>) -> &mut Self {
+        self.embed_images = value;
+        self
+    }
+
+    pub fn images_base_url(&mut self, value: Option<&'a str>) -> &mut Self {
+        self.images_base_url = value;
+        self
+    }
+
+    /// Returns `url` under the images base URL when it is relative, like `./dog.jpg` or `/dog.jpg`
+    fn with_images_base_url(&self, url: &str) -> Option<String> {
+        let images_base_url = self.images_base_url?.trim_end_matches('/');
+        if url.is_empty() || url.starts_with("//") || !relative_url(url) {
+            return None;
+        }
+        let pathname = url.trim_start_matches("./").trim_start_matches('/');
+        Some(format!("{images_base_url}/{pathname}"))
+    }
+
+    /// Returns `srcset` with each relative candidate URL under the images base URL, keeping the
+    /// width or density descriptors
+    fn srcset_with_images_base_url(&self, srcset: &str) -> Option<String> {
+        self.images_base_url?;
+        let candidates: Vec<String> = srcset
+            .split(',')
+            .map(|candidate| {
+                let candidate = candidate.trim();
+                let (url, descriptor) = candidate
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((candidate, ""));
+                let url = self
+                    .with_images_base_url(url)
+                    .unwrap_or_else(|| url.to_string());
+                if descriptor.is_empty() {
+                    url
+                } else {
+                    format!("{url} {}", descriptor.trim())
+                }
+            })
+            .collect();
+        Some(candidates.join(", "))
+    }
+
+    pub fn internal_links(&mut self, value: Option<&'a InternalLinks>) -> &mut Self {
+        self.internal_links = value;
+        self
+    }
+
     pub fn link_rel(&mut self, value: Option<&'a str>) -> &mut Self {
         self.link_rel = value;
         self
     }
 
+    pub fn link_target(&mut self, value: Option<&'a str>) -> &mut Self {
+        self.link_target = value;
+        self
+    }
+
+    pub fn responsive_images(&mut self, value: Option<&'a ResponsiveImages>) -> &mut Self {
+        self.responsive_images = value;
+        self
+    }
+
     pub fn search_term(&mut self, value: Option<&'a str>) -> &mut Self {
         self.search_term = value;
         self
     }
 
+    /// Takes any warnings collected while processing, for example images which could not be
+    /// embedded
+    pub fn take_warnings(&self) -> Vec<String> {
+        mem::take(&mut *self.warnings.borrow_mut())
+    }
+
+    fn warn(&self, message: String) {
+        self.warnings.borrow_mut().push(message);
+    }
+
+    /// Returns a `data:` URI for the image at `src` if it is a local file within the size limit
+    fn embedded_image_src(&self, src: &str, embed_images: &EmbedImages) -> Option<String> {
+        let pathname = src.split(['?', '#']).next().unwrap_or_default();
+        if pathname.is_empty() || !relative_url(pathname) {
+            return None;
+        }
+        let image_path = if let Some(root_relative_pathname) = pathname.strip_prefix('/') {
+            embed_images.project_root.join(root_relative_pathname)
+        } else {
+            embed_images.base_path.join(pathname)
+        };
+        let Ok(metadata) = fs::metadata(&image_path) else {
+            self.warn(format!("Unable to embed image ({src}): file not found"));
+            return None;
+        };
+        if metadata.len() > embed_images.max_size {
+            self.warn(format!(
+                "Skipped embedding image ({src}): {} bytes exceeds the {} byte limit",
+                metadata.len(),
+                embed_images.max_size
+            ));
+            return None;
+        }
+        let Some(mime_type) = image_mime_type(&image_path) else {
+            self.warn(format!(
+                "Skipped embedding image ({src}): unknown image type"
+            ));
+            return None;
+        };
+        let Ok(bytes) = fs::read(&image_path) else {
+            self.warn(format!(
+                "Unable to embed image ({src}): file could not be read"
+            ));
+            return None;
+        };
+        Some(format!(
+            "data:{mime_type};base64,{}",
+            STANDARD.encode(bytes)
+        ))
+    }
+
     #[allow(clippy::unused_self)]
     fn process_child(&self, _child: &mut Handle) -> bool {
         true
     }
 
     pub fn process_dom(&self, mut dom: RcDom) -> Document {
+        if self.strip_comments {
+            remove_comments(&dom.document);
+        }
+        render_alert_callouts(&dom.document);
         let mut stack = Vec::new();
         let mut removed = Vec::new();
         let link_rel = self.link_rel.map(|link_rel| format_tendril!("{link_rel}"));
@@ -124,8 +593,8 @@ impl<'a> Builder<'a> {
                         }
                         removed.push(node);
                         continue;
-                    };
-                };
+                    }
+                }
                 dom.append(&parent.clone(), NodeOrText::AppendNode(node.clone()));
             } else {
                 for sub in node.children.borrow_mut().iter_mut() {
@@ -141,6 +610,9 @@ impl<'a> Builder<'a> {
         while let Some(node) = removed.pop() {
             removed.extend_from_slice(&mem::take(&mut *node.children.borrow_mut())[..]);
         }
+        for DomHook(hook) in self.dom_hooks {
+            hook(&dom);
+        }
         Document(dom)
     }
 
@@ -166,28 +638,89 @@ impl<'a> Builder<'a> {
                 let mut attrs = attrs.borrow_mut();
                 if let Some(attr) = attrs.iter_mut().find(|attr| &*attr.name.local == "href") {
                     if relative_url(&attr.value) {
+                        if let Some(href) = self
+                            .internal_links
+                            .and_then(|internal_links| internal_links.rewrite(&attr.value))
+                        {
+                            attr.value = href.into();
+                        }
+                        if let Some(href) = self.with_base_path(&attr.value) {
+                            attr.value = href.into();
+                        }
                         if let Some(root_url_value) = self.canonical_root_url {
                             let pathname = &*attr.value;
                             attr.value = format!("{root_url_value}{pathname}").into();
                         }
-                    } else {
+                    } else if external_url(&attr.value, self.canonical_root_url) {
                         if let Some(link_target) = link_target {
-                            attrs.push(Attribute {
-                                name: QualName::new(None, ns!(), local_name!("target")),
-                                value: (*link_target).clone(),
-                            });
+                            if !attrs.iter().any(|attr| &*attr.name.local == "target") {
+                                attrs.push(Attribute {
+                                    name: QualName::new(None, ns!(), local_name!("target")),
+                                    value: (*link_target).clone(),
+                                });
+                            }
                         }
                         if let Some(link_rel) = link_rel {
-                            attrs.push(Attribute {
-                                name: QualName::new(None, ns!(), local_name!("rel")),
-                                value: link_rel.clone(),
-                            });
+                            merge_rel_attribute(&mut attrs, link_rel);
                         }
                     }
                 }
+            } else if &*name.local == "img" {
+                self.adjust_image_attributes(&mut attrs.borrow_mut());
             } else {
                 // TODO: anchor tag has no href — can emit a warning
-            };
+            }
+        }
+    }
+
+    fn adjust_image_attributes(&self, attrs: &mut Vec<Attribute>) {
+        let mut responsive_srcset = None;
+        if let Some(attr) = attrs.iter_mut().find(|attr| &*attr.name.local == "src") {
+            if let Some(data_uri) = self
+                .embed_images
+                .and_then(|embed_images| self.embedded_image_src(&attr.value, embed_images))
+            {
+                attr.value = data_uri.into();
+            } else {
+                let downscaled_src = self.downscale_images.and_then(|downscale_images| {
+                    downscale_images.src(&attr.value).unwrap_or_else(|message| {
+                        self.warn(message);
+                        None
+                    })
+                });
+                // Downscaled copies are already capped, so only the original gets a `srcset`
+                if let Some(src) = downscaled_src {
+                    attr.value = src.into();
+                } else if let Some(responsive_images) = self.responsive_images {
+                    match responsive_images.srcset(&attr.value) {
+                        Ok(value) => responsive_srcset = value,
+                        Err(message) => self.warn(message),
+                    }
+                }
+                if let Some(src) = self.with_images_base_url(&attr.value) {
+                    attr.value = src.into();
+                } else if let Some(src) = self.with_base_path(&attr.value) {
+                    attr.value = src.into();
+                }
+            }
+        }
+        // A hand-written `srcset` is kept in place of a generated one
+        if let Some(srcset) =
+            responsive_srcset.filter(|_| !attrs.iter().any(|attr| &*attr.name.local == "srcset"))
+        {
+            attrs.push(Attribute {
+                name: QualName::new(None, ns!(), local_name!("srcset")),
+                value: srcset.into(),
+            });
+            attrs.push(Attribute {
+                name: QualName::new(None, ns!(), local_name!("sizes")),
+                value: RESPONSIVE_IMAGE_SIZES.into(),
+            });
+        }
+        if let Some(attr) = attrs.iter_mut().find(|attr| &*attr.name.local == "srcset") {
+            if let Some(srcset) = self.srcset_with_images_base_url(&attr.value) {
+                attr.value = srcset.into();
+            }
         }
     }
 
@@ -344,15 +877,59 @@ impl Display for Document {
     }
 }
 
+#[allow(dead_code)]
 pub fn process_html(
     html: &str,
     canonical_root_url: Option<&str>,
     search_term: Option<&str>,
 ) -> String {
-    Builder::new()
+    let (result, _warnings) = process_html_with_warnings(
+        html,
+        canonical_root_url,
+        None,
+        None,
+        search_term,
+        None,
+        None,
+        None,
+        None,
+        &[],
+        false,
+        false,
+    );
+    result
+}
+
+/// Processes HTML as [`process_html`], additionally returning any warnings raised
+#[allow(clippy::too_many_arguments)]
+pub fn process_html_with_warnings(
+    html: &str,
+    canonical_root_url: Option<&str>,
+    base_path: Option<&str>,
+    images_base_url: Option<&str>,
+    search_term: Option<&str>,
+    embed_images: Option<&EmbedImages>,
+    downscale_images: Option<&DownscaleImages>,
+    responsive_images: Option<&ResponsiveImages>,
+    internal_links: Option<&InternalLinks>,
+    dom_hooks: &[DomHook],
+    external_links_same_tab: bool,
+    strip_comments: bool,
+) -> (String, Vec<String>) {
+    let mut builder = Builder::new();
+    builder
         .link_rel(Some("nofollow noopener noreferrer"))
+        .link_target((!external_links_same_tab).then_some("_blank"))
         .canonical_root_url(canonical_root_url)
+        .base_path(base_path)
+        .images_base_url(images_base_url)
         .search_term(search_term)
-        .process(html)
-        .to_string()
+        .embed_images(embed_images)
+        .downscale_images(downscale_images)
+        .responsive_images(responsive_images)
+        .internal_links(internal_links)
+        .dom_hooks(dom_hooks)
+        .strip_comments(strip_comments);
+    let result = builder.process(html).to_string();
+    (result, builder.take_warnings())
 }
@@ -0,0 +1,180 @@
+use super::{
+    dom::{Handle, NodeData, RcDom, SerializableHandle},
+    Builder,
+};
+use html5ever::{
+    driver, local_name,
+    serialize::{HtmlSerializer, Serialize, SerializeOpts, Serializer, TraversalScope},
+    tendril::TendrilSink,
+    LocalName,
+};
+use std::io;
+
+/// Indent added for each level of nesting in pretty-printed output
+const INDENT: &str = "  ";
+
+/// Elements which start a new line in pretty-printed output. Whitespace between these is not
+/// significant, so re-indenting it does not change how the document renders.
+const BLOCK_ELEMENTS: [LocalName; 48] = [
+    local_name!("address"),
+    local_name!("article"),
+    local_name!("aside"),
+    local_name!("base"),
+    local_name!("blockquote"),
+    local_name!("body"),
+    local_name!("caption"),
+    local_name!("dd"),
+    local_name!("details"),
+    local_name!("div"),
+    local_name!("dl"),
+    local_name!("dt"),
+    local_name!("fieldset"),
+    local_name!("figcaption"),
+    local_name!("figure"),
+    local_name!("footer"),
+    local_name!("form"),
+    local_name!("h1"),
+    local_name!("h2"),
+    local_name!("h3"),
+    local_name!("h4"),
+    local_name!("h5"),
+    local_name!("h6"),
+    local_name!("head"),
+    local_name!("header"),
+    local_name!("hr"),
+    local_name!("html"),
+    local_name!("li"),
+    local_name!("link"),
+    local_name!("main"),
+    local_name!("meta"),
+    local_name!("nav"),
+    local_name!("ol"),
+    local_name!("p"),
+    local_name!("pre"),
+    local_name!("script"),
+    local_name!("section"),
+    local_name!("style"),
+    local_name!("summary"),
+    local_name!("table"),
+    local_name!("tbody"),
+    local_name!("td"),
+    local_name!("tfoot"),
+    local_name!("th"),
+    local_name!("thead"),
+    local_name!("title"),
+    local_name!("tr"),
+    local_name!("ul"),
+];
+
+/// Block elements whose content is kept exactly as it is
+const PRESERVED_ELEMENTS: [LocalName; 5] = [
+    local_name!("pre"),
+    local_name!("script"),
+    local_name!("style"),
+    local_name!("textarea"),
+    local_name!("title"),
+];
+
+fn element_name(node: &Handle) -> Option<&LocalName> {
+    match &node.data {
+        NodeData::Element { name, .. } => Some(&name.local),
+        _ => None,
+    }
+}
+
+fn is_whitespace_text(node: &Handle) -> bool {
+    match &node.data {
+        NodeData::Text { contents } => contents.borrow().trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// Whether the children of `node` can go on their own, indented, lines: it is a block element,
+/// holding only block elements, comments and whitespace
+fn has_indentable_children(node: &Handle) -> bool {
+    let Some(name) = element_name(node) else {
+        return false;
+    };
+    BLOCK_ELEMENTS.contains(name)
+        && !PRESERVED_ELEMENTS.contains(name)
+        && node.children.borrow().iter().all(|child| {
+            is_whitespace_text(child)
+                || matches!(child.data, NodeData::Comment { .. })
+                || element_name(child).is_some_and(|value| BLOCK_ELEMENTS.contains(value))
+        })
+}
+
+fn write_pretty<S: Serializer>(node: &Handle, depth: usize, serializer: &mut S) -> io::Result<()> {
+    let NodeData::Element {
+        ref name,
+        ref attrs,
+        ..
+    } = node.data
+    else {
+        return SerializableHandle::from(node.clone())
+            .serialize(serializer, TraversalScope::IncludeNode);
+    };
+    if !has_indentable_children(node) {
+        return SerializableHandle::from(node.clone())
+            .serialize(serializer, TraversalScope::IncludeNode);
+    }
+
+    serializer.start_elem(
+        name.clone(),
+        attrs.borrow().iter().map(|at| (&at.name, &at.value[..])),
+    )?;
+    for child in node.children.borrow().iter() {
+        if is_whitespace_text(child) {
+            continue;
+        }
+        serializer.write_text(&format!("\n{}", INDENT.repeat(depth + 1)))?;
+        write_pretty(child, depth + 1, serializer)?;
+    }
+    serializer.write_text(&format!("\n{}", INDENT.repeat(depth)))?;
+    serializer.end_elem(name.clone())
+}
+
+/// Re-indents `html`, putting nested block elements on their own lines, and ends it with a single
+/// newline. Inline content, and the content of `pre`, `script` and `style` elements, is left
+/// untouched. With `fragment`, `html` is treated as content for the body of a document, rather
+/// than a whole document.
+pub fn pretty_print_html(html: &str, fragment: bool) -> String {
+    let dom: RcDom = if fragment {
+        Builder::make_parser().one(html)
+    } else {
+        driver::parse_document(RcDom::default(), driver::ParseOpts::default()).one(html)
+    };
+    let top_level_nodes: Vec<Handle> = if fragment {
+        // Fragment content is parsed into a wrapping `html` element
+        dom.document.children.borrow()[0].children.borrow().clone()
+    } else {
+        dom.document.children.borrow().clone()
+    };
+
+    let mut result = Vec::new();
+    let mut serializer = HtmlSerializer::new(
+        &mut result,
+        SerializeOpts {
+            traversal_scope: TraversalScope::ChildrenOnly(None),
+            ..SerializeOpts::default()
+        },
+    );
+    let mut first = true;
+    for node in top_level_nodes
+        .iter()
+        .filter(|node| !is_whitespace_text(node))
+    {
+        if !first {
+            serializer
+                .write_text("\n")
+                .expect("Writing to a string shouldn't fail (expect on OOM)");
+        }
+        first = false;
+        write_pretty(node, 0, &mut serializer)
+            .expect("Writing to a string shouldn't fail (expect on OOM)");
+    }
+    let mut result = String::from_utf8(result).expect("html5ever only supports UTF8");
+    result.truncate(result.trim_end().len());
+    result.push('\n');
+    result
+}
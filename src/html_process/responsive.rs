@@ -0,0 +1,221 @@
+use crate::url_utility::relative_url;
+use image::imageops::FilterType;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Widths, in pixels, of the resized variants generated for each responsive image
+pub const DEFAULT_RESPONSIVE_IMAGE_WIDTHS: [u32; 3] = [480, 960, 1440];
+
+/// `sizes` added alongside a generated `srcset`: the full viewport on narrow screens, otherwise the
+/// width of the 38rem content column
+pub const RESPONSIVE_IMAGE_SIZES: &str = "(max-width: 38rem) 100vw, 38rem";
+
+/// Configuration for generating resized variants of local raster images, and listing them in a
+/// `srcset`, so browsers can download the smallest image which fits
+#[derive(Clone, Debug)]
+pub struct ResponsiveImages {
+    /// Input document directory, holding the source images variants are generated from
+    base_path: PathBuf,
+
+    /// Directory HTML output is written to. Variants go under the same relative path as their
+    /// source image, so they resolve from the output document.
+    output_directory: PathBuf,
+
+    widths: Vec<u32>,
+}
+
+impl ResponsiveImages {
+    pub fn new<P1: AsRef<Path>, P2: AsRef<Path>>(base_path: P1, output_directory: P2) -> Self {
+        ResponsiveImages {
+            base_path: base_path.as_ref().to_path_buf(),
+            output_directory: output_directory.as_ref().to_path_buf(),
+            widths: DEFAULT_RESPONSIVE_IMAGE_WIDTHS.to_vec(),
+        }
+    }
+
+    /// Returns a `srcset` for the image at `src`, generating any missing or outdated variants.
+    /// Returns `None` for external, SVG or missing images and for images no wider than the
+    /// smallest variant width.
+    ///
+    /// # Errors
+    /// Errors with a message when the image cannot be read or a variant cannot be written
+    pub fn srcset(&self, src: &str) -> Result<Option<String>, String> {
+        let pathname = src.split(['?', '#']).next().unwrap_or_default();
+        if pathname.is_empty() || pathname.starts_with("//") || !relative_url(pathname) {
+            return Ok(None);
+        }
+        let relative_path = Path::new(pathname.trim_start_matches('/'));
+        let Some((stem, extension)) = raster_image_name(relative_path) else {
+            return Ok(None);
+        };
+        let source_path = self.base_path.join(relative_path);
+        if !source_path.is_file() {
+            return Ok(None);
+        }
+        let (source_width, _) = image::image_dimensions(&source_path)
+            .map_err(|error| format!("Unable to read image ({pathname}): {error}"))?;
+        let widths: Vec<u32> = self
+            .widths
+            .iter()
+            .copied()
+            .filter(|width| *width < source_width)
+            .collect();
+        if widths.is_empty() {
+            return Ok(None);
+        }
+
+        // Variant URLs keep the directory part of `src`, so resolve the same way
+        let url_directory = &pathname[..pathname.rfind('/').map_or(0, |index| index + 1)];
+        let mut source_image = None;
+        let mut candidates = Vec::with_capacity(widths.len() + 1);
+        for width in widths {
+            let variant_name = format!("{stem}-{width}w.{extension}");
+            let variant_path = self
+                .output_directory
+                .join(relative_path.with_file_name(&variant_name));
+            if !is_up_to_date(&variant_path, &source_path) {
+                // The source is only decoded when a variant needs writing
+                let image =
+                    if let Some(value) = &source_image {
+                        value
+                    } else {
+                        source_image.insert(image::open(&source_path).map_err(|error| {
+                            format!("Unable to read image ({pathname}): {error}")
+                        })?)
+                    };
+                if let Some(parent) = variant_path.parent() {
+                    fs::create_dir_all(parent).map_err(|error| {
+                        format!("Unable to create image directory ({pathname}): {error}")
+                    })?;
+                }
+                image
+                    .resize(width, u32::MAX, FilterType::Lanczos3)
+                    .save(&variant_path)
+                    .map_err(|error| {
+                        format!("Unable to write resized image ({variant_name}): {error}")
+                    })?;
+            }
+            candidates.push(format!("{url_directory}{variant_name} {width}w"));
+        }
+        candidates.push(format!("{pathname} {source_width}w"));
+        Ok(Some(candidates.join(", ")))
+    }
+}
+
+/// Configuration for downscaling local raster images larger than a maximum width or height, so
+/// enormous images are not published as they are
+#[derive(Clone, Debug)]
+pub struct DownscaleImages {
+    /// Input document directory, holding the full-size images which are downscaled
+    base_path: PathBuf,
+
+    /// Directory HTML output is written to. Downscaled copies go under the same relative path as
+    /// their source image, so they resolve from the output document.
+    output_directory: PathBuf,
+
+    max_height: Option<u32>,
+    max_width: Option<u32>,
+}
+
+impl DownscaleImages {
+    pub fn new<P1: AsRef<Path>, P2: AsRef<Path>>(
+        base_path: P1,
+        output_directory: P2,
+        max_width: Option<u32>,
+        max_height: Option<u32>,
+    ) -> Self {
+        DownscaleImages {
+            base_path: base_path.as_ref().to_path_buf(),
+            output_directory: output_directory.as_ref().to_path_buf(),
+            max_height,
+            max_width,
+        }
+    }
+
+    /// Width and height of an image `width` by `height` pixels scaled down, keeping its aspect
+    /// ratio, to fit the maximum dimensions, or `None` when it already fits
+    fn fitted_dimensions(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let ratio = |dimension: u32, max: Option<u32>| {
+            max.map_or(1.0, |value| f64::from(value) / f64::from(dimension))
+        };
+        let scale = ratio(width, self.max_width).min(ratio(height, self.max_height));
+        if scale >= 1.0 {
+            return None;
+        }
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let scaled = |dimension: u32| ((f64::from(dimension) * scale).round() as u32).max(1);
+        Some((scaled(width), scaled(height)))
+    }
+
+    /// Returns the source of a downscaled copy of the image at `src`, writing the copy if it is
+    /// missing or outdated. Returns `None` for external, SVG or missing images and for images
+    /// which already fit.
+    ///
+    /// # Errors
+    /// Errors with a message when the image cannot be read or the copy cannot be written
+    pub fn src(&self, src: &str) -> Result<Option<String>, String> {
+        let pathname = src.split(['?', '#']).next().unwrap_or_default();
+        if pathname.is_empty() || pathname.starts_with("//") || !relative_url(pathname) {
+            return Ok(None);
+        }
+        let relative_path = Path::new(pathname.trim_start_matches('/'));
+        let Some((stem, extension)) = raster_image_name(relative_path) else {
+            return Ok(None);
+        };
+        let source_path = self.base_path.join(relative_path);
+        if !source_path.is_file() {
+            return Ok(None);
+        }
+        let (source_width, source_height) = image::image_dimensions(&source_path)
+            .map_err(|error| format!("Unable to read image ({pathname}): {error}"))?;
+        let Some((width, height)) = self.fitted_dimensions(source_width, source_height) else {
+            return Ok(None);
+        };
+
+        let variant_name = format!("{stem}-{width}x{height}.{extension}");
+        let variant_path = self
+            .output_directory
+            .join(relative_path.with_file_name(&variant_name));
+        if !is_up_to_date(&variant_path, &source_path) {
+            let image = image::open(&source_path)
+                .map_err(|error| format!("Unable to read image ({pathname}): {error}"))?;
+            if let Some(parent) = variant_path.parent() {
+                fs::create_dir_all(parent).map_err(|error| {
+                    format!("Unable to create image directory ({pathname}): {error}")
+                })?;
+            }
+            image
+                .resize_exact(width, height, FilterType::Lanczos3)
+                .save(&variant_path)
+                .map_err(|error| {
+                    format!("Unable to write downscaled image ({variant_name}): {error}")
+                })?;
+        }
+
+        // The copy URL keeps the directory part of `src`, so resolves the same way
+        let url_directory = &pathname[..pathname.rfind('/').map_or(0, |index| index + 1)];
+        Ok(Some(format!("{url_directory}{variant_name}")))
+    }
+}
+
+/// File stem and extension of `path` when it names a raster image format variants can be
+/// generated for
+fn raster_image_name(path: &Path) -> Option<(&str, &str)> {
+    let stem = path.file_stem()?.to_str()?;
+    let extension = path.extension()?.to_str()?;
+    match extension.to_lowercase().as_str() {
+        "gif" | "jpeg" | "jpg" | "png" | "webp" => Some((stem, extension)),
+        _ => None,
+    }
+}
+
+/// Whether the variant at `variant_path` exists and was written after its source was last changed
+fn is_up_to_date(variant_path: &Path, source_path: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(variant_path), modified(source_path)) {
+        (Ok(variant_modified), Ok(source_modified)) => variant_modified >= source_modified,
+        _ => false,
+    }
+}
@@ -1,4 +1,8 @@
-use crate::html_process::{process_html, relative_url, Builder};
+use crate::html_process::{
+    pretty_print_html, process_html, process_html_with_warnings, relative_url, Builder,
+    DownscaleImages, EmbedImages, InternalLinks, ResponsiveImages,
+};
+use std::fs;
 
 #[test]
 fn test_builder_process() {
@@ -48,17 +52,16 @@ fn test_process_html() {
 fn test_relative_url() {
     assert!(relative_url("/about.html"));
     assert!(relative_url("#some-id"));
-    assert_eq!(relative_url("https://example.com"), false);
+    assert!(!relative_url("https://example.com"));
 }
 
 #[test]
 fn search_html_highlight_requested_term() {
     let result = process_html(
-        r#"<h2>Heading</h2><p>Nobody likes maple in their apple flavoured Snapple. APPLE</p><p>Paragraph with no matches</p><p>Paragraph which mentions apples again</p>"#,
+        r"<h2>Heading</h2><p>Nobody likes maple in their apple flavoured Snapple. APPLE</p><p>Paragraph with no matches</p><p>Paragraph which mentions apples again</p>",
         None,
         Some("apple"),
-    )
-    .to_string();
+    );
     let expected = r#"<h2>Heading</h2><p>Nobody likes maple in their <mark id="search-match">apple</mark> flavoured Sn<mark>apple</mark>. <mark>APPLE</mark></p><p>Paragraph with no matches</p><p>Paragraph which mentions <mark>apple</mark>s again</p>"#;
     assert_eq!(result, expected);
 }
@@ -66,11 +69,10 @@ fn search_html_highlight_requested_term() {
 #[test]
 fn search_html_highlight_requested_nested_term() {
     let result = process_html(
-        r#"<h2>Heading</h2><section><div><p>Nobody likes maple in their apple flavoured Snapple. APPLE</p><p>Paragraph with no matches</p><p>Paragraph which mentions apples again</p></div></section>"#,
+        r"<h2>Heading</h2><section><div><p>Nobody likes maple in their apple flavoured Snapple. APPLE</p><p>Paragraph with no matches</p><p>Paragraph which mentions apples again</p></div></section>",
         None,
         Some("apple"),
-    )
-    .to_string();
+    );
     let expected = r#"<h2>Heading</h2><section><div><p>Nobody likes maple in their <mark id="search-match">apple</mark> flavoured Sn<mark>apple</mark>. <mark>APPLE</mark></p><p>Paragraph with no matches</p><p>Paragraph which mentions <mark>apple</mark>s again</p></div></section>"#;
     assert_eq!(result, expected);
 }
@@ -78,11 +80,10 @@ fn search_html_highlight_requested_nested_term() {
 #[test]
 fn search_html_matches_on_multiple_terms() {
     let result = process_html(
-        r#"<h2>Heading</h2><p>Nobody likes maple in their apple flavoured Snapple. APPLE</p><p>Paragraph with no matches</p><p>Paragraph which mentions apples again</p>"#,
+        r"<h2>Heading</h2><p>Nobody likes maple in their apple flavoured Snapple. APPLE</p><p>Paragraph with no matches</p><p>Paragraph which mentions apples again</p>",
         None,
         Some("apple flavour"),
-    )
-    .to_string();
+    );
     let expected = r#"<h2>Heading</h2><p>Nobody likes maple in their <mark id="search-match">apple</mark> <mark>flavour</mark>ed Sn<mark>apple</mark>. <mark>APPLE</mark></p><p>Paragraph with no matches</p><p>Paragraph which mentions <mark>apple</mark>s again</p>"#;
     assert_eq!(result, expected);
 }
@@ -90,12 +91,457 @@ fn search_html_matches_on_multiple_terms() {
 #[test]
 fn search_html_highlight_does_nothing_when_there_are_no_matches() {
     let result = process_html(
-        r#"<h2>Heading</h2><p>Nobody likes maple in their apple flavoured Snapple. APPLE</p>"#,
+        r"<h2>Heading</h2><p>Nobody likes maple in their apple flavoured Snapple. APPLE</p>",
         None,
         Some("nonsense"),
-    )
-    .to_string();
+    );
+    let expected =
+        r"<h2>Heading</h2><p>Nobody likes maple in their apple flavoured Snapple. APPLE</p>";
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn embed_images_inlines_small_local_image_and_leaves_external_image() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    let png_bytes: [u8; 8] = [0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a];
+    fs::write(temp_dir.path().join("dot.png"), png_bytes).expect("Error writing temp image");
+    let embed_images = EmbedImages::new(temp_dir.path(), temp_dir.path(), 1024);
+
+    // act
+    let (result, warnings) = process_html_with_warnings(
+        r#"<img src="./dot.png" alt="Dot"><img src="https://example.com/remote.png" alt="Remote">"#,
+        None,
+        None,
+        None,
+        None,
+        Some(&embed_images),
+        None,
+        None,
+        None,
+        &[],
+        false,
+        false,
+    );
+
+    // assert
+    let expected = r#"<img src="data:image/png;base64,iVBORw0KGgo=" alt="Dot"><img src="https://example.com/remote.png" alt="Remote">"#;
+    assert_eq!(result, expected);
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn embed_images_skips_image_over_size_limit_with_warning() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    fs::write(temp_dir.path().join("large.png"), [0_u8; 64]).expect("Error writing temp image");
+    let embed_images = EmbedImages::new(temp_dir.path(), temp_dir.path(), 32);
+
+    // act
+    let (result, warnings) = process_html_with_warnings(
+        r#"<img src="large.png" alt="Large">"#,
+        None,
+        None,
+        None,
+        None,
+        Some(&embed_images),
+        None,
+        None,
+        None,
+        &[],
+        false,
+        false,
+    );
+
+    // assert
+    assert_eq!(result, r#"<img src="large.png" alt="Large">"#);
+    assert_eq!(
+        warnings,
+        vec![String::from(
+            "Skipped embedding image (large.png): 64 bytes exceeds the 32 byte limit"
+        )]
+    );
+}
+
+#[test]
+fn embed_images_resolves_root_relative_image_against_project_root() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    let png_bytes: [u8; 8] = [0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a];
+    fs::create_dir_all(temp_dir.path().join("posts")).expect("Error creating temp directories");
+    fs::write(temp_dir.path().join("dot.png"), png_bytes).expect("Error writing temp image");
+    fs::write(temp_dir.path().join("posts/dot.png"), [0_u8; 64]).expect("Error writing temp image");
+    let embed_images = EmbedImages::new(temp_dir.path().join("posts"), temp_dir.path(), 32);
+
+    // act
+    let (result, warnings) = process_html_with_warnings(
+        r#"<img src="/dot.png" alt="Dot">"#,
+        None,
+        None,
+        None,
+        None,
+        Some(&embed_images),
+        None,
+        None,
+        None,
+        &[],
+        false,
+        false,
+    );
+
+    // assert
+    assert_eq!(
+        result,
+        r#"<img src="data:image/png;base64,iVBORw0KGgo=" alt="Dot">"#
+    );
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn internal_links_rewrites_local_markdown_link_to_html() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    fs::write(temp_dir.path().join("other.md"), "# Other").expect("Error writing temp markdown");
+    let internal_links = InternalLinks::new(temp_dir.path(), temp_dir.path());
+
+    // act
+    let (result, _warnings) = process_html_with_warnings(
+        r#"<a href="other.md">see other post</a><a href="missing.md">missing</a>"#,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(&internal_links),
+        &[],
+        false,
+        false,
+    );
+
+    // assert
+    let expected = r#"<a href="other.html">see other post</a><a href="missing.md">missing</a>"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn internal_links_preserves_fragment_and_query() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    fs::write(temp_dir.path().join("other.md"), "# Other").expect("Error writing temp markdown");
+    let internal_links = InternalLinks::new(temp_dir.path(), temp_dir.path());
+
+    // act
+    let (result, _warnings) = process_html_with_warnings(
+        r#"<a href="other.md#section">Section</a><a href="./other.md?ref=home#top">Top</a>"#,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(&internal_links),
+        &[],
+        false,
+        false,
+    );
+
+    // assert
+    let expected =
+        r#"<a href="other.html#section">Section</a><a href="./other.html?ref=home#top">Top</a>"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn internal_links_leaves_link_outside_project_root() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    let project_root = temp_dir.path().join("project");
+    fs::create_dir(&project_root).expect("Error creating temp project");
+    fs::write(temp_dir.path().join("outside.md"), "# Outside")
+        .expect("Error writing temp markdown");
+    let internal_links = InternalLinks::new(&project_root, &project_root);
+
+    // act
+    let (result, _warnings) = process_html_with_warnings(
+        r#"<a href="../outside.md">Outside</a>"#,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(&internal_links),
+        &[],
+        false,
+        false,
+    );
+
+    // assert
+    assert_eq!(result, r#"<a href="../outside.md">Outside</a>"#);
+}
+
+#[test]
+fn internal_links_rewrites_links_matching_pattern_and_preserves_remote_links() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    let internal_links = InternalLinks::new(temp_dir.path(), temp_dir.path())
+        .patterns(&[String::from("docs/**/*.md")]);
+
+    // act
+    let (result, _warnings) = process_html_with_warnings(
+        concat!(
+            r#"<a href="./docs/guide/setup.md#install">Setup</a>"#,
+            r#"<a href="notes/todo.md">Todo</a>"#,
+            r#"<a href="https://github.com/rodneylab/markwrite/blob/main/docs/README.md">README</a>"#,
+        ),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(&internal_links),
+        &[],
+        false,
+        false,
+    );
+
+    // assert
+    let expected = concat!(
+        r#"<a href="./docs/guide/setup.html#install">Setup</a>"#,
+        r#"<a href="notes/todo.md">Todo</a>"#,
+        r#"<a href="https://github.com/rodneylab/markwrite/blob/main/docs/README.md" target="_blank" rel="nofollow noopener noreferrer">README</a>"#,
+    );
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn process_html_renders_github_alert_as_callout() {
+    // arrange
+    let html = "<blockquote>\n<p>[!WARNING]\nBack up your files first.</p>\n</blockquote>\n";
+
+    // act
+    let result = process_html(html, None, None);
+
+    // assert
+    let expected = "<div class=\"callout callout-warning\"><p class=\"callout-title\">Warning</p>\n<p>Back up your files first.</p>\n</div>\n";
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn process_html_renders_alert_marker_in_own_paragraph_and_leaves_plain_blockquote() {
+    // arrange
+    let html = "<blockquote>\n<p>[!note]</p>\n<p>Details.</p>\n</blockquote>\n<blockquote>\n<p>[!QUOTE]\nKeep me.</p>\n</blockquote>\n";
+
+    // act
+    let result = process_html(html, None, None);
+
+    // assert
+    let expected = "<div class=\"callout callout-note\"><p class=\"callout-title\">Note</p>\n\n<p>Details.</p>\n</div>\n<blockquote>\n<p>[!QUOTE]\nKeep me.</p>\n</blockquote>\n";
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn external_links_get_merged_rel_and_internal_links_are_unchanged() {
+    // arrange
+    let html = r#"<a href="https://example.org/guide" rel="external">Guide</a><a href="https://example.com/about">About</a><a href="/contact">Contact</a><a href="mailto:hello@example.org">Email</a>"#;
+
+    // act
+    let result = Builder::new()
+        .canonical_root_url(Some("https://example.com"))
+        .process(html)
+        .to_string();
+
+    // assert
+    let expected = r#"<a href="https://example.org/guide" rel="external noopener noreferrer" target="_blank">Guide</a><a href="https://example.com/about">About</a><a href="https://example.com/contact">Contact</a><a href="mailto:hello@example.org">Email</a>"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn external_links_keep_existing_target_and_omit_target_when_disabled() {
+    // arrange
+    let html = r#"<a href="https://example.org/" target="_self" rel="NOOPENER">Guide</a>"#;
+
+    // act
+    let result = Builder::new().process(html).to_string();
+    let same_tab_result = Builder::new()
+        .link_target(None)
+        .process(r#"<a href="https://example.org/">Guide</a>"#)
+        .to_string();
+
+    // assert
+    assert_eq!(
+        result,
+        r#"<a href="https://example.org/" target="_self" rel="NOOPENER noreferrer">Guide</a>"#
+    );
+    assert_eq!(
+        same_tab_result,
+        r#"<a href="https://example.org/" rel="noopener noreferrer">Guide</a>"#
+    );
+}
+
+#[test]
+fn base_path_prefixes_root_relative_link_and_image() {
+    let result = Builder::new()
+        .base_path(Some("/blog"))
+        .process(r#"<a href="/about-us#team">About</a><img src="/images/team.png" alt="Team">"#)
+        .to_string();
     let expected =
-        r#"<h2>Heading</h2><p>Nobody likes maple in their apple flavoured Snapple. APPLE</p>"#;
+        r#"<a href="/blog/about-us#team">About</a><img src="/blog/images/team.png" alt="Team">"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn base_path_leaves_absolute_and_document_relative_urls() {
+    let result = Builder::new()
+        .base_path(Some("/blog/"))
+        .process(r#"<a href="https://example.com/about">About</a><a href="other.html">Other</a><img src="//cdn.example.com/team.png" alt="Team">"#)
+        .to_string();
+    let expected = r#"<a href="https://example.com/about" target="_blank" rel="noopener noreferrer">About</a><a href="other.html">Other</a><img src="//cdn.example.com/team.png" alt="Team">"#;
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn pretty_print_html_indents_block_fragment_and_keeps_inline_content() {
+    // arrange
+    let html = "<section><h2>Title</h2><ul><li><a href=\"/\">Home</a> <em>page</em></li></ul><pre><code>a\n  b\n</code></pre></section>";
+
+    // act
+    let result = pretty_print_html(html, true);
+
+    // assert
+    let expected = "<section>
+  <h2>Title</h2>
+  <ul>
+    <li><a href=\"/\">Home</a> <em>page</em></li>
+  </ul>
+  <pre><code>a
+  b
+</code></pre>
+</section>
+";
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn images_base_url_rewrites_relative_image_sources_only() {
+    // act
+    let result = Builder::new()
+        .images_base_url(Some("https://cdn.example.com/assets/"))
+        .process(
+            r#"<a href="/about/">About</a><img src="./images/dog.jpg" alt="Dog"><img src="/cat.jpg" srcset="/cat-480.jpg 480w, https://example.com/cat-960.jpg 960w" alt="Cat"><img src="https://example.com/remote.png" alt="Remote">"#,
+        )
+        .to_string();
+
+    // assert
+    let expected = r#"<a href="/about/">About</a><img src="https://cdn.example.com/assets/images/dog.jpg" alt="Dog"><img src="https://cdn.example.com/assets/cat.jpg" srcset="https://cdn.example.com/assets/cat-480.jpg 480w, https://example.com/cat-960.jpg 960w" alt="Cat"><img src="https://example.com/remote.png" alt="Remote">"#;
     assert_eq!(result, expected);
 }
+
+#[test]
+fn responsive_images_generates_variants_and_srcset_for_large_local_image() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    let output_directory = temp_dir.path().join("public");
+    fs::create_dir(temp_dir.path().join("images")).expect("Error creating temp image directory");
+    image::RgbImage::new(1600, 40)
+        .save(temp_dir.path().join("images/wide.png"))
+        .expect("Error writing temp image");
+    let responsive_images = ResponsiveImages::new(temp_dir.path(), &output_directory);
+
+    // act
+    let result = Builder::new()
+        .responsive_images(Some(&responsive_images))
+        .process(r#"<img src="images/wide.png" alt="Wide">"#)
+        .to_string();
+
+    // assert
+    let expected = r#"<img src="images/wide.png" alt="Wide" srcset="images/wide-480w.png 480w, images/wide-960w.png 960w, images/wide-1440w.png 1440w, images/wide.png 1600w" sizes="(max-width: 38rem) 100vw, 38rem">"#;
+    assert_eq!(result, expected);
+    for width in [480, 960, 1440] {
+        let variant_path = output_directory.join(format!("images/wide-{width}w.png"));
+        let (variant_width, _) =
+            image::image_dimensions(&variant_path).expect("Expected resized image variant");
+        assert_eq!(variant_width, width);
+    }
+}
+
+#[test]
+fn responsive_images_skips_small_svg_and_external_images() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    image::RgbImage::new(320, 40)
+        .save(temp_dir.path().join("small.png"))
+        .expect("Error writing temp image");
+    fs::write(temp_dir.path().join("logo.svg"), "<svg></svg>").expect("Error writing temp image");
+    let responsive_images = ResponsiveImages::new(temp_dir.path(), temp_dir.path());
+    let html = r#"<img src="small.png" alt="Small"><img src="logo.svg" alt="Logo"><img src="https://example.com/remote.png" alt="Remote">"#;
+
+    // act
+    let result = Builder::new()
+        .responsive_images(Some(&responsive_images))
+        .process(html)
+        .to_string();
+
+    // assert
+    assert_eq!(result, html);
+    assert!(!temp_dir.path().join("small-480w.png").exists());
+}
+
+#[test]
+fn downscale_images_caps_oversized_image_and_leaves_others() {
+    // arrange
+    let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+    let output_directory = temp_dir.path().join("public");
+    fs::create_dir(temp_dir.path().join("images")).expect("Error creating temp image directory");
+    image::RgbImage::new(1600, 400)
+        .save(temp_dir.path().join("images/huge.png"))
+        .expect("Error writing temp image");
+    image::RgbImage::new(320, 40)
+        .save(temp_dir.path().join("small.png"))
+        .expect("Error writing temp image");
+    fs::write(temp_dir.path().join("logo.svg"), "<svg></svg>").expect("Error writing temp image");
+    let downscale_images =
+        DownscaleImages::new(temp_dir.path(), &output_directory, Some(800), Some(600));
+
+    // act
+    let result = Builder::new()
+        .downscale_images(Some(&downscale_images))
+        .process(
+            r#"<img src="images/huge.png" alt="Huge"><img src="small.png" alt="Small"><img src="logo.svg" alt="Logo">"#,
+        )
+        .to_string();
+
+    // assert
+    let expected = r#"<img src="images/huge-800x200.png" alt="Huge"><img src="small.png" alt="Small"><img src="logo.svg" alt="Logo">"#;
+    assert_eq!(result, expected);
+    let dimensions = image::image_dimensions(output_directory.join("images/huge-800x200.png"))
+        .expect("Expected downscaled image");
+    assert_eq!(dimensions, (800, 200));
+    assert!(!output_directory.join("small-320x40.png").exists());
+}
+
+#[test]
+fn builder_strips_comments_but_keeps_content_and_directives() {
+    // arrange
+    let html = "<p>Kept <!-- note -->text</p><!-- markwrite:grammar-ignore-start --><!-- TODO -->";
+
+    // act
+    let kept_result = Builder::new().process(html).to_string();
+    let stripped_result = Builder::new()
+        .strip_comments(true)
+        .process(html)
+        .to_string();
+
+    // assert
+    assert_eq!(kept_result, html);
+    assert_eq!(
+        stripped_result,
+        "<p>Kept text</p><!-- markwrite:grammar-ignore-start -->"
+    );
+}
@@ -1,5 +1,7 @@
 #![warn(clippy::all, clippy::pedantic)]
 
+mod cache;
+mod clean;
 mod grammar;
 mod html_process;
 mod inline_html;
@@ -7,14 +9,31 @@ mod markdown;
 mod url_utility;
 mod utilities;
 
-use crate::grammar::{CheckResult as GrammarCheckResult, Checker as GrammarChecker};
-use crate::html_process::process_html;
-use anyhow::{Context, Result};
+use crate::cache::{CachedRender, RenderCache};
+use crate::grammar::{
+    autofixes, check_language_supported, grammar_check_chunks, json_report, sarif_report,
+    source_positions, SourcePosition, DEFAULT_LANGUAGETOOL_URL, DEFAULT_MAX_CONCURRENT_REQUESTS,
+    DEFAULT_MIN_LANGUAGE_CONFIDENCE, DEFAULT_REPLACEMENT_LIMIT,
+};
+use crate::html_process::{
+    pretty_print_html, process_html_with_warnings, DownscaleImages, EmbedImages, InternalLinks,
+    ResponsiveImages, DEFAULT_EMBED_IMAGES_MAX_SIZE,
+};
+use crate::url_utility::absolute_canonical_url;
+use anyhow::{bail, Context, Result};
 use askama::Template;
-use log::{error, info, trace};
+use clap::ValueEnum;
+use flate2::{write::GzEncoder, Compression};
+use futures::{
+    future,
+    stream::{self, FuturesUnordered},
+    Stream, StreamExt,
+};
+use log::{debug, error, info, trace};
 use markdown::{
-    parse_markdown_to_html, parse_markdown_to_plaintext, Heading, ParseMarkdownOptions,
-    TextStatistics,
+    code_ranges, link_destinations, lint_markdown, parse_markdown_to_html,
+    parse_markdown_to_plaintext, parse_markdown_to_plaintext_regions, LanguageRegion,
+    LinkRendering, ParseMarkdownOptions, TextStatistics,
 };
 use owo_colors::{
     colors::{BrightBlue, BrightCyan, White},
@@ -22,22 +41,106 @@ use owo_colors::{
 };
 use serde::Deserialize;
 use std::{
-    cmp,
+    borrow::Cow,
     collections::HashSet,
-    fs::{read_to_string, File, OpenOptions},
-    future::Future,
+    fs::{create_dir_all, read_to_string, remove_file, rename, File, OpenOptions},
     include_bytes,
     io::{BufRead, BufReader, Write},
-    path::Path,
-    pin::Pin,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+    process::Stdio,
+    sync::{atomic::AtomicUsize, Arc, OnceLock},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{io::AsyncWriteExt, process::Command, sync::Semaphore};
+use yaml_rust2::{Yaml, YamlLoader};
+
+pub use clean::clean_generated_outputs;
+pub use grammar::{
+    CheckResult as GrammarCheckResult, Checker as GrammarChecker,
+    LanguageInfo as GrammarLanguageInfo, ReportFormat as GrammarReportFormat, SentenceSegmentation,
+};
+pub use html_process::{
+    DomHook, Handle as DomHandle, Node as DomNode, NodeData as DomNodeData, RcDom,
 };
-use yaml_rust2::YamlLoader;
+pub use markdown::{BlockKind, Heading, SmartPunctuation};
+use utilities::files::markdown_files;
+pub use utilities::sentences::DEFAULT_SENTENCE_TERMINATORS;
+pub use utilities::slug::slugify;
+use utilities::time::iso_date;
+pub use utilities::time::parse_since;
 
+#[derive(Clone, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct ParseInputOptions {
+    base_path: Option<String>,
+    byline: bool,
     canonical_root_url: Option<String>,
-    #[allow(unused)]
-    enable_smart_punctuation: Option<bool>,
+    default_title: Option<String>,
+    dom_hooks: Vec<DomHook>,
+
+    /// Local images larger than the maximum image width or height are replaced by smaller copies
+    downscale_images: Option<DownscaleImages>,
+    embed_images: Option<EmbedImages>,
+    embed_source: bool,
+    emoji_shortcodes: bool,
+
+    /// Leave `target="_blank"` off external links, so they open in the same tab
+    external_links_same_tab: bool,
+    extra_css: Option<String>,
+
+    /// Shared content added after the document content
+    footer: Option<Snippet>,
+    fragment: bool,
+    hard_line_breaks: bool,
+
+    /// Shared content added before the document content
+    header: Option<Snippet>,
+    images_base_url: Option<String>,
+    internal_links: Option<InternalLinks>,
+
+    /// Template from the templates directory, named by the frontmatter `layout`, used instead of
+    /// the built-in template
+    layout_template: Option<String>,
+    lint: bool,
+    max_heading_id_level: Option<u8>,
+
+    /// Sentences with more words than this, or the default maximum, get a warning, when linting
+    max_sentence_words: Option<u32>,
+    number_headings: bool,
+    responsive_images: Option<ResponsiveImages>,
+    sidebar_toc: bool,
+    smart_punctuation: SmartPunctuation,
     search_term: Option<String>,
+
+    /// Source lines before the markdown body, such as frontmatter, so lint warnings give source
+    /// line numbers
+    source_line_offset: usize,
+    strip_comments: bool,
+    subscript_superscript: bool,
+}
+
+/// Time spent in each stage of rendering a document
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct StageTimings {
+    parse: Duration,
+    html_process: Duration,
+}
+
+impl StageTimings {
+    #[must_use]
+    pub fn parse(&self) -> Duration {
+        self.parse
+    }
+
+    #[must_use]
+    pub fn html_process(&self) -> Duration {
+        self.html_process
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -46,17 +149,178 @@ pub struct ParseResults {
     headings: Option<Vec<Heading>>,
     statistics: Option<TextStatistics>,
     errors: Option<Vec<String>>,
+    timings: StageTimings,
+}
+
+impl ParseResults {
+    #[must_use]
+    pub fn html(&self) -> Option<&str> {
+        self.html.as_deref()
+    }
+
+    #[must_use]
+    pub fn headings(&self) -> Option<&[Heading]> {
+        self.headings.as_deref()
+    }
+
+    #[must_use]
+    pub fn errors(&self) -> Option<&[String]> {
+        self.errors.as_deref()
+    }
+
+    #[must_use]
+    pub fn timings(&self) -> &StageTimings {
+        &self.timings
+    }
+}
+
+/// What a successful [`update_html`] call did with its input
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UpdateStatus {
+    /// Output HTML was written
+    Rendered,
+
+    /// The input was checked in check only mode, with no output written
+    Checked,
+
+    /// No output was written, for a draft
+    Skipped,
+}
+
+/// Result of a successful [`update_html`] call, totalled over a batch by [`BatchSummary`]
+#[derive(Debug, PartialEq)]
+pub struct UpdateOutcome {
+    status: UpdateStatus,
+    word_count: u32,
+    grammar_issue_count: usize,
+
+    /// Listing for the output written, for directory indexes and sitemaps
+    document: Option<ListedDocument>,
+}
+
+impl UpdateOutcome {
+    #[must_use]
+    pub fn status(&self) -> UpdateStatus {
+        self.status
+    }
+
+    /// Listing for the output written, or `None` when no output was written
+    #[must_use]
+    pub fn document(&self) -> Option<&ListedDocument> {
+        self.document.as_ref()
+    }
+
+    #[must_use]
+    pub fn word_count(&self) -> u32 {
+        self.word_count
+    }
+
+    #[must_use]
+    pub fn grammar_issue_count(&self) -> usize {
+        self.grammar_issue_count
+    }
+}
+
+/// Totals over every input of a multi-file run, printed once the run ends
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct BatchSummary {
+    rendered: usize,
+    checked: usize,
+    skipped: usize,
+    failed: usize,
+    word_count: u64,
+    grammar_issue_count: usize,
+}
+
+impl BatchSummary {
+    /// Adds the result of a single [`update_html`] call to the totals
+    pub fn record<E>(&mut self, result: &Result<UpdateOutcome, E>) {
+        let Ok(outcome) = result else {
+            self.failed += 1;
+            return;
+        };
+        match outcome.status {
+            UpdateStatus::Rendered => self.rendered += 1,
+            UpdateStatus::Checked => self.checked += 1,
+            UpdateStatus::Skipped => self.skipped += 1,
+        }
+        self.word_count += u64::from(outcome.word_count);
+        self.grammar_issue_count += outcome.grammar_issue_count;
+    }
+
+    /// Inputs rendered or checked without error
+    #[must_use]
+    pub fn succeeded(&self) -> usize {
+        self.rendered + self.checked
+    }
+
+    #[must_use]
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    #[must_use]
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// Prints the totals as an `[ INFO ]` line, or as a JSON object for the machine-readable
+    /// formats
+    ///
+    /// # Errors
+    /// Errors when writing to `stdout_handle` fails
+    pub fn display(
+        &self,
+        format: GrammarReportFormat,
+        stdout_handle: &mut impl Write,
+    ) -> std::io::Result<()> {
+        if format == GrammarReportFormat::Human {
+            writeln!(
+                stdout_handle,
+                "[ INFO ] Summary: {} succeeded ({} rendered, {} checked), {} skipped, {} failed; \
+{} words, {} grammar issues.",
+                self.succeeded(),
+                self.rendered,
+                self.checked,
+                self.skipped,
+                self.failed,
+                self.word_count,
+                self.grammar_issue_count
+            )?;
+        } else {
+            let summary = serde_json::json!({
+                "succeeded": self.succeeded(),
+                "rendered": self.rendered,
+                "checked": self.checked,
+                "skipped": self.skipped,
+                "failed": self.failed,
+                "wordCount": self.word_count,
+                "grammarIssueCount": self.grammar_issue_count,
+            });
+            writeln!(stdout_handle, "{summary}")?;
+        }
+        stdout_handle.flush()
+    }
 }
 
 fn display_grammar_check_results(
-    results: &Vec<GrammarCheckResult>,
+    results: &[GrammarCheckResult],
+    positions: &[Option<SourcePosition>],
     path: &str,
     stdout_handle: &mut impl Write,
 ) {
-    for result in results {
+    for (result, position) in results.iter().zip(positions) {
+        let line = position.map_or_else(String::new, |value| {
+            format!(" / line {}:{}", value.line(), value.column())
+        });
+        let source = if result.is_alt_text() {
+            " / image alt text"
+        } else {
+            ""
+        };
         writeln!(
             stdout_handle,
-            "\n\n  * {path} / line {}{}{}:",
+            "\n\n  * {path}{line}{source} {}{}{}:",
             "(".fg::<White>(),
             result.short_message().fg::<BrightCyan>(),
             ")".fg::<White>(),
@@ -65,125 +329,534 @@ fn display_grammar_check_results(
         writeln!(stdout_handle, "\n    {}\n", result.context())
             .expect("Expected to be able to write to stdout");
         if let Some(value) = result.replacements_string() {
-            writeln!(stdout_handle, "    replacements:\n\n{value}",)
+            writeln!(stdout_handle, "    replacements:\n\n{value}")
                 .expect("Expected to be able to write to stdout");
         }
         writeln!(stdout_handle, "    {}", result.sentence().fg::<White>())
             .expect("Expected to be able to write to stdout");
         writeln!(
             stdout_handle,
-            "\n    {}\n\n",
-            result.message().fg::<BrightBlue>()
+            "\n    {} {}\n\n",
+            result.message().fg::<BrightBlue>(),
+            format!("[{}]", result.category_id()).fg::<White>()
         )
         .expect("Expected to be able to write to stdout");
     }
 }
 
-/* Text is trimmed into 1500 character chunks for grammar check.  This function
- * was written to help truncate each chunk, so that the chunk ends with
- * complete sentence or two new line characters.
- */
-fn strip_trailing_sentence_stub(text: &str) -> (&str, usize) {
-    let end = text.len();
+fn display_timings(
+    timings: &StageTimings,
+    grammar_check_duration: Option<Duration>,
+    stdout_handle: &mut impl Write,
+) -> std::io::Result<()> {
+    let grammar_check = match grammar_check_duration {
+        Some(value) => format!("{:.1} ms", milliseconds(value)),
+        None => String::from("skipped"),
+    };
+    writeln!(
+        stdout_handle,
+        "[ INFO ] Timings: parse {:.1} ms, HTML processing {:.1} ms, grammar check {grammar_check}.",
+        milliseconds(timings.parse),
+        milliseconds(timings.html_process),
+    )
+}
 
-    // early return for trivial cases
-    if end <= 1 {
-        return (text, end);
+/// Prints the average and longest sentence lengths, for documents with any sentences
+fn display_sentence_statistics(
+    statistics: &TextStatistics,
+    stdout_handle: &mut impl Write,
+) -> std::io::Result<()> {
+    if statistics.longest_sentence_words() == 0 {
+        return Ok(());
     }
+    writeln!(
+        stdout_handle,
+        "[ INFO ] Sentences average {:.1} words, the longest has {}.",
+        statistics.average_sentence_length(),
+        statistics.longest_sentence_words()
+    )
+}
 
-    if let Some(value) =
-        //text[..].rfind(|val: char| val == '.' || val == '\n' || val == '!' || val == '?')
-        text[..].rfind(['.', '\n', '!', '?'])
-    {
-        // last character as a &str
-        let last = &text[value..=value];
-        if value == end - 1 && last != "\n" {
-            return strip_trailing_sentence_stub(&text[..end - 1]);
-        }
+/// Prints `headings` as an outline in document order, each indented two spaces for every level
+/// below the top heading level, with any section number and, with `show_slugs`, its slug
+///
+/// # Errors
+/// Errors if unable to write to `stdout_handle`
+pub fn display_heading_outline(
+    headings: &[Heading],
+    show_slugs: bool,
+    stdout_handle: &mut impl Write,
+) -> std::io::Result<()> {
+    let top_level = headings.iter().map(Heading::level).min().unwrap_or(1);
+    for heading in headings {
+        let indent = "  ".repeat(usize::from(heading.level() - top_level));
+        let number = heading
+            .number()
+            .map(|value| format!("{value} "))
+            .unwrap_or_default();
+        let slug = if show_slugs {
+            format!(" (#{})", heading.slug())
+        } else {
+            String::new()
+        };
+        writeln!(stdout_handle, "{indent}- {number}{}{slug}", heading.text())?;
+    }
+    Ok(())
+}
 
-        // no point trimming right back to the start of the string, so just send everything
-        if value == 0 {
-            return (text, end);
-        }
+/// Size, in bytes, of the new text in each grammar check chunk
+const GRAMMAR_CHECK_CHUNK_SIZE: usize = 1500;
 
-        match last {
-            /* Could be the end of a sentence, check following character is a
-             * whitespace character to avoid accidently splitting 10.1, for
-             * example.
-             */
-            "." | "!" | "?" => match &text[value + 1..value + 2].find(char::is_whitespace) {
-                Some(_) => (&text[..value + 2], value + 2),
-                None => strip_trailing_sentence_stub(&text[..value]),
-            },
-            "\n" => match &text[value - 1..value].find('\n') {
-                Some(_) => (&text[..=value], value + 1),
-                None => strip_trailing_sentence_stub(&text[..value]),
-            },
-            _ => unreachable!("Should not be possible"),
-        }
-    } else {
-        (text, text.len())
+/// Sentences from the end of each grammar check chunk repeated at the start of the next, unless set
+/// in the options
+const DEFAULT_GRAMMAR_CHUNK_OVERLAP: usize = 2;
+
+/// Gzip compression level for gzipped output, unless set in the options
+const DEFAULT_GZIP_LEVEL: u32 = 6;
+
+/// Lint warns about sentences with more words than this, unless set in the options
+const DEFAULT_MAX_SENTENCE_WORDS: u32 = 40;
+
+/// Checks languages set by markers in `regions` against those the grammar server supports, before
+/// sending any text. When the server languages cannot be fetched, checking goes ahead.
+///
+/// # Errors
+/// Errors with a message listing supported languages, if a region language is not supported
+async fn check_region_languages(
+    regions: &[LanguageRegion],
+    grammar_checker: &GrammarChecker,
+) -> Result<(), String> {
+    let mut region_languages: Vec<&str> = regions
+        .iter()
+        .filter_map(LanguageRegion::language)
+        .collect();
+    if region_languages.is_empty() {
+        return Ok(());
     }
+    region_languages.sort_unstable();
+    region_languages.dedup();
+    let Ok(supported_languages) = grammar_checker.supported_languages().await else {
+        return Ok(());
+    };
+    region_languages
+        .into_iter()
+        .try_for_each(|language| check_language_supported(language, &supported_languages))
+}
+
+/// Options for the plaintext sent for grammar checks. Code is left out, and so are link URLs, which
+/// would otherwise be read as part of the sentence around them.
+fn grammar_markdown_options(markwrite_options: &MarkwriteOptions) -> ParseMarkdownOptions<'_> {
+    let mut result = ParseMarkdownOptions::default();
+    result
+        .disable_code_block_output(true)
+        .link_rendering(LinkRendering::TextOnly)
+        .block_kinds(Some(markwrite_options.grammar_blocks()).filter(|value| !value.is_empty()));
+    result
 }
 
-type CombinedGrammarCheckChunkResults =
-    Result<Vec<GrammarCheckResult>, Box<(dyn std::error::Error)>>;
+/// Grammar check results for each of `regions`, paired with the region index, in the order grammar
+/// server responses arrive. Text offsets are shifted from each chunk into the plaintext of its
+/// region, and issues found by both of two overlapping chunks are only yielded once. Chunks whose
+/// check fails yield nothing.
+fn region_grammar_check_stream<'a>(
+    regions: &[LanguageRegion],
+    grammar_checker: &'a GrammarChecker,
+    markwrite_options: &MarkwriteOptions,
+) -> impl Stream<Item = (usize, GrammarCheckResult)> + 'a {
+    let chunk_checks: FuturesUnordered<_> = regions
+        .iter()
+        .enumerate()
+        .flat_map(|(region_index, region)| {
+            let language = region.language().map(ToString::to_string);
+            let alt_text = region.is_alt_text();
+            grammar_check_chunks(
+                region.text(),
+                GRAMMAR_CHECK_CHUNK_SIZE,
+                markwrite_options.grammar_chunk_overlap(),
+                markwrite_options.sentence_segmentation(),
+                markwrite_options.sentence_terminators(),
+            )
+            .into_iter()
+            .map(move |(chunk_start, chunk)| {
+                trace!(
+                    "Chunk ({}): {chunk}\nlines: {}, characters: {}",
+                    language.as_deref().unwrap_or("default language"),
+                    chunk.split('\n').count(),
+                    chunk.len()
+                );
+                let chunk = chunk.to_string();
+                let language = language.clone();
+                async move {
+                    let results = grammar_checker
+                        .check_chunk(&chunk, language.as_deref())
+                        .await
+                        .unwrap_or_default();
+                    results
+                        .into_iter()
+                        .map(|mut result| {
+                            result.shift_text_offset(chunk_start);
+                            if alt_text {
+                                result.set_alt_text();
+                            }
+                            (region_index, result)
+                        })
+                        .collect::<Vec<(usize, GrammarCheckResult)>>()
+                }
+            })
+        })
+        .collect();
 
-async fn grammar_check(markdown: &str, path: &str, stdout_handle: &mut impl Write) {
-    let grammar_checker = GrammarChecker::new(None);
-    let mut markdown_options = ParseMarkdownOptions::default();
-    markdown_options.disable_code_block_output(true);
-    let plain_text = parse_markdown_to_plaintext(markdown, &markdown_options);
+    let mut seen = HashSet::new();
+    chunk_checks
+        .flat_map(stream::iter)
+        .filter(move |(region_index, result)| {
+            // Both chunks report issues in the text they overlap on, so only keep the first. Offsets
+            // are in bytes, rather than the UTF-16 code units the server counts, so they match
+            // whichever chunk an issue came from.
+            future::ready(seen.insert((
+                *region_index,
+                result.text_offset(),
+                result.rule_id().to_string(),
+            )))
+        })
+}
 
-    let mut start: usize = 0;
-    let chunk_size = 1500;
-    let plain_text_length = plain_text.len();
-    let mut end: usize = cmp::min(plain_text_length, chunk_size);
-    let mut result_futures_vec: Vec<Box<dyn Future<Output = CombinedGrammarCheckChunkResults>>> =
-        vec![];
+/// Byte range in the source of the flagged text of `result`, found in `region` of the markdown
+/// starting `markdown_start` bytes into the source
+fn result_source_range(
+    result: &GrammarCheckResult,
+    region: &LanguageRegion,
+    markdown_start: usize,
+) -> Option<Range<usize>> {
+    let text_start = result.text_offset();
+    region
+        .source_range(text_start..text_start + result.text_length())
+        .map(|range| range.start + markdown_start..range.end + markdown_start)
+}
 
-    writeln!(
-        stdout_handle,
-        "[ INFO ] Checking text spelling, punctuation and grammar..."
+/// Spelling and grammar issues in `markdown`, with any frontmatter already stripped, yielded as
+/// grammar server responses arrive, so editors can show results before the whole document is
+/// checked. Text offsets are into the plaintext of the language region an issue is in, and source
+/// ranges into `markdown`. Collecting the stream gives the same issues a full check reports, in
+/// arrival order.
+///
+/// # Errors
+/// Errors with a message listing supported languages, if a language set by a marker is not
+/// supported by the grammar server
+pub async fn grammar_check_stream<'a>(
+    markdown: &str,
+    grammar_checker: &'a GrammarChecker,
+    markwrite_options: &MarkwriteOptions,
+) -> Result<impl Stream<Item = GrammarCheckResult> + 'a, String> {
+    let regions =
+        parse_markdown_to_plaintext_regions(markdown, &grammar_markdown_options(markwrite_options));
+    check_region_languages(&regions, grammar_checker).await?;
+    Ok(
+        region_grammar_check_stream(&regions, grammar_checker, markwrite_options).map(
+            move |(region_index, mut result)| {
+                result.set_source_range(result_source_range(&result, &regions[region_index], 0));
+                result
+            },
+        ),
     )
-    .expect("Expected to be able to write to stdout");
+}
 
-    while start < plain_text_length {
-        let (chunk, trimmed_chunk_end) = strip_trailing_sentence_stub(&plain_text[start..end]);
-        trace!(
-            "Chunk: {chunk}\nlines: {}, characters: {}",
-            chunk.split('\n').collect::<Vec<&str>>().len(),
-            chunk.len()
-        );
-        let chunk_results = grammar_checker.check_chunk(chunk);
-        result_futures_vec.push(Box::new(chunk_results));
+/// Writes grammar check `results`, found in the input at `path`, in `format`
+fn write_grammar_report(
+    results: &[GrammarCheckResult],
+    positions: &[Option<SourcePosition>],
+    path: &str,
+    format: GrammarReportFormat,
+    stdout_handle: &mut impl Write,
+) {
+    match format {
+        GrammarReportFormat::Human => {
+            display_grammar_check_results(results, positions, path, stdout_handle);
+        }
+        GrammarReportFormat::Json => {
+            writeln!(stdout_handle, "{}", json_report(results, positions, path))
+                .expect("Expected to be able to write to stdout");
+        }
+        GrammarReportFormat::Sarif => {
+            writeln!(stdout_handle, "{}", sarif_report(results, positions, path))
+                .expect("Expected to be able to write to stdout");
+        }
+    }
+}
+
+/// Checks `markdown`, the body of `source` after any frontmatter, and writes results in the
+/// report format set in `markwrite_options`
+async fn grammar_check(
+    markdown: &str,
+    source: &str,
+    path: &str,
+    grammar_checker: &GrammarChecker,
+    markwrite_options: &MarkwriteOptions,
+    stdout_handle: &mut impl Write,
+) -> Vec<GrammarCheckResult> {
+    let format = markwrite_options.grammar_report_format();
+    let markdown_options = grammar_markdown_options(markwrite_options);
+    // Each region is chunked separately, so every chunk is checked in a single language
+    let regions = parse_markdown_to_plaintext_regions(markdown, &markdown_options);
+    if let Err(message) = check_region_languages(&regions, grammar_checker).await {
+        eprintln!("[ ERROR ] {message}.");
+        return Vec::new();
+    }
 
-        start += trimmed_chunk_end;
-        end = cmp::min(plain_text_length, start + chunk_size);
+    if format == GrammarReportFormat::Human {
+        writeln!(
+            stdout_handle,
+            "[ INFO ] Checking text spelling, punctuation and grammar..."
+        )
+        .expect("Expected to be able to write to stdout");
         stdout_handle.flush().expect("Unable to flush to stdout");
     }
-    let mut combined_grammar_check_results: Vec<GrammarCheckResult> = Vec::new();
-    for result in result_futures_vec {
-        let result_values = Pin::from(result).await;
-        if let Ok(mut value) = result_values {
-            combined_grammar_check_results.append(&mut value);
-        }
+
+    let mut region_results: Vec<(usize, GrammarCheckResult)> =
+        region_grammar_check_stream(&regions, grammar_checker, markwrite_options)
+            .collect()
+            .await;
+    region_results.sort_by_key(|(region_index, result)| (*region_index, result.text_offset()));
+    let markdown_start = split_frontmatter(source).1.start;
+    let combined_grammar_check_results: Vec<GrammarCheckResult> = region_results
+        .into_iter()
+        .map(|(region_index, mut result)| {
+            let region = &regions[region_index];
+            if let Some(width) = markwrite_options.grammar_context_width() {
+                result.expand_context(region.text(), width);
+            }
+            result.set_source_range(result_source_range(&result, region, markdown_start));
+            result
+        })
+        .collect();
+    let positions = source_positions(&combined_grammar_check_results, source);
+    write_grammar_report(
+        &combined_grammar_check_results,
+        &positions,
+        path,
+        format,
+        stdout_handle,
+    );
+    for warning in grammar_checker.take_warnings() {
+        writeln!(stdout_handle, "[ WARN ] {warning}")
+            .expect("Expected to be able to write to stdout");
+    }
+    stdout_handle.flush().expect("Unable to flush to stdout");
+    combined_grammar_check_results
+}
+
+/// Applies each spelling fix with a single suggestion from `results` to `source`, the contents of
+/// the file at `path`, with `markdown` its body. Code, raw HTML and words in `dictionary` are never
+/// changed. The original source is kept alongside, with a `.bak` extension added, before the fixed
+/// source is written.
+fn autofix_spelling(
+    path: &Path,
+    source: &str,
+    markdown: &str,
+    results: &[GrammarCheckResult],
+    dictionary: &HashSet<String>,
+    stdout_handle: &mut impl Write,
+) -> std::io::Result<()> {
+    let body_start = split_frontmatter(source).1.start;
+    let protected_ranges: Vec<_> = code_ranges(markdown)
+        .into_iter()
+        .map(|range| range.start + body_start..range.end + body_start)
+        .collect();
+    let fixes = autofixes(results, source, &protected_ranges, dictionary);
+    if fixes.is_empty() {
+        return Ok(());
+    }
+
+    let mut fixed_source = source.to_string();
+    for (range, replacement) in fixes.iter().rev() {
+        fixed_source.replace_range(range.clone(), replacement);
+    }
+    let file_name = path
+        .file_name()
+        .map(|value| value.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let backup_path = path.with_file_name(format!("{file_name}.bak"));
+    write_output_file(&backup_path, source)?;
+    write_output_file(path, &fixed_source)?;
+    for (range, replacement) in &fixes {
+        let line = source[..range.start].matches('\n').count() + 1;
+        writeln!(
+            stdout_handle,
+            "[ INFO ] Fixed spelling on line {line}: {} -> {replacement}.",
+            &source[range.clone()]
+        )?;
     }
-    display_grammar_check_results(&combined_grammar_check_results, path, stdout_handle);
+    writeln!(
+        stdout_handle,
+        "[ INFO ] Saved the original source to {}.",
+        backup_path.display()
+    )
 }
 
-#[derive(Deserialize, PartialEq, Debug)]
+#[derive(Clone, Default, Deserialize, PartialEq, Debug)]
 pub struct Frontmatter {
     title: Option<String>,
     description: Option<String>,
     canonical_url: Option<String>,
+    stylesheet: Option<Vec<String>>,
+    draft: Option<bool>,
+
+    /// Title for Open Graph and Twitter social share tags, when it differs from `title`
+    og_title: Option<String>,
+
+    /// Name of the template, in the templates directory, to render the document with
+    layout: Option<String>,
+
+    /// Root URL of the site, such as `https://example.com`, which root-relative links and the
+    /// canonical URL are resolved against, unless one is given on the command line
+    site_url: Option<String>,
+
+    author: Option<String>,
+
+    /// Authors of co-authored documents, listed after any single `author`
+    authors: Option<Vec<String>>,
+
+    /// Publication date, such as `2024-05-01`, which directory indexes are sorted by
+    date: Option<String>,
+}
+
+impl Frontmatter {
+    #[must_use]
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    #[must_use]
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    #[must_use]
+    pub fn canonical_url(&self) -> Option<&str> {
+        self.canonical_url.as_deref()
+    }
+
+    /// Extra stylesheets for the document, from a single path or a list of paths
+    #[must_use]
+    pub fn stylesheet(&self) -> Option<&[String]> {
+        self.stylesheet.as_deref()
+    }
+
+    #[must_use]
+    pub fn draft(&self) -> Option<bool> {
+        self.draft
+    }
+
+    #[must_use]
+    pub fn og_title(&self) -> Option<&str> {
+        self.og_title.as_deref()
+    }
+
+    /// Document authors: any single `author`, followed by each of `authors` not already listed
+    #[must_use]
+    pub fn authors(&self) -> Vec<&str> {
+        let mut result: Vec<&str> = self.author.as_deref().into_iter().collect();
+        for value in self.authors.iter().flatten() {
+            if !result.contains(&value.as_str()) {
+                result.push(value);
+            }
+        }
+        result
+    }
+
+    #[must_use]
+    pub fn layout(&self) -> Option<&str> {
+        self.layout.as_deref()
+    }
+
+    #[must_use]
+    pub fn date(&self) -> Option<&str> {
+        self.date.as_deref()
+    }
+
+    #[must_use]
+    pub fn site_url(&self) -> Option<&str> {
+        self.site_url.as_deref()
+    }
+
+    /// Sets the field named `key`, as it would be written in frontmatter YAML, to `value`
+    ///
+    /// # Errors
+    /// Errors with a message when `key` is not a frontmatter field, or `value` is not valid for it
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let text = Some(value.to_string());
+        match key {
+            "title" => self.title = text,
+            "description" => self.description = text,
+            "canonical_url" => self.canonical_url = text,
+            "stylesheet" => self.stylesheet = Some(vec![value.to_string()]),
+            "draft" => {
+                self.draft = Some(value.parse().map_err(|_| {
+                    format!("Invalid frontmatter draft value ({value}), expected true or false")
+                })?);
+            }
+            "og_title" => self.og_title = text,
+            "layout" => self.layout = text,
+            "site_url" => self.site_url = text,
+            "author" => self.author = text,
+            "authors" => {
+                self.authors = Some(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|author| !author.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                );
+            }
+            "date" => self.date = text,
+            _ => {
+                return Err(format!(
+                    "Unknown frontmatter key ({key}), expected one of {}",
+                    FRONTMATTER_KEYS.join(", ")
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Frontmatter parsed from `frontmatter_yaml`, with any overrides set in `options` applied, and the
+/// parse error message, if any. Overrides still apply when the YAML is invalid.
+fn document_frontmatter(
+    frontmatter_yaml: Option<&str>,
+    options: &MarkwriteOptions,
+) -> (Frontmatter, Option<String>) {
+    let (mut frontmatter, error) = match parse_frontmatter_yaml(frontmatter_yaml) {
+        Ok(value) => (value, None),
+        Err(message) => (Frontmatter::default(), Some(message)),
+    };
+    options.apply_frontmatter_overrides(&mut frontmatter);
+    (frontmatter, error)
 }
 
+/// Frontmatter fields which can be set from outside the document
+const FRONTMATTER_KEYS: [&str; 11] = [
+    "title",
+    "description",
+    "canonical_url",
+    "stylesheet",
+    "draft",
+    "og_title",
+    "layout",
+    "site_url",
+    "author",
+    "authors",
+    "date",
+];
+
 #[derive(Template)]
 #[template(path = "template.html")]
 struct HtmlTemplate<'a> {
+    authors: &'a [&'a str],
     canonical_url: Option<&'a str>,
     description: Option<&'a str>,
+    extra_css: Option<&'a str>,
     global_css: &'a str,
     language: &'a str,
     live_reload_script: &'a str,
@@ -191,17 +864,67 @@ struct HtmlTemplate<'a> {
     prism_dark_theme_css: &'a str,
     prism_light_theme_css: &'a str,
     prism_script: &'a str,
+
+    /// Escaped markdown source, embedded in a non-rendered script element when enabled
+    embedded_source: Option<&'a str>,
+
+    /// Headings linked from the "On this page" sidebar, when it is enabled
+    sidebar_headings: Option<&'a [&'a Heading]>,
+
+    /// Title for Open Graph and Twitter social share tags
+    social_title: &'a str,
     theme_script: &'a str,
     title: &'a str,
 }
 
-fn html_document(main_section_html: &str, frontmatter: &Frontmatter) -> String {
+/// "On this page" sidebar linking `sidebar_headings`, shared by the built-in template and the
+/// `sidebar_toc` layout template placeholder
+#[derive(Template)]
+#[template(path = "sidebar_toc.html")]
+struct SidebarTocTemplate<'a> {
+    sidebar_headings: &'a [&'a Heading],
+}
+
+/// Document title from the frontmatter, falling back to the first `h1` heading, then the configured
+/// default title and finally a generic title.
+fn document_title<'a>(
+    frontmatter: &'a Frontmatter,
+    headings: &'a [Heading],
+    default_title: Option<&'a str>,
+) -> &'a str {
+    if let Some(value) = &frontmatter.title {
+        return value;
+    }
+    if let Some(value) = headings.iter().find(|heading| heading.level() == 1) {
+        return value.text();
+    }
+    default_title.unwrap_or("Markwrite Document")
+}
+
+/// Absolute canonical URL for the document, from the frontmatter `canonical_url`, with a relative
+/// value resolved against the canonical root
+///
+/// # Errors
+/// Errors with a message when the URL is invalid, or relative with no canonical root
+fn document_canonical_url(
+    frontmatter: &Frontmatter,
+    options: &ParseInputOptions,
+) -> Result<Option<String>, String> {
+    frontmatter
+        .canonical_url
+        .as_deref()
+        .map(|value| absolute_canonical_url(value, options.canonical_root_url.as_deref()))
+        .transpose()
+}
+fn html_document(
+    main_section_html: &str,
+    frontmatter: &Frontmatter,
+    headings: &[Heading],
+    embedded_source: Option<&str>,
+    options: &ParseInputOptions,
+) -> String {
     let language = "en";
-    let Frontmatter {
-        canonical_url,
-        description,
-        title,
-    } = frontmatter;
+    let Frontmatter { description, .. } = frontmatter;
     let live_reload_script = &String::from_utf8_lossy(include_bytes!("./resources/live_reload.js"));
     let prism_dark_theme_css =
         &String::from_utf8_lossy(include_bytes!("./resources/prism-one-dark.css"));
@@ -210,14 +933,23 @@ fn html_document(main_section_html: &str, frontmatter: &Frontmatter) -> String {
     let prism_script = &String::from_utf8_lossy(include_bytes!("./resources/prism.js"));
     let global_css = &String::from_utf8_lossy(include_bytes!("./resources/styles.css"));
     let theme_script = &String::from_utf8_lossy(include_bytes!("./resources/theme.js"));
-    let title = match title {
-        Some(value) => value,
-        None => "Markwrite Document",
-    };
+    let title = document_title(frontmatter, headings, options.default_title.as_deref());
+    let social_title = frontmatter.og_title.as_deref().unwrap_or(title);
+    // The page title `h1` is left out of the sidebar, and only headings with ids are passed in
+    let sidebar_headings: Vec<&Heading> = headings
+        .iter()
+        .filter(|heading| heading.level() > 1)
+        .collect();
+    // Invalid canonical URLs are reported with the parse warnings, and left out of the document
+    let canonical_url = document_canonical_url(frontmatter, options).ok().flatten();
 
+    let authors = frontmatter.authors();
     let html = HtmlTemplate {
+        authors: &authors,
         canonical_url: canonical_url.as_deref(),
         description: description.as_deref(),
+        embedded_source,
+        extra_css: options.extra_css.as_deref(),
         global_css,
         language,
         live_reload_script,
@@ -225,43 +957,533 @@ fn html_document(main_section_html: &str, frontmatter: &Frontmatter) -> String {
         prism_dark_theme_css,
         prism_light_theme_css,
         prism_script,
+        sidebar_headings: if options.sidebar_toc && !sidebar_headings.is_empty() {
+            Some(&sidebar_headings)
+        } else {
+            None
+        },
+        social_title,
         theme_script,
         title,
     };
+    if let Some(layout_template) = &options.layout_template {
+        return render_layout_template(layout_template, &html);
+    }
     html.render().unwrap()
 }
 
-#[must_use]
-pub fn markdown_to_processed_html(
-    markdown: &str,
-    frontmatter: &Frontmatter,
-    options: &ParseInputOptions,
-) -> ParseResults {
-    match parse_markdown_to_html(markdown) {
-        Ok((html_value, headings, statistics_value)) => {
-            let main_section_html = process_html(
-                &html_value,
-                options.canonical_root_url.as_deref(),
-                options.search_term.as_deref(),
-            );
-            let html = Some(html_document(&main_section_html, frontmatter));
-            let headings = Some(headings);
-            let statistics = Some(statistics_value);
-            ParseResults {
-                html,
-                headings,
-                statistics,
-                errors: None,
-            }
+/// Placeholders a layout template can use, named after the built-in template values
+const LAYOUT_PLACEHOLDERS: [&str; 16] = [
+    "authors",
+    "canonical_url",
+    "description",
+    "embedded_source",
+    "extra_css",
+    "global_css",
+    "language",
+    "live_reload_script",
+    "main_section_html",
+    "prism_dark_theme_css",
+    "prism_light_theme_css",
+    "prism_script",
+    "sidebar_toc",
+    "social_title",
+    "theme_script",
+    "title",
+];
+
+/// Names of the `{{ name }}` placeholders in `layout_template`, in the order they appear
+fn layout_placeholders(layout_template: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut rest = layout_template;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}") else {
+            break;
+        };
+        result.push(rest[start + 2..start + length].trim());
+        rest = &rest[start + length + 2..];
+    }
+    result
+}
+
+/// Names of the `{{ name }}` placeholders in `layout_template` which are not in
+/// [`LAYOUT_PLACEHOLDERS`], in the order they appear
+fn unknown_layout_placeholders(layout_template: &str) -> Vec<&str> {
+    layout_placeholders(layout_template)
+        .into_iter()
+        .filter(|name| !LAYOUT_PLACEHOLDERS.contains(name))
+        .collect()
+}
+
+/// Checks each `.html` layout template in `templates_dir` only uses known placeholders, so a
+/// mistyped placeholder is reported before any document is rendered, rather than left in the
+/// output
+///
+/// # Errors
+/// Errors with a message naming the template and its first unknown placeholder, or when the
+/// directory or a template cannot be read
+pub fn validate_layout_templates<P: AsRef<Path>>(templates_dir: &P) -> Result<(), String> {
+    let templates_dir = templates_dir.as_ref();
+    let read_error = |path: &Path, error: std::io::Error| {
+        format!(
+            "Unable to read layout templates ({}): {error}",
+            path.display()
+        )
+    };
+    let mut template_paths = std::fs::read_dir(templates_dir)
+        .map_err(|error| read_error(templates_dir, error))?
+        .filter_map(|entry| entry.ok().map(|value| value.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|value| value == "html"))
+        .collect::<Vec<PathBuf>>();
+    template_paths.sort();
+    for template_path in template_paths {
+        let layout_template =
+            read_to_string(&template_path).map_err(|error| read_error(&template_path, error))?;
+        if let Some(name) = unknown_layout_placeholders(&layout_template).first() {
+            return Err(format!(
+                "Unknown placeholder {{{{ {name} }}}} in layout template ({}), expected one of {}",
+                template_path.display(),
+                LAYOUT_PLACEHOLDERS.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Renders a layout template, replacing each `{{ name }}` placeholder with the built-in template
+/// value of the same name. Text values, such as `title`, are HTML escaped, while HTML, CSS and
+/// script values, such as `main_section_html`, are inserted as they are. The `embedded_source`
+/// and `sidebar_toc` placeholders give the same elements as the built-in template, or nothing when
+/// those options are off. Unknown placeholders are left unchanged.
+fn render_layout_template(layout_template: &str, values: &HtmlTemplate) -> String {
+    let text = |value: &str| askama::MarkupDisplay::new_unsafe(value, askama::Html).to_string();
+    let mut result = String::with_capacity(layout_template.len());
+    let mut rest = layout_template;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}") else {
+            break;
+        };
+        let placeholder = &rest[start..start + length + 2];
+        result.push_str(&rest[..start]);
+        match placeholder[2..placeholder.len() - 2].trim() {
+            "authors" => result.push_str(&text(&author_list(values.authors))),
+            "canonical_url" => result.push_str(&text(values.canonical_url.unwrap_or_default())),
+            "description" => result.push_str(&text(values.description.unwrap_or_default())),
+            "embedded_source" => {
+                if let Some(value) = values.embedded_source {
+                    result.push_str(EMBEDDED_SOURCE_START);
+                    result.push_str(value);
+                    result.push_str("</script>");
+                }
+            }
+            "extra_css" => result.push_str(values.extra_css.unwrap_or_default()),
+            "global_css" => result.push_str(values.global_css),
+            "language" => result.push_str(&text(values.language)),
+            "live_reload_script" => result.push_str(values.live_reload_script),
+            "main_section_html" => result.push_str(values.main_section_html),
+            "prism_dark_theme_css" => result.push_str(values.prism_dark_theme_css),
+            "prism_light_theme_css" => result.push_str(values.prism_light_theme_css),
+            "prism_script" => result.push_str(values.prism_script),
+            "sidebar_toc" => {
+                if let Some(sidebar_headings) = values.sidebar_headings {
+                    result.push_str(&SidebarTocTemplate { sidebar_headings }.render().unwrap());
+                }
+            }
+            "social_title" => result.push_str(&text(values.social_title)),
+            "theme_script" => result.push_str(values.theme_script),
+            "title" => result.push_str(&text(values.title)),
+            _ => result.push_str(placeholder),
+        }
+        rest = &rest[start + placeholder.len()..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Opening tag of the script element holding the embedded markdown source
+const EMBEDDED_SOURCE_START: &str = r#"<script type="text/markdown" id="markdown-source">"#;
+
+/// Replaces each `<` in `text` starting `</script`, in any case, or `<!--` with `<\`, followed by
+/// the `/` or `!`, or does the reverse with `unescape`
+fn replace_script_closers(text: &str, unescape: bool) -> String {
+    let (closer, comment, escape_length) = if unescape {
+        ("<\\/script", "<\\!--", 2)
+    } else {
+        ("</script", "<!--", 1)
+    };
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('<') {
+        result.push_str(&rest[..index]);
+        let tail = &rest[index..];
+        let matches_closer = tail
+            .get(..closer.len())
+            .is_some_and(|value| value.eq_ignore_ascii_case(closer));
+        if matches_closer || tail.starts_with(comment) {
+            result.push_str(if unescape { "<" } else { "<\\" });
+            rest = &tail[escape_length..];
+        } else {
+            result.push('<');
+            rest = &tail[1..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Escapes `</script`, in any case, and `<!--` in markdown source as `<\/script` and `<\!--`, so it
+/// cannot close the script element it is embedded in. Script content is raw text, so the rest of
+/// the source is kept as it is, and reads back unchanged from the element's `textContent`.
+fn escape_embedded_source(markdown: &str) -> String {
+    replace_script_closers(markdown, false)
+}
+
+/// Markdown source embedded in `html` with the `--embed-source` option, unescaped to match the
+/// original document body
+#[must_use]
+pub fn embedded_source(html: &str) -> Option<String> {
+    let (_, after_start) = html.split_once(EMBEDDED_SOURCE_START)?;
+    let (escaped_source, _) = after_start.split_once("</script>")?;
+    Some(replace_script_closers(escaped_source, true))
+}
+
+/// Opening of the comment holding frontmatter kept in the output
+const FRONTMATTER_COMMENT_START: &str = "<!-- frontmatter";
+
+/// Escapes `&`, then `-->` and `--!>`, in frontmatter YAML, so it cannot close the comment it is
+/// kept in
+fn escape_frontmatter_comment(yaml: &str) -> String {
+    yaml.replace('&', "&amp;")
+        .replace("-->", "--&gt;")
+        .replace("--!>", "--!&gt;")
+}
+
+/// Frontmatter YAML kept in `html` with the `--include-frontmatter-in-output` option, unescaped to
+/// match the original frontmatter
+#[must_use]
+pub fn output_frontmatter(html: &str) -> Option<String> {
+    let (_, after_start) = html.split_once(FRONTMATTER_COMMENT_START)?;
+    let (escaped_yaml, _) = after_start.split_once("-->")?;
+    Some(
+        escaped_yaml
+            .trim()
+            .replace("--!&gt;", "--!>")
+            .replace("--&gt;", "-->")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// Renders `markdown` to processed content HTML only, without the full document, head, styles or
+/// scripts, for injecting into another template. Any frontmatter is stripped.
+#[must_use]
+pub fn render_fragment(markdown: &str) -> Option<String> {
+    let (_frontmatter_yaml, markdown) = strip_frontmatter(markdown);
+    let options = ParseInputOptions {
+        fragment: true,
+        ..ParseInputOptions::default()
+    };
+    markdown_to_processed_html(markdown, &Frontmatter::default(), &options).html
+}
+
+/// Shared HTML or markdown content, such as a site header or footer, added to every document
+#[derive(Clone, Debug)]
+enum Snippet {
+    Html(String),
+    Markdown(String),
+}
+
+impl Snippet {
+    /// Reads the snippet at `path`, which is markdown when it has a `.md` or `.markdown`
+    /// extension, and HTML otherwise
+    fn load(path: &Path) -> std::io::Result<Snippet> {
+        let content = read_to_string(path)?;
+        let markdown = path.extension().is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+        });
+        Ok(if markdown {
+            Snippet::Markdown(content)
+        } else {
+            Snippet::Html(content)
+        })
+    }
+
+    /// Snippet HTML, with markdown parsed the same way as the document body
+    fn html(&self, markdown_options: &ParseMarkdownOptions) -> String {
+        match self {
+            Snippet::Html(value) => value.clone(),
+            Snippet::Markdown(value) => parse_markdown_to_html(value, markdown_options)
+                .map(|(html, _headings, _statistics)| html)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Adds any header before, and footer after, the document content in `main_section_html`
+fn add_snippets(
+    main_section_html: &mut String,
+    options: &ParseInputOptions,
+    markdown_options: &ParseMarkdownOptions,
+) {
+    if let Some(value) = &options.header {
+        main_section_html.insert_str(0, &value.html(markdown_options));
+    }
+    if let Some(value) = &options.footer {
+        main_section_html.push_str(&value.html(markdown_options));
+    }
+}
+
+/// Reads the snippet at `path`, returning `None`, with a warning, when it cannot be read, so the
+/// document is still written without it
+fn load_snippet(path: &Path, name: &str, stdout_handle: &mut impl Write) -> Option<Snippet> {
+    match Snippet::load(path) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            writeln!(
+                stdout_handle,
+                "[ WARN ] Unable to read {name} ({}): {error}.",
+                path.display()
+            )
+            .expect("Expected to be able to write to stdout");
+            None
+        }
+    }
+}
+
+/// Authors joined for display, such as `Ada, Brian and Chen`
+fn author_list(authors: &[&str]) -> String {
+    match authors {
+        [] => String::new(),
+        [author] => (*author).to_string(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+/// Byline showing any document authors, the word count and estimated reading time
+fn byline_html(authors: &[&str], statistics: &TextStatistics) -> String {
+    let word_count = statistics.word_count();
+    let words = if word_count == 1 { "word" } else { "words" };
+    let by = if authors.is_empty() {
+        String::new()
+    } else {
+        let author_list = author_list(authors);
+        format!(
+            "By {} · ",
+            askama::MarkupDisplay::new_unsafe(&author_list, askama::Html)
+        )
+    };
+    format!(
+        r#"<div class="markwrite-byline">{by}{word_count} {words} · {} min read</div>"#,
+        statistics.reading_time()
+    )
+}
+
+/// Warnings for headings which would share an id, since only the first would be reachable as a
+/// link target.
+fn duplicate_heading_warnings(headings: &[Heading]) -> Vec<String> {
+    let mut seen_ids: HashSet<&str> = HashSet::new();
+    let mut warnings: Vec<String> = Vec::new();
+    for heading in headings {
+        let id = heading.id();
+        if !seen_ids.insert(id) {
+            warnings.push(format!("Duplicate heading id: {id}"));
+        }
+    }
+    warnings
+}
+
+/// Lint warnings for headings more than one level deeper than the heading before them, such as an
+/// `h4` straight after an `h2`, giving source line numbers. The first heading may be any level.
+fn skipped_heading_level_warnings(
+    markdown: &str,
+    headings: &[Heading],
+    line_offset: usize,
+) -> Vec<String> {
+    headings
+        .windows(2)
+        .filter(|pair| pair[1].level() > pair[0].level() + 1)
+        .map(|pair| {
+            let (previous, heading) = (&pair[0], &pair[1]);
+            let line_number =
+                markdown[..heading.source_offset()].matches('\n').count() + line_offset + 1;
+            format!(
+                "Heading level skipped on line {line_number}: h{} follows h{}",
+                heading.level(),
+                previous.level()
+            )
+        })
+        .collect()
+}
+
+/// Lint warnings for sentences with more than `max_words` words, giving source line numbers
+fn long_sentence_warnings(
+    markdown: &str,
+    statistics: &TextStatistics,
+    max_words: u32,
+    line_offset: usize,
+) -> Vec<String> {
+    statistics
+        .long_sentences(max_words)
+        .map(|(offset, words)| {
+            let line_number = markdown[..offset].matches('\n').count() + line_offset + 1;
+            format!("Sentence of {words} words on line {line_number}, longer than {max_words}")
+        })
+        .collect()
+}
+
+/// Post-processes HTML rendered from markdown, returning it with any warnings raised
+fn process_html_value(html: &str, options: &ParseInputOptions) -> (String, Vec<String>) {
+    process_html_with_warnings(
+        html,
+        options.canonical_root_url.as_deref(),
+        options.base_path.as_deref(),
+        options.images_base_url.as_deref(),
+        options.search_term.as_deref(),
+        options.embed_images.as_ref(),
+        options.downscale_images.as_ref(),
+        options.responsive_images.as_ref(),
+        options.internal_links.as_ref(),
+        &options.dom_hooks,
+        options.external_links_same_tab,
+        options.strip_comments,
+    )
+}
+
+/// Warnings about the document as a whole, from its markdown, frontmatter, headings and
+/// statistics, rather than its processed HTML
+fn document_warnings(
+    markdown: &str,
+    frontmatter: &Frontmatter,
+    headings: &[Heading],
+    statistics: &TextStatistics,
+    options: &ParseInputOptions,
+) -> Vec<String> {
+    let mut result = Vec::new();
+    // A document with only frontmatter renders as an almost empty page, usually by mistake
+    if markdown.trim().is_empty() {
+        result.push(String::from(
+            "Document has no content after the frontmatter",
+        ));
+    }
+    if options.lint {
+        result.extend(skipped_heading_level_warnings(
+            markdown,
+            headings,
+            options.source_line_offset,
+        ));
+        result.extend(long_sentence_warnings(
+            markdown,
+            statistics,
+            options
+                .max_sentence_words
+                .unwrap_or(DEFAULT_MAX_SENTENCE_WORDS),
+            options.source_line_offset,
+        ));
+    }
+    if !options.fragment {
+        result.extend(document_canonical_url(frontmatter, options).err());
+    }
+    result.extend(duplicate_heading_warnings(headings));
+    result
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Documents rendered on this thread, so tests can tell cached output from a fresh render
+    static RENDER_COUNT: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[must_use]
+pub fn markdown_to_processed_html(
+    markdown: &str,
+    frontmatter: &Frontmatter,
+    options: &ParseInputOptions,
+) -> ParseResults {
+    #[cfg(test)]
+    RENDER_COUNT.with(|count| count.set(count.get() + 1));
+    let mut markdown_options = ParseMarkdownOptions::default();
+    markdown_options
+        .smart_punctuation(options.smart_punctuation)
+        .emoji_shortcodes(options.emoji_shortcodes)
+        .subscript_superscript(options.subscript_superscript)
+        .hard_line_breaks(options.hard_line_breaks)
+        .max_heading_id_level(options.max_heading_id_level)
+        .number_headings(options.number_headings);
+    let lint_warnings = if options.lint {
+        lint_markdown(markdown, options.source_line_offset)
+    } else {
+        Vec::new()
+    };
+    let mut timings = StageTimings::default();
+    let parse_start = Instant::now();
+    let parse_result = parse_markdown_to_html(markdown, &markdown_options);
+    timings.parse = parse_start.elapsed();
+    debug!("Markdown parse took {:.3} ms", milliseconds(timings.parse));
+    match parse_result {
+        Ok((html_value, headings, statistics_value)) => {
+            let html_process_start = Instant::now();
+            let (mut main_section_html, html_warnings) = process_html_value(&html_value, options);
+            timings.html_process = html_process_start.elapsed();
+            debug!(
+                "HTML processing took {:.3} ms",
+                milliseconds(timings.html_process)
+            );
+            if options.byline {
+                main_section_html
+                    .insert_str(0, &byline_html(&frontmatter.authors(), &statistics_value));
+            }
+            add_snippets(&mut main_section_html, options, &markdown_options);
+            let html = if options.fragment {
+                Some(main_section_html)
+            } else {
+                let embedded_source = options
+                    .embed_source
+                    .then(|| escape_embedded_source(markdown));
+                Some(html_document(
+                    &main_section_html,
+                    frontmatter,
+                    &headings,
+                    embedded_source.as_deref(),
+                    options,
+                ))
+            };
+            let mut warnings = lint_warnings;
+            warnings.extend(document_warnings(
+                markdown,
+                frontmatter,
+                &headings,
+                &statistics_value,
+                options,
+            ));
+            warnings.extend(html_warnings);
+            let errors = if warnings.is_empty() {
+                None
+            } else {
+                Some(warnings)
+            };
+            let headings = Some(headings);
+            let statistics = Some(statistics_value);
+            ParseResults {
+                html,
+                headings,
+                statistics,
+                errors,
+                timings,
+            }
         }
         Err(error) => {
-            let message = format!("Error parsing markdown: {error}");
-            let errors = vec![message];
+            let (line, column) = error.line_column(markdown);
+            let message = format!(
+                "Error parsing markdown at line {}, column {column}: {error}",
+                line + options.source_line_offset
+            );
+            let mut errors = lint_warnings;
+            errors.push(message);
             ParseResults {
                 html: None,
                 headings: None,
                 statistics: None,
                 errors: Some(errors),
+                timings,
             }
         }
     }
@@ -290,389 +1512,5415 @@ fn add_word_to_dictionary<P: AsRef<Path>>(
     dictionary_file
         .write_all(new_word.as_bytes())
         .with_context(|| {
-            format!("[ ERROR ] Unable to write to dictionary file: {dictionary_display_path}",)
+            format!("[ ERROR ] Unable to write to dictionary file: {dictionary_display_path}")
         })
         .unwrap();
 }
 
+/// Grammar ignore file read from the working directory, unless another path is given
+pub const DEFAULT_GRAMMAR_IGNORE_PATH: &str = ".markwrite-grammar-ignore";
+
+/// Grammar server rule and category ids listed in the ignore file at `path`, one on each line.
+/// Blank lines, and comment lines starting with `#`, are skipped.
+///
+/// # Errors
+/// Errors if the file cannot be read
+pub fn load_grammar_ignore_file<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<String>> {
+    Ok(read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Adds words from the file at `dictionary_path` to `dictionary`, keeping any words already
+/// there, so several dictionaries can be merged. Returns the number of new words the file added.
 pub fn load_dictionary<P: AsRef<Path>, S: ::std::hash::BuildHasher>(
     dictionary_path: P,
     dictionary: &mut HashSet<String, S>,
     mut stdout_handle: impl Write,
-) {
+) -> usize {
+    let dictionary_display_path = dictionary_path.as_ref().display().to_string();
     let Ok(dictionary_file) = File::open(dictionary_path) else {
-        writeln!(stdout_handle, "[ INFO ] no dictionary file found.")
-            .expect("Expected to be able to stdout");
-        return;
+        writeln!(
+            stdout_handle,
+            "[ INFO ] no dictionary file found at {dictionary_display_path}."
+        )
+        .expect("Expected to be able to stdout");
+        return 0;
     };
 
     let reader = BufReader::new(&dictionary_file);
+    let mut result = 0;
     reader.lines().for_each(|line| {
         if let Ok(word_value) = line {
-            dictionary.insert(word_value);
-        };
+            if dictionary.insert(word_value) {
+                result += 1;
+            }
+        }
     });
+    writeln!(
+        stdout_handle,
+        "[ INFO ] Loaded {result} words from dictionary {dictionary_display_path}."
+    )
+    .expect("Expected to be able to stdout");
+    result
+}
+
+/// Adds the words of the dictionary bundled with markwrite, common technical terms, to
+/// `dictionary`. Returns the number of new words added.
+pub fn load_default_dictionary<S: ::std::hash::BuildHasher>(
+    dictionary: &mut HashSet<String, S>,
+    mut stdout_handle: impl Write,
+) -> usize {
+    let default_dictionary = String::from_utf8_lossy(include_bytes!("./resources/default.dict"));
+    let result = default_dictionary
+        .lines()
+        .filter(|word| dictionary.insert((*word).to_string()))
+        .count();
+    writeln!(
+        stdout_handle,
+        "[ INFO ] Loaded {result} words from the default dictionary."
+    )
+    .expect("Expected to be able to stdout");
+    result
+}
+
+/// Dictionary merged from the bundled default dictionary, unless `include_default` is false, and
+/// then each file in `dictionary_paths`, in order
+pub fn load_dictionaries<P: AsRef<Path>>(
+    dictionary_paths: &[P],
+    include_default: bool,
+    stdout_handle: &mut impl Write,
+) -> HashSet<String> {
+    let mut dictionary = HashSet::new();
+    if include_default {
+        load_default_dictionary(&mut dictionary, &mut *stdout_handle);
+    }
+    for dictionary_path in dictionary_paths {
+        load_dictionary(dictionary_path, &mut dictionary, &mut *stdout_handle);
+    }
+    dictionary
+}
+
+/// Splits YAML frontmatter from the document body. A leading UTF-8 byte order mark, and any
+/// whitespace before the opening `---` fence, are ignored.
+fn strip_frontmatter(input: &str) -> (Option<&str>, &str) {
+    let (frontmatter, body_range) = split_frontmatter(input);
+    (frontmatter, &input[body_range])
+}
+
+/// Frontmatter of `input`, as split by [`strip_frontmatter`], with the byte range of the document
+/// body in `input`, so positions in the body map back to the source
+fn split_frontmatter(input: &str) -> (Option<&str>, Range<usize>) {
+    let content_start = input.len() - input.strip_prefix('\u{feff}').unwrap_or(input).len();
+    let without_frontmatter = (None, content_start..input.len());
+    let fenced_input = input[content_start..].trim_start();
+    let Some(first_line) = fenced_input.lines().next() else {
+        return without_frontmatter;
+    };
+    if first_line.trim_end() != "---" {
+        return without_frontmatter;
+    }
+
+    let Some((_first_line, rest)) = fenced_input.split_once('\n') else {
+        return without_frontmatter;
+    };
+    let Some((frontmatter, body)) = rest.split_once("\n---") else {
+        return without_frontmatter;
+    };
+    let body_start = input.len() - body.trim_start().len();
+    (
+        Some(frontmatter.trim()),
+        body_start..body_start + body.trim().len(),
+    )
+}
+
+/// Error message for `input` when it opens a frontmatter fence, followed by a YAML field such as
+/// `title: Post Title`, but has no closing `---` fence. Without the closing fence, the frontmatter
+/// would be rendered as part of the document body. A leading thematic break, followed by text
+/// rather than a field, is not treated as frontmatter.
+fn unterminated_frontmatter(input: &str) -> Option<String> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut lines = input.trim_start().lines();
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+    let mut fields = 0;
+    for line in lines {
+        if line.starts_with("---") {
+            return None;
+        }
+        let is_field = line.split_once(':').is_some_and(|(key, _)| {
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|character| character.is_alphanumeric() || "_-".contains(character))
+        });
+        if fields == 0 && !line.trim().is_empty() && !is_field {
+            return None;
+        }
+        fields += usize::from(is_field);
+    }
+    (fields > 0).then(|| {
+        String::from("Unterminated frontmatter, add a `---` line to close the frontmatter fields")
+    })
+}
+
+/// Reads the markdown source at `path`, refusing documents with unterminated frontmatter, rather
+/// than rendering their frontmatter as the body
+fn read_source(path: &Path) -> Result<String, notify::Error> {
+    let source = read_to_string(path)?;
+    if let Some(message) = unterminated_frontmatter(&source) {
+        eprintln!("[ ERROR ] {message} ({}).", path.display());
+        return Err(notify::Error::generic(&message));
+    }
+    Ok(source)
+}
+
+/// Number of lines in `source` before its document body, left after stripping frontmatter
+fn line_offset(source: &str) -> usize {
+    source[..split_frontmatter(source).1.start]
+        .matches('\n')
+        .count()
+}
+
+/// Splits frontmatter from `input` and parses its fields, without rendering the document, returning
+/// them with the remaining body. Documents without frontmatter, or whose frontmatter is not valid
+/// YAML, give `None`.
+///
+/// ```
+/// let (frontmatter, body) =
+///     markwrite::parse_frontmatter("---\ntitle: Post Title\n---\n\n# Post Heading\n");
+///
+/// assert_eq!(frontmatter.unwrap().title(), Some("Post Title"));
+/// assert_eq!(body, "# Post Heading");
+/// ```
+#[must_use]
+pub fn parse_frontmatter(input: &str) -> (Option<Frontmatter>, &str) {
+    let (frontmatter_yaml, body) = strip_frontmatter(input);
+    let frontmatter = frontmatter_yaml.and_then(|value| parse_frontmatter_yaml(Some(value)).ok());
+    (frontmatter, body)
+}
+
+/// Reads and concatenates stylesheets, in order, for inlining into the output document head.
+/// Files which cannot be read, or which resolve to a location outside `project_root`, are
+/// skipped with a message.
+fn load_extra_css<P: AsRef<Path>>(
+    paths: &[P],
+    project_root: &Path,
+    stdout_handle: &mut impl Write,
+) -> Option<String> {
+    let Ok(canonical_project_root) = project_root.canonicalize() else {
+        writeln!(
+            stdout_handle,
+            "[ ERROR ] Unable to resolve the project root, skipping extra CSS."
+        )
+        .expect("Expected to be able to write to stdout");
+        return None;
+    };
+
+    let mut result = String::new();
+    for path in paths {
+        let display_path = path.as_ref().display().to_string();
+        let Ok(canonical_path) = path.as_ref().canonicalize() else {
+            writeln!(
+                stdout_handle,
+                "[ ERROR ] Unable to find stylesheet ({display_path}), skipping."
+            )
+            .expect("Expected to be able to write to stdout");
+            continue;
+        };
+        if !canonical_path.starts_with(&canonical_project_root) {
+            writeln!(
+                stdout_handle,
+                "[ ERROR ] Stylesheet ({display_path}) is outside the project root, skipping."
+            )
+            .expect("Expected to be able to write to stdout");
+            continue;
+        }
+        match read_to_string(&canonical_path) {
+            Ok(value) => {
+                if !result.is_empty() {
+                    result.push('\n');
+                }
+                result.push_str(&value);
+            }
+            Err(_) => {
+                writeln!(
+                    stdout_handle,
+                    "[ ERROR ] Unable to read stylesheet ({display_path}), skipping."
+                )
+                .expect("Expected to be able to write to stdout");
+            }
+        }
+    }
+
+    if result.is_empty() {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+/// Parses frontmatter fields, returning an error message when the YAML is invalid
+fn parse_frontmatter_yaml(frontmatter_yaml: Option<&str>) -> Result<Frontmatter, String> {
+    let Some(value) = frontmatter_yaml else {
+        return Ok(Frontmatter::default());
+    };
+    let frontmatter_value = YamlLoader::load_from_str(value)
+        .map_err(|error| format!("Unable to parse frontmatter: {error}"))?;
+    let Some(doc) = frontmatter_value.first() else {
+        return Ok(Frontmatter::default());
+    };
+
+    let title = doc["title"].as_str().map(std::string::ToString::to_string);
+    let description = doc["description"]
+        .as_str()
+        .map(std::string::ToString::to_string);
+    let canonical_url = doc["canonical_url"]
+        .as_str()
+        .map(std::string::ToString::to_string);
+    let stylesheet = match &doc["stylesheet"] {
+        Yaml::String(value) => Some(vec![value.clone()]),
+        Yaml::Array(values) => Some(
+            values
+                .iter()
+                .filter_map(|val| val.as_str().map(std::string::ToString::to_string))
+                .collect(),
+        ),
+        _ => None,
+    };
+    let draft = doc["draft"].as_bool();
+    let og_title = doc["og_title"]
+        .as_str()
+        .map(std::string::ToString::to_string);
+    let layout = doc["layout"].as_str().map(std::string::ToString::to_string);
+    let site_url = doc["site_url"]
+        .as_str()
+        .map(std::string::ToString::to_string);
+    let author = doc["author"].as_str().map(std::string::ToString::to_string);
+    let authors = match &doc["authors"] {
+        Yaml::String(value) => Some(vec![value.clone()]),
+        Yaml::Array(values) => Some(
+            values
+                .iter()
+                .filter_map(|val| val.as_str().map(std::string::ToString::to_string))
+                .collect(),
+        ),
+        _ => None,
+    };
+    let date = doc["date"].as_str().map(std::string::ToString::to_string);
+    Ok(Frontmatter {
+        title,
+        description,
+        canonical_url,
+        stylesheet,
+        draft,
+        og_title,
+        layout,
+        site_url,
+        author,
+        authors,
+        date,
+    })
+}
+
+#[derive(Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct MarkwriteOptions {
+    autofix: bool,
+    base_path: Option<String>,
+    byline: bool,
+    cache_dir: Option<PathBuf>,
+    canonical_root_url: Option<String>,
+    check_grammar: bool,
+    check_only: bool,
+    default_title: Option<String>,
+    disabled_grammar_categories: Vec<String>,
+    dom_hooks: Vec<DomHook>,
+    dump_plaintext_path: Option<PathBuf>,
+    embed_images: bool,
+    embed_images_max_size: Option<u64>,
+    embed_source: bool,
+    emoji_shortcodes: bool,
+    external_links_same_tab: bool,
+    extra_css_paths: Vec<PathBuf>,
+    footer_path: Option<PathBuf>,
+    fragment: bool,
+
+    /// Frontmatter keys and values which replace any set in each document, in the order given
+    frontmatter_overrides: Vec<(String, String)>,
+    grammar_api_credentials: Option<(String, String)>,
+    grammar_blocks: Vec<BlockKind>,
+    grammar_chunk_overlap: Option<usize>,
+    grammar_concurrency: Option<usize>,
+    grammar_context_width: Option<usize>,
+    grammar_preferred_variants: Vec<String>,
+    grammar_replacement_limit: Option<usize>,
+    grammar_report_format: GrammarReportFormat,
+    grammar_request_permits: OnceLock<Arc<Semaphore>>,
+
+    /// Grammar check requests sent so far by every checker configured from these options
+    grammar_requests_sent: Arc<AtomicUsize>,
+    grammar_url: Option<String>,
+    gzip: bool,
+    gzip_level: Option<u32>,
+    gzip_only: bool,
+    hard_line_breaks: bool,
+    header_path: Option<PathBuf>,
+    ignored_grammar_rules: Vec<String>,
+    images_base_url: Option<String>,
+    include_drafts: bool,
+    include_frontmatter_in_output: bool,
+    internal_link_patterns: Vec<String>,
+    languagetool_disabled: bool,
+    lint: bool,
+    max_grammar_requests: Option<usize>,
+    max_heading_id_level: Option<u8>,
+    max_image_height: Option<u32>,
+    max_image_width: Option<u32>,
+    max_sentence_words: Option<u32>,
+    min_language_confidence: Option<f64>,
+    number_headings: bool,
+
+    /// Command run on each output after it is written, such as a viewer or converter
+    open_in: Option<String>,
+    output_bom: bool,
+    output_line_endings: LineEnding,
+    output_mode: Option<u32>,
+    pretty: bool,
+    responsive_images: bool,
+    sentence_segmentation: SentenceSegmentation,
+    sentence_terminators: Option<Vec<char>>,
+    sidebar_toc: bool,
+    strict: bool,
+    strip_comments: bool,
+    subscript_superscript: bool,
+    templates_dir: Option<PathBuf>,
+    timings: bool,
 }
 
-fn strip_frontmatter(input: &str) -> (Option<&str>, &str) {
-    let mut lines = input.lines();
-    if let Some(first_line) = lines.next() {
-        if first_line.trim_end() != "---" {
-            return (None, input);
-        };
+impl MarkwriteOptions {
+    #[must_use]
+    pub fn autofix(&self) -> bool {
+        self.autofix
+    }
+
+    /// Apply spelling fixes with a single suggestion to the markdown source, after a grammar
+    /// check, keeping a copy of the original
+    pub fn enable_autofix(&mut self) {
+        self.autofix = true;
+    }
+
+    #[must_use]
+    pub fn base_path(&self) -> Option<&str> {
+        self.base_path.as_deref()
+    }
+
+    /// Prefix root-relative link and image URLs with `value`, for sites deployed to a subdirectory
+    pub fn set_base_path(&mut self, value: &str) {
+        self.base_path = Some(value.to_string());
+    }
+
+    #[must_use]
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.cache_dir.as_deref()
+    }
+
+    /// Keep rendered HTML in `value`, keyed by a hash of the source and options, and reuse it when
+    /// the same source is rendered with the same options
+    pub fn set_cache_dir<P: AsRef<Path>>(&mut self, value: P) {
+        self.cache_dir = Some(value.as_ref().to_path_buf());
+    }
+
+    #[must_use]
+    pub fn canonical_root_url(&self) -> Option<&str> {
+        self.canonical_root_url.as_deref()
+    }
+
+    /// Resolve root-relative links and canonical URLs against `value`, such as
+    /// `https://example.com`, in place of any frontmatter `site_url`
+    pub fn set_canonical_root_url(&mut self, value: &str) {
+        self.canonical_root_url = Some(value.to_string());
+    }
+
+    /// Whether spelling and grammar are checked, which is never the case with `LanguageTool`
+    /// disabled, even when checks are enabled
+    #[must_use]
+    pub fn check_grammar(&self) -> bool {
+        self.check_grammar && !self.languagetool_disabled
+    }
+
+    pub fn enable_grammar_check(&mut self) {
+        self.check_grammar = true;
+    }
+
+    #[must_use]
+    pub fn check_only(&self) -> bool {
+        self.check_only
+    }
+
+    /// Run every check and report problems, without writing any output file
+    pub fn enable_check_only(&mut self) {
+        self.check_only = true;
+    }
+
+    #[must_use]
+    pub fn default_title(&self) -> Option<&str> {
+        self.default_title.as_deref()
+    }
+
+    /// Title used when the document has neither a frontmatter title nor an `h1` heading
+    pub fn set_default_title(&mut self, value: &str) {
+        self.default_title = Some(value.to_string());
+    }
+
+    #[must_use]
+    pub fn disabled_grammar_categories(&self) -> &[String] {
+        &self.disabled_grammar_categories
+    }
+
+    /// Skip grammar checks in a rule category, for example `TYPOGRAPHY`
+    pub fn add_disabled_grammar_category(&mut self, category_id: &str) {
+        self.disabled_grammar_categories
+            .push(category_id.to_string());
+    }
+
+    #[must_use]
+    pub fn dom_hooks(&self) -> &[DomHook] {
+        &self.dom_hooks
+    }
+
+    /// Add a hook to run on the DOM of each document's content, after built-in processing, such
+    /// as link and image rewriting, comment stripping and search term highlighting, and before
+    /// the DOM is serialized. Hooks run in the order they are added. The byline, header, footer
+    /// and document template are added after hooks run, so hooks do not see them. Rendered
+    /// output is not cached while any hooks are set.
+    pub fn add_dom_hook<F: Fn(&RcDom) + Send + Sync + 'static>(&mut self, hook: F) {
+        self.dom_hooks.push(DomHook::new(hook));
+    }
+
+    #[must_use]
+    pub fn dump_plaintext_path(&self) -> Option<&Path> {
+        self.dump_plaintext_path.as_deref()
+    }
+
+    /// Write the plaintext sent for spelling and grammar checks to `value`, for debugging why an
+    /// issue was, or was not, reported. Each input overwrites the file.
+    pub fn set_dump_plaintext_path<P: AsRef<Path>>(&mut self, value: P) {
+        self.dump_plaintext_path = Some(value.as_ref().to_path_buf());
+    }
+
+    #[must_use]
+    pub fn embed_images(&self) -> bool {
+        self.embed_images
+    }
+
+    /// Inline local images into the output as base64 `data:` URIs
+    pub fn enable_embed_images(&mut self) {
+        self.embed_images = true;
+    }
+
+    #[must_use]
+    pub fn embed_images_max_size(&self) -> u64 {
+        self.embed_images_max_size
+            .unwrap_or(DEFAULT_EMBED_IMAGES_MAX_SIZE)
+    }
+
+    /// Images larger than `value` bytes are not embedded
+    pub fn set_embed_images_max_size(&mut self, value: u64) {
+        self.embed_images_max_size = Some(value);
+    }
+
+    #[must_use]
+    pub fn embed_source(&self) -> bool {
+        self.embed_source
+    }
+
+    /// Embed the markdown body in the output, in a non-rendered `text/markdown` script element
+    pub fn enable_embed_source(&mut self) {
+        self.embed_source = true;
+    }
+
+    #[must_use]
+    pub fn emoji_shortcodes(&self) -> bool {
+        self.emoji_shortcodes
+    }
+
+    /// Expand GitHub-style emoji shortcodes, such as `:smile:`, outside code
+    pub fn enable_emoji_shortcodes(&mut self) {
+        self.emoji_shortcodes = true;
+    }
+
+    #[must_use]
+    pub fn external_links_same_tab(&self) -> bool {
+        self.external_links_same_tab
+    }
+
+    /// Open external links in the same tab, leaving out the `target="_blank"` otherwise added.
+    /// External links still get `rel="noopener noreferrer"`.
+    pub fn enable_external_links_same_tab(&mut self) {
+        self.external_links_same_tab = true;
+    }
+
+    #[must_use]
+    pub fn extra_css_paths(&self) -> &[PathBuf] {
+        &self.extra_css_paths
+    }
+
+    /// Stylesheets are inlined after the bundled styles, in the order they are added
+    pub fn add_extra_css_path<P: AsRef<Path>>(&mut self, path: P) {
+        self.extra_css_paths.push(path.as_ref().to_path_buf());
+    }
+
+    #[must_use]
+    pub fn grammar_api_credentials(&self) -> Option<(&str, &str)> {
+        self.grammar_api_credentials
+            .as_ref()
+            .map(|(username, api_key)| (username.as_str(), api_key.as_str()))
+    }
+
+    /// Account username and API key sent with grammar check requests, for premium servers
+    pub fn set_grammar_api_credentials(&mut self, username: &str, api_key: &str) {
+        self.grammar_api_credentials = Some((username.to_string(), api_key.to_string()));
+    }
+
+    #[must_use]
+    pub fn grammar_blocks(&self) -> &[BlockKind] {
+        &self.grammar_blocks
+    }
+
+    /// Check the text of `kind` blocks, such as paragraphs, for spelling and grammar. Once any kind
+    /// is added, text in other kinds of block, such as headings, is left out of checks.
+    pub fn add_grammar_block(&mut self, kind: BlockKind) {
+        if !self.grammar_blocks.contains(&kind) {
+            self.grammar_blocks.push(kind);
+        }
+    }
+
+    #[must_use]
+    pub fn grammar_chunk_overlap(&self) -> usize {
+        self.grammar_chunk_overlap
+            .unwrap_or(DEFAULT_GRAMMAR_CHUNK_OVERLAP)
+    }
+
+    /// Repeat the last `value` sentences of each grammar check chunk at the start of the next, so
+    /// issues spanning chunks are found. Zero turns overlap off.
+    pub fn set_grammar_chunk_overlap(&mut self, value: usize) {
+        self.grammar_chunk_overlap = Some(value);
+    }
+
+    #[must_use]
+    pub fn grammar_context_width(&self) -> Option<usize> {
+        self.grammar_context_width
+    }
+
+    /// Show `value` characters of the checked text either side of each grammar issue, rather than
+    /// the context returned by the grammar server
+    pub fn set_grammar_context_width(&mut self, value: usize) {
+        self.grammar_context_width = Some(value);
+    }
+
+    /// Grammar checker configured from these options. Create one and pass it to each
+    /// [`update_html`] call, so connections and settings are reused between checks.
+    #[must_use]
+    pub fn grammar_checker(&self) -> GrammarChecker {
+        let mut result = GrammarChecker::new(self.grammar_url());
+        if let Some((username, api_key)) = self.grammar_api_credentials() {
+            result.api_credentials(username, api_key);
+        }
+        result
+            .disabled_categories(self.disabled_grammar_categories().to_vec())
+            .ignored_rules(self.ignored_grammar_rules().to_vec())
+            .preferred_variants(self.grammar_preferred_variants().to_vec())
+            .replacement_limit(self.grammar_replacement_limit())
+            .request_permits(self.grammar_request_permits())
+            .min_language_confidence(self.min_language_confidence());
+        if let Some(value) = self.max_grammar_requests {
+            result.request_budget(value, Arc::clone(&self.grammar_requests_sent));
+        }
+        result
+    }
+
+    #[must_use]
+    pub fn grammar_url(&self) -> Option<&str> {
+        self.grammar_url.as_deref()
+    }
+
+    /// Grammar server check endpoint, overriding the public `LanguageTool` API
+    pub fn set_grammar_url(&mut self, value: &str) {
+        self.grammar_url = Some(value.to_string());
+    }
+
+    #[must_use]
+    pub fn grammar_preferred_variants(&self) -> &[String] {
+        &self.grammar_preferred_variants
+    }
+
+    /// Prefer a language variant, for example `en-US`, when the grammar server detects the
+    /// language. Regions without a language marker are then checked with detection.
+    pub fn add_grammar_preferred_variant(&mut self, variant: &str) {
+        self.grammar_preferred_variants.push(variant.to_string());
+    }
+
+    #[must_use]
+    pub fn grammar_replacement_limit(&self) -> usize {
+        self.grammar_replacement_limit
+            .unwrap_or(DEFAULT_REPLACEMENT_LIMIT)
+    }
+
+    /// Show at most `value` replacement suggestions for each grammar match, zero hides them
+    pub fn set_grammar_replacement_limit(&mut self, value: usize) {
+        self.grammar_replacement_limit = Some(value);
+    }
+
+    #[must_use]
+    pub fn footer_path(&self) -> Option<&Path> {
+        self.footer_path.as_deref()
+    }
+
+    /// Add the HTML, or markdown, snippet at `value` after the content of every document, such as
+    /// a shared copyright notice
+    pub fn set_footer_path<P: AsRef<Path>>(&mut self, value: P) {
+        self.footer_path = Some(value.as_ref().to_path_buf());
+    }
+
+    #[must_use]
+    pub fn frontmatter_overrides(&self) -> &[(String, String)] {
+        &self.frontmatter_overrides
+    }
+
+    /// Set the frontmatter field `key`, such as `title`, to `value` for every document, taking
+    /// precedence over any value in the document itself
+    ///
+    /// # Errors
+    /// Errors with a message when `key` is not a frontmatter field, or `value` is not valid for it
+    pub fn set_frontmatter_value(&mut self, key: &str, value: &str) -> Result<(), String> {
+        Frontmatter::default().set(key, value)?;
+        self.frontmatter_overrides
+            .push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    /// Replaces fields in `frontmatter` with any values set in these options
+    fn apply_frontmatter_overrides(&self, frontmatter: &mut Frontmatter) {
+        for (key, value) in &self.frontmatter_overrides {
+            frontmatter
+                .set(key, value)
+                .expect("Frontmatter overrides are checked when they are set");
+        }
+    }
+
+    #[must_use]
+    pub fn fragment(&self) -> bool {
+        self.fragment
+    }
+
+    /// Write only the processed content HTML, without the surrounding document and inlined assets
+    pub fn enable_fragment(&mut self) {
+        self.fragment = true;
+    }
+
+    #[must_use]
+    pub fn gzip(&self) -> bool {
+        self.gzip
+    }
+
+    /// Also write a gzipped copy of the output HTML, ending `.html.gz`, for static hosts which
+    /// serve pre-compressed files
+    pub fn enable_gzip(&mut self) {
+        self.gzip = true;
+    }
+
+    #[must_use]
+    pub fn gzip_level(&self) -> u32 {
+        self.gzip_level.unwrap_or(DEFAULT_GZIP_LEVEL)
+    }
+
+    /// Compress gzipped output at `value`, from 0 (none) to 9 (best), instead of the default 6
+    pub fn set_gzip_level(&mut self, value: u32) {
+        self.gzip_level = Some(value.min(9));
+    }
+
+    #[must_use]
+    pub fn gzip_only(&self) -> bool {
+        self.gzip_only
+    }
+
+    /// Write only the gzipped copy of the output HTML, and not the uncompressed file
+    pub fn enable_gzip_only(&mut self) {
+        self.gzip = true;
+        self.gzip_only = true;
+    }
+
+    #[must_use]
+    pub fn hard_line_breaks(&self) -> bool {
+        self.hard_line_breaks
+    }
+
+    /// Render single newlines within a paragraph as line breaks, for poetry or addresses
+    pub fn enable_hard_line_breaks(&mut self) {
+        self.hard_line_breaks = true;
+    }
+
+    #[must_use]
+    pub fn header_path(&self) -> Option<&Path> {
+        self.header_path.as_deref()
+    }
+
+    /// Add the HTML, or markdown, snippet at `value` before the content of every document, such as
+    /// shared navigation
+    pub fn set_header_path<P: AsRef<Path>>(&mut self, value: P) {
+        self.header_path = Some(value.as_ref().to_path_buf());
+    }
+
+    #[must_use]
+    pub fn ignored_grammar_rules(&self) -> &[String] {
+        &self.ignored_grammar_rules
+    }
+
+    /// Drop grammar check results for a rule, such as `ELLIPSIS`, or for every rule in a category,
+    /// such as `TYPOGRAPHY`. Unlike disabled categories, the server still checks these.
+    pub fn add_ignored_grammar_rule(&mut self, id: &str) {
+        self.ignored_grammar_rules.push(id.to_string());
+    }
+
+    #[must_use]
+    pub fn images_base_url(&self) -> Option<&str> {
+        self.images_base_url.as_deref()
+    }
+
+    /// Base URL, for example a CDN, for relative image sources, leaving relative links unchanged
+    pub fn set_images_base_url(&mut self, value: &str) {
+        self.images_base_url = Some(value.to_string());
+    }
+
+    #[must_use]
+    pub fn include_drafts(&self) -> bool {
+        self.include_drafts
+    }
+
+    /// Write output for documents with `draft: true` frontmatter, which are skipped by default
+    pub fn enable_include_drafts(&mut self) {
+        self.include_drafts = true;
+    }
+
+    #[must_use]
+    pub fn include_frontmatter_in_output(&self) -> bool {
+        self.include_frontmatter_in_output
+    }
+
+    /// Keep the document frontmatter in the output, as an HTML comment at the top
+    pub fn enable_include_frontmatter_in_output(&mut self) {
+        self.include_frontmatter_in_output = true;
+    }
+
+    #[must_use]
+    pub fn internal_link_patterns(&self) -> &[String] {
+        &self.internal_link_patterns
+    }
+
+    /// Rewrite `.md` links matching the glob `pattern`, such as `docs/**/*.md`, to `.html`, even
+    /// when the target is not a local file. Other `.md` links are only rewritten when the target
+    /// exists in the project.
+    pub fn add_internal_link_pattern(&mut self, pattern: &str) {
+        self.internal_link_patterns.push(pattern.to_string());
+    }
+
+    #[must_use]
+    pub fn grammar_report_format(&self) -> GrammarReportFormat {
+        self.grammar_report_format
+    }
+
+    /// Write grammar check results as human-readable text, JSON or SARIF
+    pub fn set_grammar_report_format(&mut self, value: GrammarReportFormat) {
+        self.grammar_report_format = value;
+    }
+
+    #[must_use]
+    pub fn max_grammar_requests(&self) -> Option<usize> {
+        self.max_grammar_requests
+    }
+
+    /// Send at most `value` grammar check requests over the whole run, to stay within a request
+    /// quota. Later checks are skipped, while output is still written.
+    pub fn set_max_grammar_requests(&mut self, value: usize) {
+        self.max_grammar_requests = Some(value);
+    }
+
+    /// Permits bounding concurrent grammar server requests, shared by every file rendered with
+    /// these options
+    pub fn grammar_request_permits(&self) -> Arc<Semaphore> {
+        Arc::clone(
+            self.grammar_request_permits
+                .get_or_init(|| Arc::new(Semaphore::new(self.grammar_concurrency()))),
+        )
+    }
+
+    #[must_use]
+    pub fn grammar_concurrency(&self) -> usize {
+        self.grammar_concurrency
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_REQUESTS)
+    }
+
+    /// Allow at most `value` grammar server requests in flight at once, across all files.  Zero is
+    /// treated as one, so checks can still progress.
+    pub fn set_grammar_concurrency(&mut self, value: usize) {
+        self.grammar_concurrency = Some(value.max(1));
+        self.grammar_request_permits = OnceLock::new();
+    }
+
+    #[must_use]
+    pub fn max_heading_id_level(&self) -> Option<u8> {
+        self.max_heading_id_level
+    }
+
+    /// Only give headings down to level `value` (1 to 6) an `id` and permalink
+    pub fn set_max_heading_id_level(&mut self, value: u8) {
+        self.max_heading_id_level = Some(value.clamp(1, 6));
+    }
+
+    #[must_use]
+    pub fn max_image_height(&self) -> Option<u32> {
+        self.max_image_height
+    }
+
+    /// Replace local raster images taller than `value` pixels with downscaled copies, written
+    /// next to the output, keeping their aspect ratio
+    pub fn set_max_image_height(&mut self, value: u32) {
+        self.max_image_height = Some(value.max(1));
+    }
+
+    #[must_use]
+    pub fn max_image_width(&self) -> Option<u32> {
+        self.max_image_width
+    }
+
+    /// Replace local raster images wider than `value` pixels with downscaled copies, written next
+    /// to the output, keeping their aspect ratio
+    pub fn set_max_image_width(&mut self, value: u32) {
+        self.max_image_width = Some(value.max(1));
+    }
+
+    #[must_use]
+    pub fn max_sentence_words(&self) -> Option<u32> {
+        self.max_sentence_words
+    }
+
+    /// With linting, warn about sentences of more than `value` words, instead of the default 40
+    pub fn set_max_sentence_words(&mut self, value: u32) {
+        self.max_sentence_words = Some(value);
+    }
+
+    #[must_use]
+    pub fn min_language_confidence(&self) -> f64 {
+        self.min_language_confidence
+            .unwrap_or(DEFAULT_MIN_LANGUAGE_CONFIDENCE)
+    }
+
+    /// Warn when the grammar server detects the document language with confidence (0 to 1) below
+    /// `value`
+    pub fn set_min_language_confidence(&mut self, value: f64) {
+        self.min_language_confidence = Some(value);
+    }
+
+    #[must_use]
+    pub fn number_headings(&self) -> bool {
+        self.number_headings
+    }
+
+    /// Prefix headings, and their sidebar contents entries, with section numbers such as `1.2.1`
+    pub fn enable_number_headings(&mut self) {
+        self.number_headings = true;
+    }
+
+    #[must_use]
+    pub fn open_in(&self) -> Option<&str> {
+        self.open_in.as_deref()
+    }
+
+    /// Start `command`, split into a program and its arguments as a shell would, so quoted
+    /// arguments may contain spaces, each time output is written, with the output path added as the
+    /// last argument. Updates do not wait for the command to exit. When one of the arguments is
+    /// `-`, the HTML is passed on standard input instead of adding the path.
+    pub fn set_open_in(&mut self, command: &str) {
+        self.open_in = Some(command.to_string());
+    }
+
+    #[must_use]
+    pub fn output_bom(&self) -> bool {
+        self.output_bom
+    }
+
+    /// Start written HTML output files with a UTF-8 byte order mark, for tools which expect one
+    pub fn enable_output_bom(&mut self) {
+        self.output_bom = true;
+    }
+
+    #[must_use]
+    pub fn output_line_endings(&self) -> LineEnding {
+        self.output_line_endings
+    }
+
+    /// End lines of written HTML output files with `value`, instead of a line feed
+    pub fn set_output_line_endings(&mut self, value: LineEnding) {
+        self.output_line_endings = value;
+    }
+
+    #[must_use]
+    pub fn output_mode(&self) -> Option<u32> {
+        self.output_mode
+    }
+
+    /// Set the permissions of written HTML output files to `value`, such as `0o644` for files
+    /// anyone can read. Ignored on platforms other than Unix.
+    pub fn set_output_mode(&mut self, value: u32) {
+        self.output_mode = Some(value);
+    }
+
+    #[must_use]
+    pub fn pretty(&self) -> bool {
+        self.pretty
+    }
+
+    /// Indent nested block elements in the output HTML, instead of writing it compactly
+    pub fn enable_pretty(&mut self) {
+        self.pretty = true;
+    }
+
+    #[must_use]
+    pub fn byline(&self) -> bool {
+        self.byline
+    }
+
+    /// Show the word count and estimated reading time at the top of the document content
+    pub fn enable_byline(&mut self) {
+        self.byline = true;
+    }
+
+    #[must_use]
+    pub fn languagetool_disabled(&self) -> bool {
+        self.languagetool_disabled
+    }
+
+    /// Never call the `LanguageTool` server, so enabling spelling and grammar checks does nothing.
+    /// This keeps offline environments from waiting on network timeouts.
+    pub fn disable_languagetool(&mut self) {
+        self.languagetool_disabled = true;
+    }
+
+    #[must_use]
+    pub fn lint(&self) -> bool {
+        self.lint
+    }
+
+    /// Warn about trailing whitespace, runs of spaces in prose, hard tabs, code fences mixing tab
+    /// and space indentation, undefined link references, long sentences, skipped heading levels,
+    /// links sharing text but not targets, and reused images in the markdown source
+    pub fn enable_lint(&mut self) {
+        self.lint = true;
+    }
+
+    #[must_use]
+    pub fn responsive_images(&self) -> bool {
+        self.responsive_images
+    }
+
+    /// Generate smaller copies of large local images next to the output, listed in a `srcset`
+    pub fn enable_responsive_images(&mut self) {
+        self.responsive_images = true;
+    }
+
+    #[must_use]
+    pub fn sentence_segmentation(&self) -> SentenceSegmentation {
+        self.sentence_segmentation
+    }
+
+    /// Find the sentence ends grammar check chunks are trimmed back to with `value`, instead of
+    /// the heuristic
+    pub fn set_sentence_segmentation(&mut self, value: SentenceSegmentation) {
+        self.sentence_segmentation = value;
+    }
+
+    #[must_use]
+    pub fn sentence_terminators(&self) -> &[char] {
+        self.sentence_terminators
+            .as_deref()
+            .unwrap_or(&DEFAULT_SENTENCE_TERMINATORS)
+    }
+
+    /// Characters which end a sentence, when splitting text into grammar check chunks, each
+    /// character of `value` being one terminator, in place of [`DEFAULT_SENTENCE_TERMINATORS`]
+    pub fn set_sentence_terminators(&mut self, value: &str) {
+        self.sentence_terminators = Some(value.chars().collect());
+    }
+
+    #[must_use]
+    pub fn sidebar_toc(&self) -> bool {
+        self.sidebar_toc
+    }
+
+    /// Render heading links into an "On this page" sidebar, outside the main content
+    pub fn enable_sidebar_toc(&mut self) {
+        self.sidebar_toc = true;
+    }
+
+    #[must_use]
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Treat any parse warnings as errors, and skip writing output when there are any
+    pub fn enable_strict(&mut self) {
+        self.strict = true;
+    }
+
+    #[must_use]
+    pub fn strip_comments(&self) -> bool {
+        self.strip_comments
+    }
+
+    /// Remove HTML comments, such as authoring notes, from the output. They are kept by default.
+    pub fn enable_strip_comments(&mut self) {
+        self.strip_comments = true;
+    }
+
+    #[must_use]
+    pub fn subscript_superscript(&self) -> bool {
+        self.subscript_superscript
+    }
+
+    /// Render `~subscript~` and `^superscript^` spans, such as `H~2~O` and `x^2^`, outside code
+    pub fn enable_subscript_superscript(&mut self) {
+        self.subscript_superscript = true;
+    }
+
+    #[must_use]
+    pub fn templates_dir(&self) -> Option<&Path> {
+        self.templates_dir.as_deref()
+    }
+
+    /// Directory holding layout templates, which documents choose with the frontmatter `layout`
+    /// field
+    pub fn set_templates_dir<P: AsRef<Path>>(&mut self, value: P) {
+        self.templates_dir = Some(value.as_ref().to_path_buf());
+    }
+
+    #[must_use]
+    pub fn timings(&self) -> bool {
+        self.timings
+    }
+
+    /// Print a summary of time spent in each stage after each update
+    pub fn enable_timings(&mut self) {
+        self.timings = true;
+    }
+
+    /// Settings in effect, after defaults, environment variables and command line flags are
+    /// resolved, as a JSON object. Any grammar server API key is redacted.
+    #[must_use]
+    pub fn effective_config(&self) -> serde_json::Value {
+        let entries = [
+            ("basePath", serde_json::json!(self.base_path())),
+            ("byline", serde_json::json!(self.byline())),
+            ("cacheDir", serde_json::json!(self.cache_dir())),
+            (
+                "canonicalRootUrl",
+                serde_json::json!(self.canonical_root_url()),
+            ),
+            ("checkOnly", serde_json::json!(self.check_only())),
+            ("defaultTitle", serde_json::json!(self.default_title())),
+            (
+                "dumpPlaintextPath",
+                serde_json::json!(self.dump_plaintext_path()),
+            ),
+            ("embedImages", serde_json::json!(self.embed_images())),
+            (
+                "embedImagesMaxSize",
+                serde_json::json!(self.embed_images_max_size()),
+            ),
+            ("embedSource", serde_json::json!(self.embed_source())),
+            (
+                "emojiShortcodes",
+                serde_json::json!(self.emoji_shortcodes()),
+            ),
+            (
+                "externalLinksSameTab",
+                serde_json::json!(self.external_links_same_tab()),
+            ),
+            ("extraCssPaths", serde_json::json!(self.extra_css_paths())),
+            ("footerPath", serde_json::json!(self.footer_path())),
+            ("fragment", serde_json::json!(self.fragment())),
+            (
+                "frontmatterOverrides",
+                serde_json::Value::Object(
+                    self.frontmatter_overrides()
+                        .iter()
+                        .map(|(key, value)| (key.clone(), serde_json::json!(value)))
+                        .collect(),
+                ),
+            ),
+            ("hardLineBreaks", serde_json::json!(self.hard_line_breaks())),
+            ("headerPath", serde_json::json!(self.header_path())),
+            ("imagesBaseUrl", serde_json::json!(self.images_base_url())),
+            ("includeDrafts", serde_json::json!(self.include_drafts())),
+            (
+                "includeFrontmatterInOutput",
+                serde_json::json!(self.include_frontmatter_in_output()),
+            ),
+            (
+                "internalLinkPatterns",
+                serde_json::json!(self.internal_link_patterns()),
+            ),
+            ("lint", serde_json::json!(self.lint())),
+            (
+                "maxHeadingIdLevel",
+                serde_json::json!(self.max_heading_id_level()),
+            ),
+            ("maxImageHeight", serde_json::json!(self.max_image_height())),
+            ("maxImageWidth", serde_json::json!(self.max_image_width())),
+            (
+                "maxSentenceWords",
+                serde_json::json!(self
+                    .max_sentence_words()
+                    .unwrap_or(DEFAULT_MAX_SENTENCE_WORDS)),
+            ),
+            ("numberHeadings", serde_json::json!(self.number_headings())),
+            ("openIn", serde_json::json!(self.open_in())),
+            ("pretty", serde_json::json!(self.pretty())),
+            (
+                "responsiveImages",
+                serde_json::json!(self.responsive_images()),
+            ),
+            ("sidebarToc", serde_json::json!(self.sidebar_toc())),
+            ("strict", serde_json::json!(self.strict())),
+            ("stripComments", serde_json::json!(self.strip_comments())),
+            (
+                "subscriptSuperscript",
+                serde_json::json!(self.subscript_superscript()),
+            ),
+            ("templatesDir", serde_json::json!(self.templates_dir())),
+            ("timings", serde_json::json!(self.timings())),
+        ];
+        serde_json::Value::Object(
+            entries
+                .into_iter()
+                .chain(self.grammar_config_entries())
+                .chain(self.output_config_entries())
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// Output file entries of [`MarkwriteOptions::effective_config`]
+    fn output_config_entries(&self) -> [(&'static str, serde_json::Value); 6] {
+        [
+            ("gzip", serde_json::json!(self.gzip())),
+            ("gzipLevel", serde_json::json!(self.gzip_level())),
+            ("gzipOnly", serde_json::json!(self.gzip_only())),
+            ("outputBom", serde_json::json!(self.output_bom())),
+            (
+                "outputLineEndings",
+                serde_json::json!(self
+                    .output_line_endings()
+                    .to_possible_value()
+                    .map(|value| value.get_name().to_string())),
+            ),
+            (
+                "outputMode",
+                serde_json::json!(self.output_mode().map(|value| format!("{value:o}"))),
+            ),
+        ]
+    }
+
+    /// Spelling and grammar check entries of [`MarkwriteOptions::effective_config`]
+    fn grammar_config_entries(&self) -> [(&'static str, serde_json::Value); 19] {
+        let (grammar_username, grammar_api_key) = match self.grammar_api_credentials() {
+            Some((username, _)) => (Some(username), Some("[redacted]")),
+            None => (None, None),
+        };
+        [
+            ("autofix", serde_json::json!(self.autofix())),
+            ("checkGrammar", serde_json::json!(self.check_grammar())),
+            (
+                "disabledGrammarCategories",
+                serde_json::json!(self.disabled_grammar_categories()),
+            ),
+            ("grammarApiKey", serde_json::json!(grammar_api_key)),
+            (
+                "grammarBlocks",
+                serde_json::json!(self
+                    .grammar_blocks()
+                    .iter()
+                    .filter_map(|value| value
+                        .to_possible_value()
+                        .map(|value| value.get_name().to_string()))
+                    .collect::<Vec<String>>()),
+            ),
+            (
+                "grammarChunkOverlap",
+                serde_json::json!(self.grammar_chunk_overlap()),
+            ),
+            (
+                "grammarConcurrency",
+                serde_json::json!(self.grammar_concurrency()),
+            ),
+            (
+                "grammarContextWidth",
+                serde_json::json!(self.grammar_context_width()),
+            ),
+            (
+                "grammarPreferredVariants",
+                serde_json::json!(self.grammar_preferred_variants()),
+            ),
+            (
+                "grammarReplacementLimit",
+                serde_json::json!(self.grammar_replacement_limit()),
+            ),
+            (
+                "grammarReportFormat",
+                serde_json::json!(self
+                    .grammar_report_format()
+                    .to_possible_value()
+                    .map(|value| value.get_name().to_string())),
+            ),
+            (
+                "grammarUrl",
+                serde_json::json!(self.grammar_url().unwrap_or(DEFAULT_LANGUAGETOOL_URL)),
+            ),
+            ("grammarUsername", serde_json::json!(grammar_username)),
+            (
+                "ignoredGrammarRules",
+                serde_json::json!(self.ignored_grammar_rules()),
+            ),
+            (
+                "languagetoolDisabled",
+                serde_json::json!(self.languagetool_disabled()),
+            ),
+            (
+                "maxGrammarRequests",
+                serde_json::json!(self.max_grammar_requests()),
+            ),
+            (
+                "minLanguageConfidence",
+                serde_json::json!(self.min_language_confidence()),
+            ),
+            (
+                "sentenceSegmentation",
+                serde_json::json!(self
+                    .sentence_segmentation()
+                    .to_possible_value()
+                    .map(|value| value.get_name().to_string())),
+            ),
+            (
+                "sentenceTerminators",
+                serde_json::json!(self.sentence_terminators().iter().collect::<String>()),
+            ),
+        ]
+    }
+}
+
+/// Renderer holding configuration, a spelling dictionary and a grammar checker, built once and
+/// reused for each document, so settings are resolved and connections kept between renders.
+///
+/// ```
+/// use std::collections::HashSet;
+///
+/// let mut options = markwrite::MarkwriteOptions::default();
+/// options.enable_fragment();
+/// let markwrite = markwrite::Markwrite::new(options, HashSet::new());
+///
+/// let first = markwrite.render("---\ntitle: First\n---\n\nFirst *post*.\n");
+/// let second = markwrite.render("Second post.\n");
+///
+/// assert_eq!(first.html(), Some("<p>First <em>post</em>.</p>\n"));
+/// assert_eq!(second.html(), Some("<p>Second post.</p>\n"));
+/// ```
+pub struct Markwrite {
+    dictionary: HashSet<String>,
+    grammar_checker: GrammarChecker,
+    options: MarkwriteOptions,
+
+    /// Parse options shared by every input, without any which depend on the input path
+    parse_options: ParseInputOptions,
+}
+
+impl Markwrite {
+    #[must_use]
+    pub fn new(options: MarkwriteOptions, dictionary: HashSet<String>) -> Self {
+        let mut grammar_checker = options.grammar_checker();
+        grammar_checker.dictionary(dictionary.clone());
+        Markwrite {
+            dictionary,
+            grammar_checker,
+            parse_options: base_parse_input_options(&options),
+            options,
+        }
+    }
+
+    #[must_use]
+    pub fn dictionary(&self) -> &HashSet<String> {
+        &self.dictionary
+    }
+
+    #[must_use]
+    pub fn grammar_checker(&self) -> &GrammarChecker {
+        &self.grammar_checker
+    }
+
+    #[must_use]
+    pub fn options(&self) -> &MarkwriteOptions {
+        &self.options
+    }
+
+    /// Renders `markdown`, with any frontmatter, to processed HTML, without reading or writing
+    /// files. Relative stylesheets, images and links are not resolved, since there is no input
+    /// path, and invalid frontmatter is reported with the parse errors.
+    #[must_use]
+    pub fn render(&self, markdown: &str) -> ParseResults {
+        if let Some(message) = unterminated_frontmatter(markdown) {
+            return ParseResults {
+                html: None,
+                headings: None,
+                statistics: None,
+                errors: Some(vec![message]),
+                timings: StageTimings::default(),
+            };
+        }
+        let (frontmatter_yaml, body) = strip_frontmatter(markdown);
+        let (frontmatter, frontmatter_error) =
+            document_frontmatter(frontmatter_yaml, &self.options);
+        let mut options = self.parse_options.clone();
+        options.source_line_offset = line_offset(markdown);
+        if options.canonical_root_url.is_none() {
+            options.canonical_root_url = frontmatter
+                .site_url()
+                .map(|value| value.trim_end_matches('/').to_string());
+        }
+        let mut result = markdown_to_processed_html(body, &frontmatter, &options);
+        if let Some(message) = frontmatter_error {
+            result
+                .errors
+                .get_or_insert_with(Vec::new)
+                .insert(0, message);
+        }
+        result
+    }
+
+    /// Checks spelling, punctuation and grammar in the markdown at `path`, printing the results,
+    /// without rendering HTML or writing any output. The check runs whether or not it is enabled
+    /// in the options, unless `LanguageTool` is disabled. Returns the number of issues found.
+    ///
+    /// # Errors
+    /// Errors if unable to read input file, or its frontmatter is unterminated
+    pub async fn check_file_grammar<P: AsRef<Path>>(
+        &self,
+        path: &P,
+        stdout_handle: &mut impl Write,
+    ) -> Result<usize, notify::Error> {
+        let source = read_source(path.as_ref())?;
+        let display_path = path.as_ref().display().to_string();
+        if self.options.languagetool_disabled() {
+            writeln!(
+                stdout_handle,
+                "[ INFO ] LanguageTool is disabled, skipped checking {display_path}."
+            )?;
+            return Ok(0);
+        }
+        let (_frontmatter_yaml, markdown) = strip_frontmatter(&source);
+        let (_duration, issue_count) = timed_grammar_check(
+            markdown,
+            &source,
+            path.as_ref(),
+            &display_path,
+            &self.options,
+            Some(&self.grammar_checker),
+            stdout_handle,
+        )
+        .await;
+        stdout_handle.flush()?;
+        Ok(issue_count)
+    }
+
+    /// Renders the markdown at `path` to HTML at `output_path`, like [`update_html`], with the
+    /// options and grammar checker held here
+    ///
+    /// # Errors
+    /// Errors if unable to read input file, if there are parse warnings with strict mode enabled
+    /// or if the output file cannot be created
+    pub async fn update_file<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        path: &P1,
+        output_path: &P2,
+        stdout_handle: &mut impl Write,
+    ) -> Result<UpdateOutcome, notify::Error> {
+        update_html(
+            path,
+            output_path,
+            &self.options,
+            Some(&self.grammar_checker),
+            stdout_handle,
+        )
+        .await
+    }
+}
+
+/// Reads the template for `layout` from `templates_dir`, returning `None`, with a warning, when
+/// there is no such template, or `layout` is not a plain file name, so the built-in template is
+/// used instead. Also warns when the template leaves out the placeholder for enabled
+/// `--embed-source` or `--sidebar-toc` output, which would otherwise be dropped.
+fn load_layout_template(
+    templates_dir: &Path,
+    layout: &str,
+    markwrite_options: &MarkwriteOptions,
+    stdout_handle: &mut impl Write,
+) -> Option<String> {
+    // Frontmatter may not pick a template from outside the templates directory
+    let mut components = Path::new(layout).components();
+    if layout.contains(['/', '\\'])
+        || !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        )
+    {
+        writeln!(
+            stdout_handle,
+            "[ WARN ] Layout ({layout}) is not a template name in the templates directory, using \
+the built-in template."
+        )
+        .expect("Expected to be able to write to stdout");
+        return None;
+    }
+    let template_path = templates_dir.join(format!("{layout}.html"));
+    let result = read_to_string(&template_path).ok();
+    match &result {
+        None => writeln!(
+            stdout_handle,
+            "[ WARN ] Unable to read layout template ({}), using the built-in template.",
+            template_path.display()
+        ),
+        // The template may have changed since it was validated, while watching
+        Some(value) => unknown_layout_placeholders(value)
+            .iter()
+            .try_for_each(|name| {
+                writeln!(
+                    stdout_handle,
+                    "[ WARN ] Unknown placeholder {{{{ {name} }}}} in layout template ({}).",
+                    template_path.display()
+                )
+            }),
+    }
+    .expect("Expected to be able to write to stdout");
+    if let Some(value) = &result {
+        let placeholders = layout_placeholders(value);
+        let options = [
+            (
+                markwrite_options.embed_source(),
+                "embedded_source",
+                "--embed-source",
+            ),
+            (
+                markwrite_options.sidebar_toc(),
+                "sidebar_toc",
+                "--sidebar-toc",
+            ),
+        ];
+        for (_, placeholder, option) in options
+            .iter()
+            .filter(|(enabled, placeholder, _)| *enabled && !placeholders.contains(placeholder))
+        {
+            writeln!(
+                stdout_handle,
+                "[ WARN ] Layout template ({}) has no {{{{ {placeholder} }}}} placeholder, so {option} output is left out.",
+                template_path.display()
+            )
+            .expect("Expected to be able to write to stdout");
+        }
+    }
+    result
+}
+
+/// Resolves options for rendering the input at `path`, from the CLI options and its frontmatter
+fn parse_input_options(
+    path: &Path,
+    output_path: &Path,
+    frontmatter: &Frontmatter,
+    markwrite_options: &MarkwriteOptions,
+    stdout_handle: &mut impl Write,
+) -> std::io::Result<ParseInputOptions> {
+    // Frontmatter stylesheet paths are relative to the input file and follow any CLI ones
+    let mut extra_css_paths = markwrite_options.extra_css_paths().to_vec();
+    if let Some(stylesheets) = &frontmatter.stylesheet {
+        let input_directory = path.parent().unwrap_or_else(|| Path::new(""));
+        extra_css_paths.extend(
+            stylesheets
+                .iter()
+                .map(|stylesheet| input_directory.join(stylesheet)),
+        );
+    }
+    let project_root = std::env::current_dir()?;
+    let extra_css = if extra_css_paths.is_empty() {
+        None
+    } else {
+        load_extra_css(&extra_css_paths, &project_root, stdout_handle)
+    };
+
+    let input_directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let embed_images = if markwrite_options.embed_images() {
+        Some(EmbedImages::new(
+            input_directory,
+            &project_root,
+            markwrite_options.embed_images_max_size(),
+        ))
+    } else {
+        None
+    };
+    let output_directory = output_path.parent().unwrap_or_else(|| Path::new(""));
+    let responsive_images = markwrite_options
+        .responsive_images()
+        .then(|| ResponsiveImages::new(input_directory, output_directory));
+    let max_image_width = markwrite_options.max_image_width();
+    let max_image_height = markwrite_options.max_image_height();
+    let downscale_images = (max_image_width.is_some() || max_image_height.is_some()).then(|| {
+        DownscaleImages::new(
+            input_directory,
+            output_directory,
+            max_image_width,
+            max_image_height,
+        )
+    });
+    let header = markwrite_options
+        .header_path()
+        .and_then(|value| load_snippet(value, "header", stdout_handle));
+    let footer = markwrite_options
+        .footer_path()
+        .and_then(|value| load_snippet(value, "footer", stdout_handle));
+    let layout_template = match (markwrite_options.templates_dir(), &frontmatter.layout) {
+        (Some(templates_dir), Some(layout)) => {
+            load_layout_template(templates_dir, layout, markwrite_options, stdout_handle)
+        }
+        _ => None,
+    };
+
+    Ok(ParseInputOptions {
+        // The command line root is shared by every input, so it wins over the frontmatter one
+        canonical_root_url: markwrite_options
+            .canonical_root_url()
+            .or(frontmatter.site_url.as_deref())
+            .map(|value| value.trim_end_matches('/').to_string()),
+        downscale_images,
+        embed_images,
+        extra_css,
+        footer,
+        header,
+        internal_links: Some(
+            InternalLinks::new(input_directory, project_root)
+                .patterns(markwrite_options.internal_link_patterns()),
+        ),
+        layout_template,
+        responsive_images,
+        ..base_parse_input_options(markwrite_options)
+    })
+}
+
+/// Options for rendering any input with `markwrite_options`, leaving out those which depend on
+/// the input path or its frontmatter
+fn base_parse_input_options(markwrite_options: &MarkwriteOptions) -> ParseInputOptions {
+    ParseInputOptions {
+        base_path: markwrite_options.base_path().map(ToString::to_string),
+        byline: markwrite_options.byline(),
+        canonical_root_url: markwrite_options
+            .canonical_root_url()
+            .map(|value| value.trim_end_matches('/').to_string()),
+        default_title: markwrite_options.default_title().map(ToString::to_string),
+        dom_hooks: markwrite_options.dom_hooks().to_vec(),
+        embed_source: markwrite_options.embed_source(),
+        emoji_shortcodes: markwrite_options.emoji_shortcodes(),
+        external_links_same_tab: markwrite_options.external_links_same_tab(),
+        fragment: markwrite_options.fragment(),
+        hard_line_breaks: markwrite_options.hard_line_breaks(),
+        images_base_url: markwrite_options.images_base_url().map(ToString::to_string),
+        lint: markwrite_options.lint(),
+        max_heading_id_level: markwrite_options.max_heading_id_level(),
+        max_sentence_words: markwrite_options.max_sentence_words(),
+        number_headings: markwrite_options.number_headings(),
+        sidebar_toc: markwrite_options.sidebar_toc(),
+        strip_comments: markwrite_options.strip_comments(),
+        subscript_superscript: markwrite_options.subscript_superscript(),
+        ..ParseInputOptions::default()
+    }
+}
+
+/// Output path used when none is given: the input path with an `.html` extension
+#[must_use]
+pub fn default_output_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut result = path.as_ref().to_path_buf();
+    result.set_extension("html");
+    result
+}
+
+/// Pairs each markdown input with the path its HTML is written to.  Directory inputs expand to the
+/// markdown files they contain.  With `output_dir`, outputs go under that directory, mirroring any
+/// subdirectories of a directory input.  `output` names the output file and only applies to a
+/// single input file.
+///
+/// # Errors
+/// Errors if `output` is combined with multiple inputs, or a directory input cannot be read or
+/// contains no markdown files
+pub fn output_paths(
+    inputs: &[PathBuf],
+    output: Option<&Path>,
+    output_dir: Option<&Path>,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    // Each input paired with its path relative to the output directory
+    let mut sources: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut has_directory_input = false;
+    for input in inputs {
+        if input.is_dir() {
+            has_directory_input = true;
+            let markdown_paths = markdown_files(input)
+                .with_context(|| format!("Unable to read input directory {}", input.display()))?;
+            if markdown_paths.is_empty() {
+                bail!("No markdown files found in {}", input.display());
+            }
+            for markdown_path in markdown_paths {
+                let relative_path = markdown_path
+                    .strip_prefix(input)
+                    .unwrap_or(&markdown_path)
+                    .to_path_buf();
+                sources.push((markdown_path, relative_path));
+            }
+        } else {
+            let relative_path = input
+                .file_name()
+                .map_or_else(|| input.clone(), PathBuf::from);
+            sources.push((input.clone(), relative_path));
+        }
+    }
+    if output.is_some() && (has_directory_input || sources.len() > 1) {
+        bail!("--output takes a single input file, use --output-dir for multiple inputs");
+    }
+
+    Ok(sources
+        .into_iter()
+        .map(|(input, relative_path)| {
+            let output_path = match (output, output_dir) {
+                (Some(value), _) => value.to_path_buf(),
+                (None, Some(directory)) => directory.join(default_output_path(relative_path)),
+                (None, None) => default_output_path(&input),
+            };
+            (input, output_path)
+        })
+        .collect())
+}
+
+/// Keeps the `targets` whose input was modified at or after `cutoff`, along with any whose
+/// modification time cannot be read. Returns the kept targets and the number skipped.
+#[must_use]
+pub fn modified_since(
+    targets: Vec<(PathBuf, PathBuf)>,
+    cutoff: SystemTime,
+) -> (Vec<(PathBuf, PathBuf)>, usize) {
+    let target_count = targets.len();
+    let result: Vec<(PathBuf, PathBuf)> = targets
+        .into_iter()
+        .filter(|(path, _)| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .map_or(true, |modified| modified >= cutoff)
+        })
+        .collect();
+    let skipped_count = target_count - result.len();
+    (result, skipped_count)
+}
+
+/// Words of document text shown in a directory index, for documents with no description
+const INDEX_EXCERPT_WORDS: usize = 30;
+
+/// Rendered document, as listed in a directory index and sitemap
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListedDocument {
+    frontmatter: Frontmatter,
+
+    /// Frontmatter description, or the start of the document text when there is none
+    excerpt: String,
+
+    /// When the input was last modified
+    last_modified: Option<SystemTime>,
+}
+
+impl ListedDocument {
+    /// Listing for the input at `path`, with body `markdown` and `frontmatter`
+    fn new(path: &Path, markdown: &str, frontmatter: Frontmatter) -> Self {
+        let excerpt = if let Some(value) = frontmatter.description() {
+            value.to_string()
+        } else {
+            // Headings are left out, so the excerpt does not repeat the title
+            let mut markdown_options = ParseMarkdownOptions::default();
+            markdown_options
+                .block_kinds(Some(&[BlockKind::Paragraph]))
+                .link_rendering(LinkRendering::TextOnly);
+            let plaintext = parse_markdown_to_plaintext(markdown, &markdown_options);
+            let words: Vec<&str> = plaintext.split_whitespace().collect();
+            let mut excerpt = words[..words.len().min(INDEX_EXCERPT_WORDS)].join(" ");
+            if words.len() > INDEX_EXCERPT_WORDS {
+                excerpt.push('…');
+            }
+            excerpt
+        };
+        ListedDocument {
+            frontmatter,
+            excerpt,
+            last_modified: std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+        }
+    }
+
+    /// Listing for the input at `path`, read from its source, for inputs rendered by an earlier run
+    /// rather than updated in this one, or `None` for a draft
+    ///
+    /// # Errors
+    /// Errors if the input cannot be read
+    pub fn read<P: AsRef<Path>>(
+        path: &P,
+        markwrite_options: &MarkwriteOptions,
+    ) -> std::io::Result<Option<Self>> {
+        let source = read_to_string(path)?;
+        let (frontmatter_yaml, markdown) = strip_frontmatter(&source);
+        let (frontmatter, _) = document_frontmatter(frontmatter_yaml, markwrite_options);
+        if frontmatter.draft() == Some(true) && !markwrite_options.include_drafts() {
+            return Ok(None);
+        }
+        Ok(Some(Self::new(path.as_ref(), markdown, frontmatter)))
+    }
+}
+
+/// Link to `output_path` from a page in `directory`, with `/` separators. Outputs outside
+/// `directory` are linked by file name.
+fn relative_href(output_path: &Path, directory: &Path) -> String {
+    let relative_path = output_path
+        .strip_prefix(directory)
+        .unwrap_or_else(|_| output_path.file_name().map_or(output_path, Path::new));
+    relative_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Writes an `index.html` page to `index_directory`, linking to each output in `documents`, with
+/// the listing [`update_html`] gave for it, showing its title, date and description from
+/// frontmatter, newest first. Documents with no description get an excerpt of their text, and
+/// undated documents go last. Returns the path of the index.
+///
+/// # Errors
+/// Errors if the index cannot be written
+pub fn write_index<P: AsRef<Path>>(
+    documents: &[(&Path, &ListedDocument)],
+    index_directory: &P,
+    markwrite_options: &MarkwriteOptions,
+) -> std::io::Result<PathBuf> {
+    let index_directory = index_directory.as_ref();
+    let mut entries = documents.to_vec();
+    // Dates are compared as text, which orders ISO 8601 dates correctly
+    entries.sort_by(|(_, first), (_, second)| second.frontmatter.date.cmp(&first.frontmatter.date));
+
+    let escape = |value: &str| askama::MarkupDisplay::new_unsafe(value, askama::Html).to_string();
+    let items: Vec<String> = entries
+        .iter()
+        .map(|(output_path, document)| {
+            let href = relative_href(output_path, index_directory);
+            let title = document.frontmatter.title().map_or_else(
+                || {
+                    output_path
+                        .file_stem()
+                        .map_or_else(|| href.clone(), |value| value.to_string_lossy().to_string())
+                },
+                ToString::to_string,
+            );
+            let date = document
+                .frontmatter
+                .date()
+                .map_or_else(String::new, |value| {
+                    format!(r#" <time datetime="{0}">{0}</time>"#, escape(value))
+                });
+            format!(
+                r#"<li><a href="{}">{}</a>{date}<p>{}</p></li>"#,
+                escape(&href),
+                escape(&title),
+                escape(&document.excerpt),
+            )
+        })
+        .collect();
+    let main_section_html = format!(
+        "<h1>Index</h1>\n<ul class=\"markwrite-index\">\n{}\n</ul>\n",
+        items.join("\n")
+    );
+
+    let frontmatter = Frontmatter {
+        title: Some(String::from("Index")),
+        ..Default::default()
+    };
+    let html = html_document(
+        &main_section_html,
+        &frontmatter,
+        &[],
+        None,
+        &base_parse_input_options(markwrite_options),
+    );
+    let index_path = index_directory.join("index.html");
+    write_output_file(&index_path, html)?;
+    Ok(index_path)
+}
+
+/// Escapes `&`, `<` and `>` in `text` for XML content
+fn escape_xml_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Writes a `sitemap.xml` to `output_directory`, listing the URL of each output in `documents`,
+/// with the date its input was last modified, from the listing [`update_html`] gave for it. URLs
+/// are the frontmatter canonical URL, when set, otherwise the output path under the canonical
+/// root, from the options or frontmatter `site_url`. Documents with no resolvable URL are left
+/// out. Returns the path of the sitemap.
+///
+/// # Errors
+/// Errors if the sitemap cannot be written
+pub fn write_sitemap<P: AsRef<Path>>(
+    documents: &[(&Path, &ListedDocument)],
+    output_directory: &P,
+    markwrite_options: &MarkwriteOptions,
+) -> std::io::Result<PathBuf> {
+    let output_directory = output_directory.as_ref();
+    let mut entries = Vec::with_capacity(documents.len());
+    for (output_path, document) in documents {
+        let frontmatter = &document.frontmatter;
+        let canonical_root_url = markwrite_options
+            .canonical_root_url()
+            .or(frontmatter.site_url())
+            .map(|value| value.trim_end_matches('/'));
+        let location = match frontmatter.canonical_url() {
+            Some(value) => absolute_canonical_url(value, canonical_root_url).ok(),
+            None => canonical_root_url
+                .map(|root| format!("{root}/{}", relative_href(output_path, output_directory))),
+        };
+        let Some(location) = location else {
+            continue;
+        };
+        let last_modified = document
+            .last_modified
+            .map(|value| format!("<lastmod>{}</lastmod>", iso_date(value)))
+            .unwrap_or_default();
+        entries.push(format!(
+            "  <url><loc>{}</loc>{last_modified}</url>\n",
+            escape_xml_text(&location)
+        ));
+    }
+    let sitemap = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n{}</urlset>\n",
+        entries.concat()
+    );
+    let sitemap_path = output_directory.join("sitemap.xml");
+    write_output_file(&sitemap_path, sitemap)?;
+    Ok(sitemap_path)
+}
+
+/// Sets the permissions of the file at `path` to `mode`, such as `0o644`
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+/// File modes are Unix only, so elsewhere files keep their default permissions
+#[cfg(not(unix))]
+fn set_file_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Writes `contents` to `output_path`, first creating any missing parent directories.  Contents go
+/// to a temporary file in the same directory, which is then renamed into place, so readers never
+/// see a partially written file.
+fn write_output_file(output_path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    write_output_file_with_mode(output_path, contents, None)
+}
+
+/// Writes `contents` to `output_path`, like [`write_output_file`], setting the permissions of the
+/// written file to any `mode`, such as `0o644`, before it is renamed into place
+fn write_output_file_with_mode(
+    output_path: &Path,
+    contents: impl AsRef<[u8]>,
+    mode: Option<u32>,
+) -> std::io::Result<()> {
+    if let Some(parent) = output_path.parent() {
+        if !parent.as_os_str().is_empty() {
+            create_dir_all(parent)?;
+        }
+    }
+    let file_name = output_path
+        .file_name()
+        .map(|value| value.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temporary_path =
+        output_path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+    let result = File::create(&temporary_path)
+        .and_then(|mut outfile| {
+            outfile.write_all(contents.as_ref())?;
+            outfile.sync_all()
+        })
+        .and_then(|()| mode.map_or(Ok(()), |value| set_file_mode(&temporary_path, value)))
+        .and_then(|()| rename(&temporary_path, output_path));
+    if result.is_err() {
+        let _ = remove_file(&temporary_path);
+    }
+    result
+}
+
+/// Renders the markdown at `path` to HTML at `output_path`, grammar checking it first when enabled.
+/// Pass a `grammar_checker` shared between calls to reuse its settings and connections, otherwise
+/// one is configured from `markwrite_options` for this call. Returns what was done with the input,
+/// for totalling in a [`BatchSummary`].
+///
+/// # Errors
+/// Errors if unable to read input file, if the markdown cannot be parsed, if there are parse
+/// warnings with strict mode enabled or if the output file cannot be created
+pub async fn update_html<P1: AsRef<Path>, P2: AsRef<Path>>(
+    path: &P1,
+    output_path: &P2,
+    markwrite_options: &MarkwriteOptions,
+    grammar_checker: Option<&GrammarChecker>,
+    stdout_handle: &mut impl Write,
+) -> Result<UpdateOutcome, notify::Error> {
+    let source = read_source(path.as_ref())?;
+
+    let (frontmatter_yaml, markdown) = strip_frontmatter(&source);
+    let (frontmatter, frontmatter_error) =
+        document_frontmatter(frontmatter_yaml, markwrite_options);
+    let check_failed = frontmatter_error.is_some();
+    if let Some(message) = frontmatter_error {
+        eprintln!("[ ERROR ] {message}");
+    }
+
+    let (parse_results, render_cache) = render_source(
+        path.as_ref(),
+        output_path.as_ref(),
+        &source,
+        markdown,
+        &frontmatter,
+        markwrite_options,
+        stdout_handle,
+    )?;
+    let ParseResults {
+        html,
+        statistics,
+        errors,
+        timings,
+        ..
+    } = parse_results;
+    if let Some(warnings) = errors {
+        display_parse_warnings(&warnings, markwrite_options.strict(), stdout_handle)?;
+    }
+    let word_count = statistics.as_ref().map_or(0, TextStatistics::word_count);
+    if let Some(value) = statistics.filter(|_| markwrite_options.lint()) {
+        display_sentence_statistics(&value, stdout_handle)?;
+    }
+
+    if let Some(value) = markwrite_options.dump_plaintext_path() {
+        dump_plaintext(markdown, value, markwrite_options, stdout_handle)?;
+    }
+    let display_path = path.as_ref().display().to_string();
+    let (grammar_check_duration, grammar_issue_count) = if markwrite_options.check_grammar() {
+        let (duration, issue_count) = timed_grammar_check(
+            markdown,
+            &source,
+            path.as_ref(),
+            &display_path,
+            markwrite_options,
+            grammar_checker,
+            stdout_handle,
+        )
+        .await;
+        (Some(duration), issue_count)
+    } else {
+        (None, 0)
+    };
+    if markwrite_options.timings() {
+        display_timings(&timings, grammar_check_duration, stdout_handle)?;
+    }
+
+    let outcome = |status, document| UpdateOutcome {
+        status,
+        word_count,
+        grammar_issue_count,
+        document,
+    };
+    let Some(html) = html else {
+        eprintln!("[ ERROR ] Unable to parse markdown to HTML.");
+        stdout_handle.flush()?;
+        return Err(notify::Error::generic("Unable to parse markdown to HTML"));
+    };
+    if markwrite_options.check_only() {
+        stdout_handle.flush()?;
+        if check_failed {
+            return Err(notify::Error::generic("Check failed"));
+        }
+        writeln!(
+            stdout_handle,
+            "[ INFO ] Checked {display_path} ({word_count} words), no output written."
+        )?;
+        stdout_handle.flush()?;
+        return Ok(outcome(UpdateStatus::Checked, None));
+    }
+    if frontmatter.draft == Some(true) && !markwrite_options.include_drafts() {
+        writeln!(
+            stdout_handle,
+            "[ INFO ] Skipped draft {display_path}, no output written."
+        )?;
+        stdout_handle.flush()?;
+        return Ok(outcome(UpdateStatus::Skipped, None));
+    }
+    write_rendered_output(
+        output_path.as_ref(),
+        html,
+        frontmatter_yaml,
+        word_count,
+        render_cache,
+        markwrite_options,
+        stdout_handle,
+    )?;
+    let document = ListedDocument::new(path.as_ref(), markdown, frontmatter);
+    Ok(outcome(UpdateStatus::Rendered, Some(document)))
+}
+
+/// Writes the output for rendered `html` to `output_path`, opens it with any `--open-in` command,
+/// then stores `html` in any `render_cache` entry
+fn write_rendered_output(
+    output_path: &Path,
+    html: String,
+    frontmatter_yaml: Option<&str>,
+    word_count: u32,
+    render_cache: Option<(RenderCache, String)>,
+    markwrite_options: &MarkwriteOptions,
+    stdout_handle: &mut impl Write,
+) -> Result<(), notify::Error> {
+    let output_html = format_output_html(&html, frontmatter_yaml, markwrite_options);
+    write_html_output(
+        output_path,
+        &output_html,
+        word_count,
+        markwrite_options,
+        stdout_handle,
+    )?;
+    open_output(output_path, &output_html, markwrite_options);
+    if let Some((cache, key)) = render_cache {
+        store_cached_render(&cache, &key, html, word_count, stdout_handle)?;
+    }
+    Ok(())
+}
+
+/// Stores `html` in the render cache under `key`, warning, rather than failing the update, when it
+/// cannot be written
+fn store_cached_render(
+    cache: &RenderCache,
+    key: &str,
+    html: String,
+    word_count: u32,
+    stdout_handle: &mut impl Write,
+) -> std::io::Result<()> {
+    if let Err(error) = cache.insert(key, &CachedRender { html, word_count }) {
+        writeln!(
+            stdout_handle,
+            "[ WARN ] Unable to write render cache entry: {error}."
+        )?;
+    }
+    Ok(())
+}
+
+/// Render cache, and the key for rendering `source`, the input at `path` with body `markdown`,
+/// with `options`, when a cache directory is set. Renders embedding or resizing images also depend
+/// on the image files, as do renders with links to local markdown files on whether those files
+/// exist, and lint, timings and check only runs report on a fresh render, so these are never
+/// cached.
+fn render_cache_entry(
+    path: &Path,
+    source: &str,
+    markdown: &str,
+    options: &ParseInputOptions,
+    markwrite_options: &MarkwriteOptions,
+) -> Option<(RenderCache, String)> {
+    let cache_dir = markwrite_options.cache_dir()?;
+    // Hook output cannot be keyed, so is never cached
+    if markwrite_options.check_only()
+        || !markwrite_options.dom_hooks().is_empty()
+        || markwrite_options.embed_images()
+        || markwrite_options.lint()
+        || markwrite_options.max_image_height().is_some()
+        || markwrite_options.max_image_width().is_some()
+        || markwrite_options.responsive_images()
+        || markwrite_options.timings()
+    {
+        return None;
+    }
+    if let Some(internal_links) = &options.internal_links {
+        if link_destinations(markdown)
+            .iter()
+            .any(|href| internal_links.depends_on_target(href))
+        {
+            return None;
+        }
+    }
+
+    // Where the cache lives, and what opens the output, do not change the output
+    let mut config = markwrite_options.effective_config();
+    if let Some(value) = config.as_object_mut() {
+        value.remove("cacheDir");
+        value.remove("openIn");
+    }
+    let snippet_content = |snippet: &Option<Snippet>| match snippet {
+        Some(Snippet::Html(value)) => format!("html:{value}"),
+        Some(Snippet::Markdown(value)) => format!("markdown:{value}"),
+        None => String::new(),
+    };
+    // Relative links and image sources resolve against the input directory
+    let input_directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let input_directory = input_directory
+        .canonicalize()
+        .unwrap_or_else(|_| input_directory.to_path_buf());
+    let key = RenderCache::key(&[
+        env!("CARGO_PKG_VERSION").as_bytes(),
+        config.to_string().as_bytes(),
+        input_directory.as_os_str().as_encoded_bytes(),
+        source.as_bytes(),
+        options.extra_css.as_deref().unwrap_or_default().as_bytes(),
+        snippet_content(&options.header).as_bytes(),
+        snippet_content(&options.footer).as_bytes(),
+        options
+            .layout_template
+            .as_deref()
+            .unwrap_or_default()
+            .as_bytes(),
+    ]);
+    Some((RenderCache::new(cache_dir), key))
+}
+
+/// Renders `markdown`, the body of `source` read from `path`, for output to `output_path`, unless
+/// the render cache already holds its HTML. Also returns any cache and key to store a new render
+/// under, as [`render_with_cache`].
+///
+/// # Errors
+/// Errors if the options for the input cannot be set up, for example when a layout template cannot
+/// be read
+fn render_source(
+    path: &Path,
+    output_path: &Path,
+    source: &str,
+    markdown: &str,
+    frontmatter: &Frontmatter,
+    markwrite_options: &MarkwriteOptions,
+    stdout_handle: &mut impl Write,
+) -> std::io::Result<(ParseResults, Option<(RenderCache, String)>)> {
+    let mut options = parse_input_options(
+        path,
+        output_path,
+        frontmatter,
+        markwrite_options,
+        stdout_handle,
+    )?;
+    options.source_line_offset = line_offset(source);
+    Ok(render_with_cache(
+        path,
+        source,
+        markdown,
+        frontmatter,
+        &options,
+        markwrite_options,
+    ))
+}
+
+/// Renders `markdown`, unless the render cache already holds its HTML. On a cache miss with no
+/// warnings, also returns the cache and key to store the new render under. Renders with warnings
+/// are not cached, so the warnings show each time until they are fixed.
+fn render_with_cache(
+    path: &Path,
+    source: &str,
+    markdown: &str,
+    frontmatter: &Frontmatter,
+    options: &ParseInputOptions,
+    markwrite_options: &MarkwriteOptions,
+) -> (ParseResults, Option<(RenderCache, String)>) {
+    let render_cache = render_cache_entry(path, source, markdown, options, markwrite_options);
+    if let Some(value) = render_cache
+        .as_ref()
+        .and_then(|(cache, key)| cache.get(key))
+    {
+        debug!("Render cache hit");
+        let results = ParseResults {
+            html: Some(value.html),
+            headings: None,
+            statistics: Some(TextStatistics::new(value.word_count)),
+            errors: None,
+            timings: StageTimings::default(),
+        };
+        return (results, None);
+    }
+    let results = markdown_to_processed_html(markdown, frontmatter, options);
+    let render_cache = render_cache.filter(|_| results.errors.is_none());
+    (results, render_cache)
+}
+
+/// Writes the plaintext of `markdown` to `dump_path`, parsed with the options grammar checks use,
+/// so code blocks are left out
+fn dump_plaintext(
+    markdown: &str,
+    dump_path: &Path,
+    markwrite_options: &MarkwriteOptions,
+    stdout_handle: &mut impl Write,
+) -> std::io::Result<()> {
+    let plaintext =
+        parse_markdown_to_plaintext(markdown, &grammar_markdown_options(markwrite_options));
+    let dump_display_path = dump_path.display();
+    if let Err(error) = write_output_file(dump_path, &plaintext) {
+        eprintln!("[ ERROR ] Unable to write plaintext to {dump_display_path}: {error}");
+        return Ok(());
+    }
+    writeln!(
+        stdout_handle,
+        "[ INFO ] Wrote grammar check plaintext to {dump_display_path}."
+    )
+}
+
+/// Prints parse `warnings`, which are errors with `strict` mode enabled
+fn display_parse_warnings(
+    warnings: &[String],
+    strict: bool,
+    stdout_handle: &mut impl Write,
+) -> Result<(), notify::Error> {
+    for warning in warnings {
+        writeln!(stdout_handle, "[ WARN ] {warning}")?;
+    }
+    if strict {
+        writeln!(
+            stdout_handle,
+            "[ ERROR ] Strict mode is enabled, so not writing output ({} warnings).",
+            warnings.len()
+        )?;
+        stdout_handle.flush()?;
+        return Err(notify::Error::generic(
+            "Parse warnings found with strict mode enabled",
+        ));
+    }
+    Ok(())
+}
+
+/// Output HTML ending in a single newline, and re-indented when pretty output is enabled. Any
+/// `frontmatter_yaml` is kept in a leading comment when that option is enabled.
+fn format_output_html(
+    html: &str,
+    frontmatter_yaml: Option<&str>,
+    markwrite_options: &MarkwriteOptions,
+) -> String {
+    let html = if markwrite_options.pretty() {
+        pretty_print_html(html, markwrite_options.fragment())
+    } else {
+        format!("{}\n", html.trim_end())
+    };
+    match frontmatter_yaml {
+        Some(value) if markwrite_options.include_frontmatter_in_output() => {
+            format!(
+                "{FRONTMATTER_COMMENT_START}\n{}\n-->\n{html}",
+                escape_frontmatter_comment(value)
+            )
+        }
+        _ => html,
+    }
+}
+
+/// Runs a grammar check with `grammar_checker`, or a checker configured from `markwrite_options`
+/// when none is given, returning the time it took and the number of issues found
+async fn timed_grammar_check(
+    markdown: &str,
+    source: &str,
+    path: &Path,
+    display_path: &str,
+    markwrite_options: &MarkwriteOptions,
+    grammar_checker: Option<&GrammarChecker>,
+    stdout_handle: &mut impl Write,
+) -> (Duration, usize) {
+    let grammar_check_start = Instant::now();
+    let default_grammar_checker;
+    let grammar_checker = if let Some(value) = grammar_checker {
+        value
+    } else {
+        default_grammar_checker = markwrite_options.grammar_checker();
+        &default_grammar_checker
+    };
+    let results = grammar_check(
+        markdown,
+        source,
+        display_path,
+        grammar_checker,
+        markwrite_options,
+        stdout_handle,
+    )
+    .await;
+    let duration = grammar_check_start.elapsed();
+    debug!("Grammar check took {:.3} ms", milliseconds(duration));
+    if markwrite_options.autofix() {
+        if let Err(error) = autofix_spelling(
+            path,
+            source,
+            markdown,
+            &results,
+            grammar_checker.dictionary_words(),
+            stdout_handle,
+        ) {
+            eprintln!("[ ERROR ] Unable to apply spelling fixes: {error}");
+        }
+    }
+    (duration, results.len())
+}
+
+/// Line ending written between lines of HTML output files
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum LineEnding {
+    /// Line feed, as on Unix
+    #[default]
+    Lf,
+
+    /// Carriage return and line feed, as on Windows
+    Crlf,
+}
+
+/// UTF-8 byte order mark, added at the start of output with [`MarkwriteOptions::output_bom`]
+const UTF8_BOM: &str = "\u{feff}";
+
+/// Bytes written for the rendered `html`, with the line endings and byte order mark chosen in
+/// `markwrite_options`. Line endings are only rewritten for CRLF output, and any CRLF endings
+/// already in `html` are kept as they are, rather than doubled.
+fn encoded_output<'a>(html: &'a str, markwrite_options: &MarkwriteOptions) -> Cow<'a, str> {
+    let mut result = match markwrite_options.output_line_endings() {
+        LineEnding::Lf => Cow::Borrowed(html),
+        LineEnding::Crlf => Cow::Owned(html.replace("\r\n", "\n").replace('\n', "\r\n")),
+    };
+    if markwrite_options.output_bom() && !result.starts_with(UTF8_BOM) {
+        result = Cow::Owned(format!("{UTF8_BOM}{result}"));
+    }
+    result
+}
+
+/// Writes rendered HTML to `output_path`, reporting the result. With gzip output enabled, a
+/// compressed copy is written alongside it, or instead of it. Line endings and any byte order
+/// mark are applied to the bytes written.
+fn write_html_output(
+    output_path: &Path,
+    html: &str,
+    word_count: u32,
+    markwrite_options: &MarkwriteOptions,
+    stdout_handle: &mut impl Write,
+) -> Result<(), notify::Error> {
+    let contents = encoded_output(html, markwrite_options);
+    if !markwrite_options.gzip_only() {
+        let output_display_path = output_path.display().to_string();
+        if let Err(error) = write_output_file_with_mode(
+            output_path,
+            contents.as_bytes(),
+            markwrite_options.output_mode(),
+        ) {
+            eprintln!("[ ERROR ] Unable to write to output file: {output_display_path}");
+            return Err(error.into());
+        }
+        info!("Wrote {output_display_path}.");
+        writeln!(
+            stdout_handle,
+            "[ INFO ] Wrote {output_display_path} ({word_count} words)."
+        )?;
+    }
+    if markwrite_options.gzip() {
+        let gzip_path = gzip_output_path(output_path);
+        let gzip_display_path = gzip_path.display().to_string();
+        if let Err(error) = gzip_compress(contents.as_bytes(), markwrite_options.gzip_level())
+            .and_then(|contents| {
+                write_output_file_with_mode(&gzip_path, contents, markwrite_options.output_mode())
+            })
+        {
+            eprintln!("[ ERROR ] Unable to write to output file: {gzip_display_path}");
+            return Err(error.into());
+        }
+        info!("Wrote {gzip_display_path}.");
+        writeln!(
+            stdout_handle,
+            "[ INFO ] Wrote {gzip_display_path} ({word_count} words)."
+        )?;
+    }
+    stdout_handle.flush()?;
+    Ok(())
+}
+
+/// Starts any open in command set in `markwrite_options` on the output just written to
+/// `output_path`, or its gzipped copy with only gzip output enabled, with `html` the output
+/// written, before line endings and any byte order mark are applied. A failing command is
+/// reported, without failing the update.
+fn open_output(output_path: &Path, html: &str, markwrite_options: &MarkwriteOptions) {
+    let Some(command) = markwrite_options.open_in() else {
+        return;
+    };
+    let opened_path = if markwrite_options.gzip_only() {
+        gzip_output_path(output_path)
+    } else {
+        output_path.to_path_buf()
+    };
+    if let Err(message) = run_open_in_command(command, &opened_path, html) {
+        eprintln!("[ ERROR ] {message}.");
+    }
+}
+
+/// Starts `command` on the output at `output_path`, returning without waiting for it, so a viewer
+/// left open does not hold up later updates. The command is split into words as a shell would, and
+/// the path is added as the last argument, unless an argument is `-`, in which case `html` is
+/// written to the command's standard input. Failures once the command has started are reported
+/// from a background task.
+///
+/// # Errors
+/// Errors with a message when the command cannot be parsed or started
+fn run_open_in_command(command: &str, output_path: &Path, html: &str) -> Result<(), String> {
+    let words = shlex::split(command)
+        .ok_or_else(|| format!("Unable to parse the command to open output in: {command}"))?;
+    let (program, arguments) = words
+        .split_first()
+        .ok_or_else(|| String::from("No command given to open output in"))?;
+    let use_stdin = arguments.iter().any(|argument| argument == "-");
+    let mut child_command = Command::new(program);
+    child_command.args(arguments);
+    if use_stdin {
+        child_command.stdin(Stdio::piped());
+    } else {
+        child_command.arg(output_path);
+    }
+    let mut child = child_command
+        .spawn()
+        .map_err(|error| format!("Unable to run {program}: {error}"))?;
+    let stdin_html = child.stdin.take().map(|stdin| (stdin, html.to_string()));
+    let program = program.clone();
+    let output_display_path = output_path.display().to_string();
+    tokio::spawn(async move {
+        if let Some((mut stdin, html)) = stdin_html {
+            if let Err(error) = stdin.write_all(html.as_bytes()).await {
+                eprintln!("[ ERROR ] Unable to pass output to {program}: {error}.");
+            }
+            // Closing standard input lets the command see the end of the output
+            drop(stdin);
+        }
+        match child.wait().await {
+            Ok(status) if !status.success() => {
+                eprintln!("[ ERROR ] {program} failed on {output_display_path} ({status}).");
+            }
+            Ok(_) => {}
+            Err(error) => eprintln!("[ ERROR ] Unable to run {program}: {error}."),
+        }
+    });
+    Ok(())
+}
+
+/// Path of the gzipped copy of the output at `output_path`, with `.gz` appended, so static hosts
+/// find it next to the uncompressed file
+fn gzip_output_path(output_path: &Path) -> PathBuf {
+    let mut result = output_path.as_os_str().to_owned();
+    result.push(".gz");
+    PathBuf::from(result)
+}
+
+/// Gzip compresses `contents` at `level`, from 0 (no compression) to 9 (best compression)
+fn gzip_compress(contents: &[u8], level: u32) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level));
+    encoder.write_all(contents)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_word_to_dictionary, document_title, embedded_source, grammar_check,
+        grammar_check_stream, line_offset, load_dictionaries, load_dictionary, load_extra_css,
+        modified_since, output_frontmatter, output_paths, parse_since, render_fragment,
+        split_frontmatter, strip_frontmatter, update_html, validate_layout_templates, write_index,
+        write_output_file, write_sitemap, BatchSummary, BlockKind, DomHandle, DomNodeData,
+        Frontmatter, GrammarCheckResult, GrammarReportFormat, Heading, LineEnding, Markwrite,
+        MarkwriteOptions, RENDER_COUNT,
+    };
+    use futures::StreamExt;
+    use html5ever::{
+        driver::ParseOpts,
+        local_name, namespace_url, ns, parse_document,
+        tendril::{fmt::UTF8, Tendril, TendrilSink},
+        Attribute, QualName,
+    };
+    use markup5ever_rcdom::{NodeData, RcDom};
+    use std::{
+        cell::Cell,
+        collections::HashSet,
+        fs::{self, read_to_string, remove_file, File},
+        io::{self, BufRead, BufReader, BufWriter, Read, Write},
+        net::TcpListener,
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant, SystemTime},
+    };
+    use yaml_rust2::YamlLoader;
+
+    /// Starts a local grammar server, which replies with the `LanguageTool` response fixture when
+    /// the checked text mentions a "foox", and with no matches otherwise
+    fn start_grammar_server() -> String {
+        start_grammar_server_with(|body| {
+            let mut response = languagetool_response_fixture();
+            if !body.contains("foox") {
+                response["matches"] = serde_json::json!([]);
+            }
+            response
+        })
+    }
+
+    /// Starts a local grammar server, which flags each "foox" in the checked text as a spelling
+    /// mistake, at its offset in UTF-16 code units, as the server counts them
+    fn start_foox_grammar_server() -> String {
+        start_grammar_server_with(|body| {
+            let text = url::form_urlencoded::parse(body.as_bytes())
+                .find(|(name, _)| name == "text")
+                .map(|(_, value)| value.to_string())
+                .unwrap_or_default();
+            let mut response = languagetool_response_fixture();
+            let template = response["matches"][0].clone();
+            let matches: Vec<serde_json::Value> = text
+                .match_indices("foox")
+                .map(|(offset, flagged_text)| {
+                    let mut value = template.clone();
+                    value["offset"] = serde_json::json!(text[..offset].encode_utf16().count());
+                    value["length"] = serde_json::json!(flagged_text.len());
+                    value["context"] =
+                        serde_json::json!({ "text": flagged_text, "offset": 0, "length": 4 });
+                    value
+                })
+                .collect();
+            response["matches"] = serde_json::json!(matches);
+            response
+        })
+    }
+
+    /// Starts a local grammar server, which flags each sentence starting with the same word as the
+    /// sentence before it, at its offset in UTF-16 code units, so only requests holding both
+    /// sentences can find an issue
+    fn start_repeated_sentence_grammar_server() -> String {
+        start_grammar_server_with(|body| {
+            let text = url::form_urlencoded::parse(body.as_bytes())
+                .find(|(name, _)| name == "text")
+                .map(|(_, value)| value.to_string())
+                .unwrap_or_default();
+            let mut response = languagetool_response_fixture();
+            let template = response["matches"][0].clone();
+            let matches: Vec<serde_json::Value> = text
+                .match_indices("Sentence")
+                .skip(1)
+                .map(|(offset, _)| {
+                    let length = "Sentence ".len()
+                        + text[offset + "Sentence ".len()..]
+                            .chars()
+                            .take_while(char::is_ascii_digit)
+                            .count();
+                    let flagged_text = &text[offset..offset + length];
+                    let mut value = template.clone();
+                    value["offset"] = serde_json::json!(text[..offset].encode_utf16().count());
+                    value["length"] = serde_json::json!(length);
+                    value["context"] =
+                        serde_json::json!({ "text": flagged_text, "offset": 0, "length": length });
+                    value["rule"]["id"] = serde_json::json!("ENGLISH_WORD_REPEAT_BEGINNING_RULE");
+                    value
+                })
+                .collect();
+            response["matches"] = serde_json::json!(matches);
+            response
+        })
+    }
+
+    fn languagetool_response_fixture() -> serde_json::Value {
+        serde_json::from_str(include_str!("../fixtures/languagetool_response.json"))
+            .expect("Expected valid LanguageTool response fixture")
+    }
+
+    /// Starts a local grammar server, which replies with the response `respond` gives for each
+    /// request body
+    fn start_grammar_server_with(
+        respond: impl Fn(&str) -> serde_json::Value + Send + 'static,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Error binding test server");
+        let url = format!(
+            "http://{}/v2/check",
+            listener.local_addr().expect("Expected test server address")
+        );
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream);
+                let mut content_length = 0;
+                let mut line = String::new();
+                while reader.read_line(&mut line).expect("Error reading request") > 0
+                    && line != "\r\n"
+                {
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().expect("Expected content length");
+                        }
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0; content_length];
+                reader
+                    .read_exact(&mut body)
+                    .expect("Error reading request body");
+                let response_body = respond(&String::from_utf8_lossy(&body)).to_string();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response_body}",
+                    response_body.len()
+                )
+                .expect("Error writing response");
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn strip_frontmatter_removes_frontmatter() {
+        // arrange
+        let markdown = "---
+title: Test Document
+---
+
+# Test
+
+This is a test.";
+
+        // act
+        let (_, result) = strip_frontmatter(markdown);
+
+        // assert
+        let expected_result = "# Test
+
+This is a test.";
+        assert_eq!(result, expected_result);
+    }
+    #[test]
+    fn add_word_to_dictionary_inserts_new_word() {
+        // arrange
+        let mut dictionary: HashSet<String> = HashSet::new();
+        let stdout = io::stdout();
+        let handle = BufWriter::new(stdout);
+        let dictionary_path = "fixtures/custom.dict";
+        let dictionary_file = assert_fs::NamedTempFile::new("custom.dict")
+            .expect("Error getting temp dictionary path");
+        let temp_dictionary_path = dictionary_file.path();
+        fs::copy(dictionary_path, temp_dictionary_path).expect("Error copying temp fixture");
+
+        // act
+        let new_word = "stairs";
+        add_word_to_dictionary(new_word, temp_dictionary_path, &mut dictionary, handle);
+
+        // assert
+        assert!(dictionary.contains(new_word));
+    }
+
+    #[test]
+    fn add_word_to_dictionary_updates_dictionary_file() {
+        // arrange
+        let mut dictionary: HashSet<String> = HashSet::new();
+        let stdout = io::stdout();
+        let handle = BufWriter::new(stdout);
+        let dictionary_path = "fixtures/custom.dict";
+        let dictionary_file = assert_fs::NamedTempFile::new("custom.dict")
+            .expect("Error getting temp dictionary path");
+        let temp_dictionary_path = dictionary_file.path();
+        fs::copy(dictionary_path, temp_dictionary_path).expect("Error copying temp fixture");
+
+        // act
+        let new_word = "stairs";
+        add_word_to_dictionary(new_word, temp_dictionary_path, &mut dictionary, handle);
+
+        // assert
+        let dictionary_file_contents =
+            read_to_string(temp_dictionary_path).expect("Failed to read file to string");
+        assert!(dictionary_file_contents.contains(new_word));
+    }
+
+    #[test]
+    fn load_dictionary_returns_input_dictionary_when_dictionary_file_is_absent() {
+        //arrange
+        let mut dictionary: HashSet<String> = HashSet::new();
+        let stdout = io::stdout();
+        let handle = BufWriter::new(stdout);
+
+        // act
+        load_dictionary("nonsense.dict", &mut dictionary, handle);
+
+        //assert
+        assert_eq!(dictionary.len(), 0);
+    }
+
+    #[test]
+    fn load_dictionary_adds_words_from_file_to_dictionary() {
+        //arrange
+        let mut dictionary: HashSet<String> = HashSet::new();
+        let stdout = io::stdout();
+        let handle = io::BufWriter::new(stdout);
+
+        // act
+        load_dictionary("fixtures/custom.dict", &mut dictionary, handle);
+
+        //assert
+        assert_eq!(dictionary.len(), 3);
+        assert!(dictionary.contains("Cheese"));
+        assert!(dictionary.contains("apples"));
+    }
+
+    #[test]
+    fn load_dictionary_merges_words_from_multiple_files() {
+        // arrange
+        let mut dictionary: HashSet<String> = HashSet::new();
+        let personal_dictionary = assert_fs::NamedTempFile::new("personal.dict")
+            .expect("Error getting temp dictionary path");
+        fs::write(personal_dictionary.path(), "apples\nmarkwrite\nrustacean\n")
+            .expect("Error writing temp dictionary");
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        let project_count = load_dictionary("fixtures/custom.dict", &mut dictionary, &mut handle);
+        let personal_count =
+            load_dictionary(personal_dictionary.path(), &mut dictionary, &mut handle);
+
+        // assert
+        assert_eq!(project_count, 3);
+        assert_eq!(personal_count, 2);
+        assert_eq!(dictionary.len(), 5);
+        assert!(dictionary.contains("Cheese"));
+        assert!(dictionary.contains("rustacean"));
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ INFO ] Loaded 2 words from dictionary"));
+    }
+
+    #[test]
+    fn load_dictionaries_includes_default_dictionary_unless_disabled() {
+        // arrange
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        let dictionary = load_dictionaries(&["fixtures/custom.dict"], true, &mut handle);
+        let without_default = load_dictionaries(&["fixtures/custom.dict"], false, &mut handle);
+
+        // assert
+        assert!(dictionary.contains("LanguageTool"));
+        assert!(dictionary.contains("Cheese"));
+        assert!(!without_default.contains("LanguageTool"));
+        assert!(without_default.contains("Cheese"));
+        assert_eq!(without_default.len(), 3);
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ INFO ] Loaded 53 words from the default dictionary."));
+    }
+
+    #[tokio::test]
+    async fn markwrite_check_skips_spelling_results_in_default_dictionary() {
+        // arrange
+        let url = start_grammar_server_with(|_body| {
+            let mut response = languagetool_response_fixture();
+            let mut bundled_term = response["matches"][0].clone();
+            bundled_term["context"] = serde_json::json!({
+                "text": "We query GraphQL from the frontend.",
+                "offset": 9,
+                "length": 7
+            });
+            bundled_term["offset"] = serde_json::json!(9);
+            bundled_term["length"] = serde_json::json!(7);
+            response["matches"] = serde_json::json!([bundled_term]);
+            response
+        });
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("terms.md");
+        fs::write(&markdown_path, "We query GraphQL from the frontend.\n")
+            .expect("Error writing temp markdown");
+        let options = || {
+            let mut result = MarkwriteOptions::default();
+            result.set_grammar_url(&url);
+            result
+        };
+        let mut handle: Vec<u8> = Vec::new();
+        let no_paths: &[&str] = &[];
+        let markwrite = Markwrite::new(options(), load_dictionaries(no_paths, true, &mut handle));
+        let no_default_markwrite =
+            Markwrite::new(options(), load_dictionaries(no_paths, false, &mut handle));
+
+        // act
+        let issue_count = markwrite
+            .check_file_grammar(&markdown_path, &mut handle)
+            .await
+            .expect("Error checking grammar");
+        let no_default_issue_count = no_default_markwrite
+            .check_file_grammar(&markdown_path, &mut handle)
+            .await
+            .expect("Error checking grammar");
+
+        // assert
+        assert_eq!(issue_count, 0);
+        assert_eq!(no_default_issue_count, 1);
+    }
+
+    #[test]
+    fn strip_frontmatter_removes_frontmatter_after_byte_order_mark() {
+        // arrange
+        let markdown = "\u{feff}---\ntitle: Test Document\n---\n\n# Test\n\nThis is a test.";
+
+        // act
+        let (frontmatter, result) = strip_frontmatter(markdown);
+
+        // assert
+        assert_eq!(frontmatter, Some("title: Test Document"));
+        assert_eq!(result, "# Test\n\nThis is a test.");
+    }
+
+    #[test]
+    fn strip_frontmatter_removes_frontmatter_after_leading_blank_line() {
+        // arrange
+        let markdown = "\n  \n---\ntitle: Test Document\n---\n\n# Test\n\nThis is a test.";
+
+        // act
+        let (frontmatter, result) = strip_frontmatter(markdown);
+
+        // assert
+        assert_eq!(frontmatter, Some("title: Test Document"));
+        assert_eq!(result, "# Test\n\nThis is a test.");
+    }
+
+    #[test]
+    fn split_frontmatter_finds_body_repeated_in_frontmatter() {
+        // arrange
+        let markdown = "---\ndescription: Short post.\n---\n\nShort post.\n";
+
+        // act
+        let (frontmatter, body_range) = split_frontmatter(markdown);
+
+        // assert
+        assert_eq!(frontmatter, Some("description: Short post."));
+        assert_eq!(body_range.start, markdown.rfind("Short post.").unwrap());
+        assert_eq!(&markdown[body_range], "Short post.");
+        assert_eq!(line_offset(markdown), 4);
+    }
+
+    #[test]
+    fn strip_frontmatter_ignores_document_starting_with_text() {
+        // arrange
+        let markdown = "\u{feff}# Test\n\n---\n\nThis is a test.\n---\n";
+
+        // act
+        let (frontmatter, result) = strip_frontmatter(markdown);
+
+        // assert
+        assert_eq!(frontmatter, None);
+        assert_eq!(result, "# Test\n\n---\n\nThis is a test.\n---\n");
+    }
+
+    #[test]
+    fn strip_frontmatter_returns_expected_result_when_frontmatter_is_absent() {
+        // arrange
+        let markdown = "# Test
+
+This is a test.";
+
+        // act
+        let (_, result) = strip_frontmatter(markdown);
+
+        // assert
+        assert_eq!(result, markdown);
+    }
+
+    #[tokio::test]
+    async fn update_html_writes_parsed_markdown_to_html_file() {
+        // arrange
+        let markdown_path = Path::new("./fixtures/file.md");
+        let html_path = Path::new("./fixtures/file_a.html");
+        let stdout = io::stdout();
+        let mut handle = io::BufWriter::new(stdout);
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let mut html_file = File::open(html_path).unwrap();
+        let parse_result = parse_document(RcDom::default(), ParseOpts::default())
+            .from_utf8()
+            .read_from(&mut html_file)
+            .expect("Error parsing generated HTML file");
+        assert_eq!(parse_result.errors.len(), 0);
+
+        // cleanup
+        remove_file(html_path).expect("Unable to delete HTML output in cleanup");
+    }
+
+    #[tokio::test]
+    async fn update_html_output_has_expected_tags_set() {
+        // arrange
+        let markdown_path = Path::new("./fixtures/file.md");
+        let html_path = Path::new("./fixtures/file_b.html");
+        let stdout = io::stdout();
+        let mut handle = io::BufWriter::new(stdout);
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let mut html_file = File::open(html_path).unwrap();
+        let parse_result = parse_document(RcDom::default(), ParseOpts::default())
+            .from_utf8()
+            .read_from(&mut html_file)
+            .expect("Error parsing generated HTML file");
+
+        let document_nodes = parse_result.document.children.borrow();
+        let mut document_nodes_iterator = document_nodes.iter();
+        let doctype_node = document_nodes_iterator.next().unwrap();
+
+        // check DOCTYPE element
+        let NodeData::Doctype { ref name, .. } = doctype_node.data else {
+            unimplemented!("Expected DOCTYPE element to exist")
+        };
+        assert_eq!(name, &Tendril::<UTF8>::from_slice("html"));
+
+        // check html element
+        let html_node = document_nodes_iterator.next().unwrap();
+        let NodeData::Element {
+            ref attrs,
+            ref name,
+            ..
+        } = html_node.data
+        else {
+            unimplemented!("Expected html element to exist")
+        };
+        assert_eq!(name, &QualName::new(None, ns!(html), local_name!("html")));
+
+        // check html lang is set
+        let mut attrs = attrs.borrow_mut();
+        let lang = match attrs.iter_mut().find(|val| &*val.name.local == "lang") {
+            Some(value) => &value.value,
+            None => unimplemented!("Expected lang attribute to be set on html element"),
+        };
+        assert_eq!(lang, &Tendril::<UTF8>::from_slice("en"));
+
+        // cleanup
+        remove_file(html_path).expect("Unable to delete HTML output in cleanup");
+    }
+
+    #[tokio::test]
+    async fn update_html_inlines_extra_css_after_global_css() {
+        // arrange
+        let markdown_path = Path::new("./fixtures/file.md");
+        let html_path = Path::new("./fixtures/file_c.html");
+        let stdout = io::stdout();
+        let mut handle = io::BufWriter::new(stdout);
+        let mut options = MarkwriteOptions::default();
+        options.add_extra_css_path("fixtures/extra.css");
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(html_path).expect("Failed to read file to string");
+        let head_end = html
+            .find("</head>")
+            .expect("Expected output to have a head");
+        let global_css_position = html
+            .find(".screen-reader-text{")
+            .expect("Expected global CSS in output");
+        let extra_css_position = html
+            .find(".extra-css-fixture{color:rebeccapurple}")
+            .expect("Expected extra CSS in output");
+        assert!(global_css_position < extra_css_position);
+        assert!(extra_css_position < head_end);
+
+        // cleanup
+        remove_file(html_path).expect("Unable to delete HTML output in cleanup");
+    }
+
+    #[test]
+    fn load_extra_css_skips_stylesheets_outside_project_root() {
+        // arrange
+        let stylesheet = assert_fs::NamedTempFile::new("outside.css")
+            .expect("Error getting temp stylesheet path");
+        fs::write(stylesheet.path(), "body{color:red}").expect("Error writing temp stylesheet");
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        let result = load_extra_css(
+            &[stylesheet.path(), Path::new("fixtures/extra.css")],
+            Path::new("."),
+            &mut handle,
+        );
+
+        // assert
+        assert_eq!(
+            result,
+            Some(String::from(".extra-css-fixture{color:rebeccapurple}\n"))
+        );
+        let output = String::from_utf8(handle).expect("Expected valid UTF-8 output");
+        assert!(output.contains("is outside the project root, skipping."));
+    }
+
+    #[tokio::test]
+    async fn update_html_skips_output_for_warnings_in_strict_mode() {
+        // arrange
+        let markdown_path = Path::new("./fixtures/duplicate_headings.md");
+        let html_path = Path::new("./fixtures/duplicate_headings_a.html");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_strict();
+
+        // act
+        let result = update_html(&markdown_path, &html_path, &options, None, &mut handle).await;
+
+        // assert
+        assert!(result.is_err());
+        assert!(!html_path.exists());
+        let output = String::from_utf8(handle).expect("Expected valid UTF-8 output");
+        assert!(output.contains("[ WARN ] Duplicate heading id: section"));
+    }
+
+    #[tokio::test]
+    async fn update_html_writes_output_for_warnings_when_not_strict() {
+        // arrange
+        let markdown_path = Path::new("./fixtures/duplicate_headings.md");
+        let html_path = Path::new("./fixtures/duplicate_headings_b.html");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        let result = update_html(&markdown_path, &html_path, &options, None, &mut handle).await;
+
+        // assert
+        assert!(result.is_ok());
+        assert!(html_path.exists());
+        let output = String::from_utf8(handle).expect("Expected valid UTF-8 output");
+        assert!(output.contains("[ WARN ] Duplicate heading id: section"));
+
+        // cleanup
+        remove_file(html_path).expect("Unable to delete HTML output in cleanup");
+    }
+
+    #[tokio::test]
+    async fn update_html_writes_timings_summary_when_enabled() {
+        // arrange
+        let markdown_path = Path::new("./fixtures/file.md");
+        let html_path = Path::new("./fixtures/file_d.html");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_timings();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let output = String::from_utf8(handle).expect("Expected valid UTF-8 output");
+        let summary = output
+            .lines()
+            .find(|line| line.starts_with("[ INFO ] Timings: "))
+            .expect("Expected timings summary line");
+        assert!(summary.contains("parse "));
+        assert!(summary.contains("HTML processing "));
+        assert!(summary.contains("grammar check skipped"));
+
+        // cleanup
+        remove_file(html_path).expect("Unable to delete HTML output in cleanup");
+    }
+
+    #[tokio::test]
+    async fn update_html_creates_missing_output_directory() {
+        // arrange
+        let markdown_path = Path::new("./fixtures/file.md");
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let html_path = temp_dir.path().join("nonexistent_dir/out.html");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        assert!(html_path.exists());
+        let output = String::from_utf8(handle).expect("Expected valid UTF-8 output");
+        assert!(output.contains("out.html (5 words)."));
+    }
+
+    #[test]
+    fn document_title_prefers_frontmatter_title() {
+        // arrange
+        let frontmatter = Frontmatter {
+            title: Some(String::from("Frontmatter Title")),
+            ..Default::default()
+        };
+        let headings = vec![Heading::new("Heading Title", "heading-title", 1, 0)];
+
+        // act
+        let result = document_title(&frontmatter, &headings, Some("Site Name"));
+
+        // assert
+        assert_eq!(result, "Frontmatter Title");
+    }
+
+    #[test]
+    fn document_title_derives_title_from_first_h1() {
+        // arrange
+        let frontmatter = Frontmatter::default();
+        let headings = vec![
+            Heading::new("Introduction", "introduction", 2, 0),
+            Heading::new("Heading Title", "heading-title", 1, 16),
+            Heading::new("Second Title", "second-title", 1, 40),
+        ];
+
+        // act
+        let result = document_title(&frontmatter, &headings, Some("Site Name"));
+
+        // assert
+        assert_eq!(result, "Heading Title");
+    }
+
+    #[test]
+    fn document_title_uses_configured_default_title_without_h1() {
+        // arrange
+        let frontmatter = Frontmatter::default();
+        let headings = vec![Heading::new("Introduction", "introduction", 2, 0)];
+
+        // act
+        let result = document_title(&frontmatter, &headings, Some("Site Name"));
+        let fallback_result = document_title(&frontmatter, &headings, None);
+
+        // assert
+        assert_eq!(result, "Site Name");
+        assert_eq!(fallback_result, "Markwrite Document");
+    }
+
+    #[tokio::test]
+    async fn update_html_renders_inputs_into_output_directory() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let source_directory = temp_dir.path().join("posts");
+        fs::create_dir_all(source_directory.join("nested")).expect("Error creating temp source");
+        fs::write(source_directory.join("first.md"), "# First\n\nFirst post.")
+            .expect("Error writing temp markdown");
+        fs::write(
+            source_directory.join("nested").join("second.md"),
+            "# Second\n\nSecond post.",
+        )
+        .expect("Error writing temp markdown");
+        let output_directory = temp_dir.path().join("public");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        let paths = output_paths(&[source_directory], None, Some(&output_directory))
+            .expect("Error computing output paths");
+        for (input_path, output_path) in &paths {
+            update_html(input_path, output_path, &options, None, &mut handle)
+                .await
+                .expect("Error calling update_html");
+        }
+
+        // assert
+        assert_eq!(paths.len(), 2);
+        let first = read_to_string(output_directory.join("first.html"))
+            .expect("Expected first output in output directory");
+        assert!(first.contains("First post."));
+        let second = read_to_string(output_directory.join("nested").join("second.html"))
+            .expect("Expected second output mirrored into nested output directory");
+        assert!(second.contains("Second post."));
+    }
+
+    #[test]
+    fn output_paths_rejects_output_file_for_multiple_inputs() {
+        // arrange
+        let inputs = [PathBuf::from("first.md"), PathBuf::from("second.md")];
+
+        // act
+        let result = output_paths(&inputs, Some(Path::new("out.html")), None);
+
+        // assert
+        let error = result.expect_err("Expected error for --output with multiple inputs");
+        assert!(error.to_string().contains("--output-dir"));
+    }
+
+    #[test]
+    fn write_output_file_writes_complete_file_without_leaving_temporary_files() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let output_path = temp_dir.path().join("nested").join("post.html");
+        fs::create_dir_all(
+            output_path
+                .parent()
+                .expect("Expected output parent directory"),
+        )
+        .expect("Error creating temp output directory");
+        fs::write(&output_path, "<!DOCTYPE html><html><body>Old")
+            .expect("Error writing stale output");
+        let contents = format!(
+            "<!DOCTYPE html><html><body>{}</body></html>",
+            "<p>Paragraph</p>".repeat(10_000)
+        );
+
+        // act
+        write_output_file(&output_path, &contents).expect("Error writing output file");
+
+        // assert
+        let result = read_to_string(&output_path).expect("Error reading output file");
+        assert!(result.ends_with("</html>"));
+        assert_eq!(result, contents);
+        let file_names = fs::read_dir(output_path.parent().expect("Expected output parent"))
+            .expect("Error reading output directory")
+            .map(|entry| entry.expect("Expected directory entry").file_name())
+            .collect::<Vec<_>>();
+        assert_eq!(file_names, vec![std::ffi::OsString::from("post.html")]);
+    }
+
+    #[tokio::test]
+    async fn update_html_renders_sidebar_toc_outside_main_content() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("guide.md");
+        let html_path = temp_dir.path().join("guide.html");
+        fs::write(
+            &markdown_path,
+            "# Guide\n\n## Install\n\nText.\n\n### Build & run\n\nMore text.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_sidebar_toc();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        let (before_main_end, after_main_end) = html
+            .split_once("</main>")
+            .expect("Expected main element in output");
+        assert!(!before_main_end.contains("<aside"));
+        assert!(after_main_end.contains(r#"<aside class="sidebar-toc""#));
+        assert!(after_main_end
+            .contains(r##"<li class="sidebar-toc-level-2"><a href="#install">Install</a></li>"##));
+        assert!(after_main_end.contains(
+            r##"<li class="sidebar-toc-level-3"><a href="#build-run">Build &amp; run</a></li>"##
+        ));
+        assert!(!after_main_end.contains(r##"href="#guide""##));
+    }
+
+    #[tokio::test]
+    async fn update_html_omits_sidebar_toc_by_default() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("guide.md");
+        let html_path = temp_dir.path().join("guide.html");
+        fs::write(&markdown_path, "# Guide\n\n## Install\n\nText.\n")
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(!html.contains("<aside"));
+    }
+    #[tokio::test]
+    async fn update_html_skips_draft_by_default() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("draft.md");
+        let html_path = temp_dir.path().join("draft.html");
+        fs::write(
+            &markdown_path,
+            "---\ntitle: Unfinished\ndraft: true\n---\n\n# Unfinished\n\nSome text.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        assert!(!html_path.exists());
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ INFO ] Skipped draft"));
+    }
+
+    #[tokio::test]
+    async fn update_html_writes_draft_when_drafts_are_included() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("draft.md");
+        let html_path = temp_dir.path().join("draft.html");
+        fs::write(
+            &markdown_path,
+            "---\ntitle: Unfinished\ndraft: true\n---\n\n# Unfinished\n\nSome text.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_include_drafts();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(html.contains("<title>Unfinished</title>"));
+    }
+    #[tokio::test]
+    async fn update_html_embedded_source_round_trips_to_body_markdown() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("source.md");
+        let html_path = temp_dir.path().join("source.html");
+        let body = "# Source\n\nTom &amp; Jerry use <kbd>Ctrl</kbd> & `</script><script>alert(1)</script>`.\n\n<!-- note -->\n";
+        fs::write(&markdown_path, format!("---\ntitle: Source\n---\n\n{body}"))
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_embed_source();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(!html.contains("</script><script>alert(1)"));
+        let result = embedded_source(&html).expect("Expected embedded markdown source");
+        assert_eq!(result, body.trim());
+    }
+
+    /// Text content of the element in the tree under `node` with an `id` of `id`
+    fn element_text_by_id(node: &markup5ever_rcdom::Handle, id: &str) -> Option<String> {
+        if let NodeData::Element { attrs, .. } = &node.data {
+            let matches_id = attrs.borrow().iter().any(|attribute| {
+                attribute.name.local == local_name!("id") && &*attribute.value == id
+            });
+            if matches_id {
+                return Some(
+                    node.children
+                        .borrow()
+                        .iter()
+                        .filter_map(|child| match &child.data {
+                            NodeData::Text { contents } => Some(contents.borrow().to_string()),
+                            _ => None,
+                        })
+                        .collect(),
+                );
+            }
+        }
+        node.children
+            .borrow()
+            .iter()
+            .find_map(|child| element_text_by_id(child, id))
+    }
+
+    #[tokio::test]
+    async fn update_html_embedded_source_is_script_text_content() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("text.md");
+        let html_path = temp_dir.path().join("text.html");
+        let body = "# Text\n\nTom &amp; Jerry know 1 < 2 && <kbd>Ctrl</kbd> works.\n";
+        fs::write(&markdown_path, body).expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_embed_source();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        let dom = parse_document(RcDom::default(), ParseOpts::default()).one(html.as_str());
+        assert_eq!(
+            element_text_by_id(&dom.document, "markdown-source"),
+            Some(body.to_string())
+        );
+    }
+    #[test]
+    fn render_fragment_outputs_content_without_document() {
+        // arrange
+        let markdown = "---\ntitle: Fragment\n---\n\n## Section\n\nSome \"quoted\" text.\n\n```rust\nfn main() {}\n```\n";
+
+        // act
+        let result = render_fragment(markdown).expect("Expected fragment HTML");
+
+        // assert
+        assert!(result.contains(r#"<h2 id="section">Section"#));
+        assert!(result.contains("Some “quoted” text."));
+        assert!(!result.contains("<html"));
+        assert!(!result.contains("<head"));
+        assert!(!result.contains("<script"));
+        assert!(!result.contains("Prism"));
+        assert!(!result.contains("title: Fragment"));
+    }
+
+    #[tokio::test]
+    async fn update_html_writes_fragment_when_enabled() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("fragment.md");
+        let html_path = temp_dir.path().join("fragment.html");
+        fs::write(&markdown_path, "# Fragment\n\nSome text.\n")
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_fragment();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(html.contains("<p>Some text.</p>"));
+        assert!(!html.contains("<!DOCTYPE"));
+        assert!(!html.contains("<html"));
+        assert!(!html.contains("<head"));
+        assert!(!html.contains("Prism"));
+    }
+    #[tokio::test]
+    async fn update_html_writes_valid_pretty_html_ending_in_newline() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("pretty.md");
+        let html_path = temp_dir.path().join("pretty.html");
+        fs::write(
+            &markdown_path,
+            "# Pretty\n\n## Section\n\nSome *emphasised* text.\n\n- one\n- two\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_pretty();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(html.ends_with("</html>\n"));
+        assert!(html.contains("\n    <main>\n"));
+        assert!(html.contains("\n      <ul>\n        <li>one</li>\n"));
+        assert!(html.contains("<p>Some <em>emphasised</em> text.</p>"));
+        assert!(html.contains("fn main() {\n    println!(\"hi\");\n}\n</code></pre>"));
+        let parse_result =
+            parse_document(RcDom::default(), ParseOpts::default()).one(html.as_str());
+        assert_eq!(parse_result.errors.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn update_html_output_ends_in_single_newline() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("compact.md");
+        let html_path = temp_dir.path().join("compact.html");
+        fs::write(&markdown_path, "# Compact\n\nSome text.\n")
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(html.ends_with("</html>\n"));
+        assert!(!html.ends_with("\n\n"));
+    }
+    #[tokio::test]
+    async fn update_html_labels_grammar_results_from_image_alt_text() {
+        // arrange
+        let url = start_foox_grammar_server();
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("alt.md");
+        let html_path = temp_dir.path().join("alt.html");
+        fs::write(
+            &markdown_path,
+            "# Photos\n\nHere is my dog.\n\n![A sleepy foox in the sun](fox.png)\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_grammar_check();
+        options.enable_check_only();
+        options.set_grammar_url(&url);
+        options.set_grammar_report_format(GrammarReportFormat::Json);
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        let report_start = output.find("[\n").expect("Expected JSON report");
+        let report: serde_json::Value = serde_json::Deserializer::from_str(&output[report_start..])
+            .into_iter()
+            .next()
+            .expect("Expected JSON report")
+            .expect("Expected valid JSON report");
+        let results = report.as_array().expect("Expected array of results");
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|result| result["altText"] == true));
+        assert_eq!(results[0]["line"], 5);
+        assert_eq!(results[0]["column"], 12);
+    }
+
+    #[tokio::test]
+    async fn update_html_shares_one_grammar_checker_between_calls() {
+        // arrange
+        let url = start_grammar_server();
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let first_markdown_path = temp_dir.path().join("first.md");
+        let second_markdown_path = temp_dir.path().join("second.md");
+        let first_html_path = temp_dir.path().join("first.html");
+        let second_html_path = temp_dir.path().join("second.html");
+        fs::write(&first_markdown_path, "# First\n\nThe quick brown foox.\n")
+            .expect("Error writing temp markdown");
+        fs::write(&second_markdown_path, "# Second\n\nAnother foox jumps.\n")
+            .expect("Error writing temp markdown");
+        let mut first_handle: Vec<u8> = Vec::new();
+        let mut second_handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_grammar_check();
+        options.enable_check_only();
+        options.set_grammar_url(&url);
+        options.set_grammar_report_format(GrammarReportFormat::Json);
+        let grammar_checker = options.grammar_checker();
+
+        // act
+        update_html(
+            &first_markdown_path,
+            &first_html_path,
+            &options,
+            Some(&grammar_checker),
+            &mut first_handle,
+        )
+        .await
+        .expect("Error calling update_html");
+        update_html(
+            &second_markdown_path,
+            &second_html_path,
+            &options,
+            Some(&grammar_checker),
+            &mut second_handle,
+        )
+        .await
+        .expect("Error calling update_html");
+
+        // assert
+        for handle in [first_handle, second_handle] {
+            let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+            assert!(output.contains("\"ruleId\": \"MORFOLOGIK_RULE_EN_GB\""));
+        }
+    }
+
+    #[tokio::test]
+    async fn update_html_keeps_frontmatter_in_output_comment_when_enabled() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("frontmatter.md");
+        let html_path = temp_dir.path().join("frontmatter.html");
+        fs::write(
+            &markdown_path,
+            "---\ntitle: Arrows --> and & more\ndescription: Kept metadata\n---\n\n# Heading\n\nBody.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_include_frontmatter_in_output();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(html.starts_with("<!-- frontmatter\n"));
+        assert!(html.contains("Arrows --&gt; and &amp; more"));
+        let yaml = output_frontmatter(&html).expect("Expected frontmatter comment");
+        let docs = YamlLoader::load_from_str(&yaml).expect("Expected valid YAML");
+        assert_eq!(docs[0]["title"].as_str(), Some("Arrows --> and & more"));
+        assert_eq!(docs[0]["description"].as_str(), Some("Kept metadata"));
+    }
+
+    #[tokio::test]
+    async fn update_html_omits_frontmatter_comment_by_default() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("frontmatter.md");
+        let html_path = temp_dir.path().join("frontmatter.html");
+        fs::write(
+            &markdown_path,
+            "---\ntitle: Hidden Metadata\n---\n\n# Heading\n\nBody.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert_eq!(output_frontmatter(&html), None);
+    }
+
+    #[tokio::test]
+    async fn update_html_renders_byline_with_word_count_when_enabled() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("post.md");
+        let html_path = temp_dir.path().join("post.html");
+        fs::write(
+            &markdown_path,
+            "# Post\n\nThere are exactly seven words here.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_byline();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        let (_, main_content) = html
+            .split_once("<main>")
+            .expect("Expected main element in output");
+        assert!(main_content
+            .trim_start()
+            .starts_with(r#"<div class="markwrite-byline">7 words · 1 min read</div>"#));
+    }
+
+    #[tokio::test]
+    async fn update_html_omits_byline_by_default() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("post.md");
+        let html_path = temp_dir.path().join("post.html");
+        fs::write(&markdown_path, "# Post\n\nSome words.\n").expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(!html.contains(r#"<div class="markwrite-byline">"#));
+    }
+
+    #[tokio::test]
+    async fn update_html_lint_warns_with_source_line_numbers() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("lint.md");
+        let html_path = temp_dir.path().join("lint.html");
+        fs::write(
+            &markdown_path,
+            "---\ntitle: Lint\n---\n\n# Lint\n\nTrailing spaces here.  \nA tidy line.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_lint();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ WARN ] Trailing whitespace on line 7\n"));
+    }
+
+    #[tokio::test]
+    async fn update_html_uses_og_title_for_social_tags_and_title_for_title_element() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("social.md");
+        let html_path = temp_dir.path().join("social.html");
+        fs::write(
+            &markdown_path,
+            "---\ntitle: Baking Bread | Rodney Lab\nog_title: Baking Bread\n---\n\nBody.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(html.contains("<title>Baking Bread | Rodney Lab</title>"));
+        assert!(html.contains(r#"<meta property="og:title" content="Baking Bread" >"#));
+        assert!(html.contains(r#"<meta name="twitter:title" content="Baking Bread" >"#));
+    }
+
+    #[tokio::test]
+    async fn update_html_social_title_falls_back_to_title() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("social.md");
+        let html_path = temp_dir.path().join("social.html");
+        fs::write(&markdown_path, "---\ntitle: Baking Bread\n---\n\nBody.\n")
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(html.contains("<title>Baking Bread</title>"));
+        assert!(html.contains(r#"<meta property="og:title" content="Baking Bread" >"#));
+    }
+
+    #[tokio::test]
+    async fn update_html_rewrites_image_sources_to_images_base_url() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("cdn.md");
+        let html_path = temp_dir.path().join("cdn.html");
+        fs::write(
+            &markdown_path,
+            "# CDN\n\n![Dog](images/dog.jpg)\n\nRead [about me](about/).\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.set_images_base_url("https://cdn.example.com");
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML output");
+        assert!(html.contains(r#"<img src="https://cdn.example.com/images/dog.jpg" alt="Dog">"#));
+        assert!(html.contains(r#"<a href="about/">about me</a>"#));
+    }
+
+    #[tokio::test]
+    async fn update_html_reports_parse_errors_at_source_line() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("unterminated.md");
+        let html_path = temp_dir.path().join("unterminated.html");
+        fs::write(
+            &markdown_path,
+            "---\ntitle: Unterminated\n---\n\n# Unterminated\n\n\
+<!-- markwrite:grammar-ignore-start -->\n\nNever checked.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        let result = update_html(&markdown_path, &html_path, &options, None, &mut handle).await;
+
+        // assert
+        assert!(result.is_err());
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains(
+            "Error parsing markdown at line 7, column 1: grammar ignore start marker without a \
+matching end marker\n"
+        ));
+        assert!(!html_path.exists());
+    }
+
+    #[tokio::test]
+    async fn update_html_makes_no_grammar_requests_with_languagetool_disabled() {
+        // arrange
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Error binding test server");
+        let url = format!(
+            "http://{}/v2/check",
+            listener.local_addr().expect("Expected test server address")
+        );
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("offline.md");
+        let html_path = temp_dir.path().join("offline.html");
+        fs::write(&markdown_path, "# Offline\n\nA foox in the text.\n")
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_grammar_check();
+        options.set_grammar_url(&url);
+        options.disable_languagetool();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        assert!(!options.check_grammar());
+        listener
+            .set_nonblocking(true)
+            .expect("Error setting test server non-blocking");
+        let request = listener.accept();
+        assert!(
+            matches!(&request, Err(error) if error.kind() == io::ErrorKind::WouldBlock),
+            "Expected no grammar server requests"
+        );
+        assert!(html_path.exists());
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(!output.contains("foox"));
+    }
+
+    #[tokio::test]
+    async fn update_html_lint_warns_about_long_sentences() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("long.md");
+        let html_path = temp_dir.path().join("long.html");
+        fs::write(
+            &markdown_path,
+            "---\ntitle: Long\n---\n\n# Long\n\nA short sentence. Another short one.\n\n\
+This sentence keeps going and going, adding clause after clause, because the writer never \
+quite found a good place to stop, so it rambles on past any sensible length for a reader.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_lint();
+        options.set_max_sentence_words(20);
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ WARN ] Sentence of 32 words on line 9, longer than 20\n"));
+        assert!(output.contains("[ INFO ] Sentences average 12.7 words, the longest has 32.\n"));
+        assert_eq!(output.matches("[ WARN ] Sentence of").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn update_html_autofix_applies_single_suggestion_spelling_fixes() {
+        // arrange
+        // Flags the "foox" from the fixture, with several suggestions, and each "dogg", with one
+        let url = start_grammar_server_with(|body| {
+            let text = url::form_urlencoded::parse(body.as_bytes())
+                .find(|(name, _)| name == "text")
+                .map(|(_, value)| value.to_string())
+                .unwrap_or_default();
+            let mut response = languagetool_response_fixture();
+            let mut matches = vec![response["matches"][0].clone()];
+            for (offset, flagged_text) in text.match_indices("dogg") {
+                let mut single_suggestion = response["matches"][0].clone();
+                single_suggestion["context"] =
+                    serde_json::json!({ "text": flagged_text, "offset": 0, "length": 4 });
+                single_suggestion["offset"] = serde_json::json!(offset);
+                single_suggestion["replacements"] = serde_json::json!([{ "value": "dog" }]);
+                matches.push(single_suggestion);
+            }
+            response["matches"] = serde_json::json!(matches);
+            response
+        });
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("fix.md");
+        let html_path = temp_dir.path().join("fix.html");
+        let source = "---\ntitle: Fix\n---\n\n# Fix\n\n\
+The quick brown foox jumps over `dogg`, the lazy dogg.\n";
+        fs::write(&markdown_path, source).expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_grammar_check();
+        options.set_grammar_url(&url);
+        options.enable_autofix();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let fixed_source = read_to_string(&markdown_path).expect("Error reading fixed markdown");
+        assert_eq!(
+            fixed_source,
+            "---\ntitle: Fix\n---\n\n# Fix\n\n\
+The quick brown foox jumps over `dogg`, the lazy dog.\n"
+        );
+        let backup = read_to_string(temp_dir.path().join("fix.md.bak"))
+            .expect("Error reading source backup");
+        assert_eq!(backup, source);
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ INFO ] Fixed spelling on line 7: dogg -> dog.\n"));
+        assert_eq!(output.matches("Fixed spelling").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn update_html_renders_frontmatter_layout_from_templates_dir() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let templates_dir = temp_dir.path().join("templates");
+        fs::create_dir(&templates_dir).expect("Error creating templates directory");
+        fs::write(
+            templates_dir.join("landing.html"),
+            "<!DOCTYPE html>\n<html lang=\"{{ language }}\"><head><title>{{ title }}</title></head>\
+<body class=\"landing\">{{ main_section_html }}{{ unknown }}</body></html>\n",
+        )
+        .expect("Error writing landing template");
+        let landing_path = temp_dir.path().join("landing.md");
+        let landing_html_path = temp_dir.path().join("landing.html");
+        fs::write(
+            &landing_path,
+            "---\ntitle: Fish & Chips\nlayout: landing\n---\n\nWelcome.\n",
+        )
+        .expect("Error writing temp markdown");
+        let post_path = temp_dir.path().join("post.md");
+        let post_html_path = temp_dir.path().join("post.html");
+        fs::write(&post_path, "---\ntitle: Post\n---\n\nPost body.\n")
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.set_templates_dir(&templates_dir);
+
+        // act
+        update_html(
+            &landing_path,
+            &landing_html_path,
+            &options,
+            None,
+            &mut handle,
+        )
+        .await
+        .expect("Error calling update_html");
+        update_html(&post_path, &post_html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let landing_html = read_to_string(&landing_html_path).expect("Error reading landing HTML");
+        assert_eq!(
+            landing_html,
+            "<!DOCTYPE html>\n<html lang=\"en\"><head><title>Fish &amp; Chips</title></head>\
+<body class=\"landing\"><p>Welcome.</p>\n{{ unknown }}</body></html>\n"
+        );
+        let post_html = read_to_string(&post_html_path).expect("Error reading post HTML");
+        assert!(post_html.contains("<title>Post</title>"));
+        assert!(post_html.contains("theme-switch-wrapper"));
+    }
+
+    #[tokio::test]
+    async fn update_html_fills_embedded_source_layout_placeholder() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        fs::write(
+            temp_dir.path().join("source.html"),
+            "<html><body>{{ main_section_html }}{{ embedded_source }}</body></html>\n",
+        )
+        .expect("Error writing source template");
+        let markdown_path = temp_dir.path().join("post.md");
+        let html_path = temp_dir.path().join("post.html");
+        fs::write(
+            &markdown_path,
+            "---\nlayout: source\n---\n\n## Section\n\nSome <kbd>text</kbd>.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.set_templates_dir(temp_dir.path());
+        options.enable_embed_source();
+        options.enable_sidebar_toc();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML");
+        assert_eq!(
+            embedded_source(&html),
+            Some(String::from("## Section\n\nSome <kbd>text</kbd>."))
+        );
+        assert!(!html.contains("sidebar-toc"));
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("has no {{ sidebar_toc }} placeholder, so --sidebar-toc output"));
+        assert!(!output.contains("{{ embedded_source }} placeholder"));
+    }
+
+    #[tokio::test]
+    async fn update_html_falls_back_to_built_in_template_for_missing_layout() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("missing.md");
+        let html_path = temp_dir.path().join("missing.html");
+        fs::write(&markdown_path, "---\nlayout: missing\n---\n\nBody.\n")
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.set_templates_dir(temp_dir.path());
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML");
+        assert!(html.contains("theme-switch-wrapper"));
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ WARN ] Unable to read layout template"));
+    }
+
+    #[tokio::test]
+    async fn update_html_ignores_layout_outside_templates_dir() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let templates_dir = temp_dir.path().join("templates");
+        fs::create_dir(&templates_dir).expect("Error creating templates directory");
+        fs::write(
+            temp_dir.path().join("secret.html"),
+            "<html><body>Secret {{ main_section_html }}</body></html>\n",
+        )
+        .expect("Error writing outside template");
+        let markdown_path = temp_dir.path().join("escape.md");
+        let html_path = temp_dir.path().join("escape.html");
+        fs::write(&markdown_path, "---\nlayout: ../secret\n---\n\nBody.\n")
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.set_templates_dir(&templates_dir);
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = read_to_string(&html_path).expect("Error reading HTML");
+        assert!(!html.contains("Secret"));
+        assert!(html.contains("theme-switch-wrapper"));
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains(
+            "[ WARN ] Layout (../secret) is not a template name in the templates directory"
+        ));
+    }
+
+    #[tokio::test]
+    async fn update_html_lint_warns_about_skipped_heading_levels() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("headings.md");
+        let html_path = temp_dir.path().join("headings.html");
+        fs::write(
+            &markdown_path,
+            "---\ntitle: Headings\n---\n\n### Intro\n\n## Ingredients\n\n### Dry\n\n\
+## Method\n\n#### Kneading\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_lint();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ WARN ] Heading level skipped on line 13: h4 follows h2\n"));
+        assert_eq!(output.matches("Heading level skipped").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn update_html_reports_issues_spanning_grammar_check_chunks_once() {
+        // arrange
+        let url = start_repeated_sentence_grammar_server();
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("chunks.md");
+        let html_path = temp_dir.path().join("chunks.html");
+        let sentence_count = 120;
+        let markdown = (1..=sentence_count)
+            .map(|index| format!("Sentence {index} is written out in words here.\n\n"))
+            .collect::<Vec<String>>()
+            .concat();
+        fs::write(&markdown_path, &markdown).expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_grammar_check();
+        options.set_grammar_url(&url);
+        options.set_grammar_report_format(GrammarReportFormat::Json);
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        let report_start = output.find("[\n").expect("Expected JSON report");
+        let report_end = output.rfind("\n]").expect("Expected JSON report end") + 2;
+        let report: Vec<serde_json::Value> =
+            serde_json::from_str(&output[report_start..report_end]).expect("Expected JSON report");
+        let lines: Vec<u64> = report
+            .iter()
+            .map(|result| result["line"].as_u64().expect("Expected result line"))
+            .collect();
+        // Each sentence is a paragraph, so sentence n is on line 2n - 1
+        assert_eq!(
+            lines,
+            (2..=sentence_count)
+                .map(|index| 2 * index - 1)
+                .collect::<Vec<u64>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn update_html_reports_issues_after_non_ascii_text_in_chunk_overlaps_once() {
+        // arrange
+        let url = start_repeated_sentence_grammar_server();
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("chunks.md");
+        let html_path = temp_dir.path().join("chunks.html");
+        let sentence_count = 120;
+        // Characters outside ASCII take fewer UTF-16 code units than bytes, so offsets from chunks
+        // starting at different points only match once converted to bytes
+        let markdown = (1..=sentence_count)
+            .map(|index| format!("Sentence {index} is about a café 😀 déjà vu here.\n\n"))
+            .collect::<Vec<String>>()
+            .concat();
+        fs::write(&markdown_path, &markdown).expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_grammar_check();
+        options.set_grammar_url(&url);
+        options.set_grammar_report_format(GrammarReportFormat::Json);
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        let report_start = output.find("[\n").expect("Expected JSON report");
+        let report_end = output.rfind("\n]").expect("Expected JSON report end") + 2;
+        let report: Vec<serde_json::Value> =
+            serde_json::from_str(&output[report_start..report_end]).expect("Expected JSON report");
+        let lines: Vec<u64> = report
+            .iter()
+            .map(|result| result["line"].as_u64().expect("Expected result line"))
+            .collect();
+        assert_eq!(
+            lines,
+            (2..=sentence_count)
+                .map(|index| 2 * index - 1)
+                .collect::<Vec<u64>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_summary_reports_succeeded_and_failed_inputs() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp dir");
+        let first_path = temp_dir.path().join("first.md");
+        let second_path = temp_dir.path().join("second.md");
+        let missing_path = temp_dir.path().join("missing.md");
+        let unparsed_path = temp_dir.path().join("unparsed.md");
+        fs::write(&first_path, "# First\n\nOne two three.\n").expect("Error writing markdown");
+        fs::write(&second_path, "# Second\n\nFour five.\n").expect("Error writing markdown");
+        fs::write(
+            &unparsed_path,
+            "# Unparsed\n\n<!-- markwrite:grammar-ignore-start -->\n\nNever closed.\n",
+        )
+        .expect("Error writing markdown");
+        let options = MarkwriteOptions::default();
+        let mut handle: Vec<u8> = Vec::new();
+        let mut summary = BatchSummary::default();
+
+        // act
+        for markdown_path in [&first_path, &missing_path, &unparsed_path, &second_path] {
+            let html_path = markdown_path.with_extension("html");
+            let result = update_html(markdown_path, &html_path, &options, None, &mut handle).await;
+            summary.record(&result);
+        }
+        summary
+            .display(GrammarReportFormat::Human, &mut handle)
+            .expect("Error displaying summary");
+        let mut json_handle: Vec<u8> = Vec::new();
+        summary
+            .display(GrammarReportFormat::Json, &mut json_handle)
+            .expect("Error displaying summary");
+
+        // assert
+        assert_eq!(summary.succeeded(), 2);
+        assert_eq!(summary.skipped(), 0);
+        assert_eq!(summary.failed(), 2);
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains(
+            "[ INFO ] Summary: 2 succeeded (2 rendered, 0 checked), 0 skipped, 2 failed; 7 words, \
+0 grammar issues.\n"
+        ));
+        let json: serde_json::Value =
+            serde_json::from_slice(&json_handle).expect("Expected JSON summary");
+        assert_eq!(json["succeeded"], 2);
+        assert_eq!(json["failed"], 2);
+    }
+
+    #[tokio::test]
+    async fn update_html_resolves_links_against_frontmatter_site_url_unless_flag_given() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp dir");
+        let markdown_path = temp_dir.path().join("site.md");
+        let html_path = temp_dir.path().join("site.html");
+        fs::write(
+            &markdown_path,
+            "---\nsite_url: https://example.com/\ncanonical_url: /site/\n---\n\n\
+# Site\n\nRead the [archive](/archive/).\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+        let mut flag_options = MarkwriteOptions::default();
+        flag_options.set_canonical_root_url("https://example.org");
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+        let frontmatter_html = read_to_string(&html_path).expect("Error reading output");
+        update_html(&markdown_path, &html_path, &flag_options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+        let flag_html = read_to_string(&html_path).expect("Error reading output");
+
+        // assert
+        assert!(frontmatter_html.contains(r#"<a href="https://example.com/archive/">"#));
+        assert!(frontmatter_html
+            .contains(r#"<link rel="canonical" href="https://example.com/site/" >"#));
+        assert!(flag_html.contains(r#"<a href="https://example.org/archive/">"#));
+        assert!(flag_html.contains(r#"<link rel="canonical" href="https://example.org/site/" >"#));
+    }
+
+    #[tokio::test]
+    async fn update_html_dumps_grammar_plaintext_without_code() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp dir");
+        let markdown_path = temp_dir.path().join("code.md");
+        let html_path = temp_dir.path().join("code.html");
+        let plaintext_path = temp_dir.path().join("code.txt");
+        fs::write(
+            &markdown_path,
+            "# Code\n\nRun the command.\n\n```shell\nrm --recursive build\n```\n\nThen rebuild.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.set_dump_plaintext_path(&plaintext_path);
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let plaintext = read_to_string(&plaintext_path).expect("Error reading plaintext");
+        assert!(plaintext.contains("Run the command."));
+        assert!(plaintext.contains("Then rebuild."));
+        assert!(!plaintext.contains("rm --recursive build"));
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ INFO ] Wrote grammar check plaintext to"));
+        assert!(html_path.exists());
+    }
+
+    #[test]
+    fn modified_since_skips_inputs_older_than_cutoff() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp dir");
+        let old_path = temp_dir.path().join("old.md");
+        let new_path = temp_dir.path().join("new.md");
+        fs::write(&old_path, "# Old\n").expect("Error writing old markdown");
+        fs::write(&new_path, "# New\n").expect("Error writing new markdown");
+        let now = SystemTime::now();
+        File::options()
+            .write(true)
+            .open(&old_path)
+            .and_then(|file| file.set_modified(now - Duration::from_secs(7 * 24 * 60 * 60)))
+            .expect("Error setting modification time");
+        let cutoff = parse_since("1d", now).expect("Expected valid duration");
+        let targets = vec![
+            (old_path, temp_dir.path().join("old.html")),
+            (new_path.clone(), temp_dir.path().join("new.html")),
+        ];
+
+        // act
+        let (result, skipped_count) = modified_since(targets, cutoff);
+
+        // assert
+        assert_eq!(skipped_count, 1);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].0, new_path);
+    }
+
+    #[tokio::test]
+    async fn grammar_report_text_offsets_point_at_flagged_text_in_source() {
+        // arrange
+        let url = start_foox_grammar_server();
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("offsets.md");
+        let html_path = temp_dir.path().join("offsets.html");
+        // Long enough to need several requests, so offsets from later chunks are shifted
+        let source = String::from("---\ntitle: Décalages\n---\n\n")
+            + &(1..=80)
+                .map(|index| {
+                    format!(
+                        "Paragraphe {index} — café 😀 a un *foox* dans [ça](https://example.com).\n\n"
+                    )
+                })
+                .collect::<Vec<String>>()
+                .concat();
+        fs::write(&markdown_path, &source).expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_grammar_check();
+        options.set_grammar_url(&url);
+        options.set_grammar_report_format(GrammarReportFormat::Json);
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        let report_start = output.find("[\n").expect("Expected JSON report");
+        let report_end = output.rfind("\n]").expect("Expected JSON report end") + 2;
+        let report: Vec<serde_json::Value> =
+            serde_json::from_str(&output[report_start..report_end]).expect("Expected JSON report");
+        assert_eq!(report.len(), 80);
+        for result in &report {
+            let offset = result["textOffset"].as_u64().expect("Expected text offset");
+            let length = result["textLength"].as_u64().expect("Expected text length");
+            let start = usize::try_from(offset).expect("Expected offset to fit");
+            let end = start + usize::try_from(length).expect("Expected length to fit");
+            assert_eq!(&source[start..end], "foox");
+        }
+        let last_offset = report[79]["textOffset"]
+            .as_u64()
+            .expect("Expected text offset");
+        assert!(last_offset > 1500);
+    }
+
+    #[tokio::test]
+    async fn update_html_stops_grammar_requests_at_budget() {
+        // arrange
+        let request_count = Arc::new(AtomicUsize::new(0));
+        let server_request_count = Arc::clone(&request_count);
+        let url = start_grammar_server_with(move |_| {
+            server_request_count.fetch_add(1, Ordering::SeqCst);
+            let mut response = languagetool_response_fixture();
+            response["matches"] = serde_json::json!([]);
+            response
+        });
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("budget.md");
+        let html_path = temp_dir.path().join("budget.html");
+        let markdown = (1..=120)
+            .map(|index| format!("Sentence {index} is written out in words here.\n\n"))
+            .collect::<Vec<String>>()
+            .concat();
+        fs::write(&markdown_path, &markdown).expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_grammar_check();
+        options.set_grammar_url(&url);
+        options.set_max_grammar_requests(2);
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        assert_eq!(request_count.load(Ordering::SeqCst), 2);
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ WARN ] Grammar request budget of 2 reached"));
+        assert!(html_path.exists());
+    }
+
+    #[tokio::test]
+    async fn update_html_writes_gzip_output_matching_html() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("compressed.md");
+        let html_path = temp_dir.path().join("compressed.html");
+        let gzip_path = temp_dir.path().join("compressed.html.gz");
+        fs::write(&markdown_path, "# Compressed\n\nServed pre-compressed.\n")
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_gzip();
+        options.set_gzip_level(9);
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = fs::read(&html_path).expect("Expected HTML output");
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(File::open(&gzip_path).expect("Expected gzip output"))
+            .read_to_end(&mut decompressed)
+            .expect("Expected valid gzip output");
+        assert_eq!(decompressed, html);
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("compressed.html.gz"));
+    }
+
+    #[tokio::test]
+    async fn update_html_writes_only_gzip_output_when_gzip_only() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("compressed.md");
+        let html_path = temp_dir.path().join("compressed.html");
+        fs::write(&markdown_path, "# Compressed\n").expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_gzip_only();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        assert!(!html_path.exists());
+        assert!(temp_dir.path().join("compressed.html.gz").exists());
+    }
+
+    #[tokio::test]
+    async fn grammar_check_stream_yields_same_results_as_batch_check() {
+        // arrange
+        let url = start_grammar_server_with(|_| languagetool_response_fixture());
+        let markdown = (1..=120)
+            .map(|index| format!("Sentence {index} is written out in words here.\n\n"))
+            .collect::<Vec<String>>()
+            .concat();
+        let mut options = MarkwriteOptions::default();
+        options.set_grammar_url(&url);
+        let grammar_checker = options.grammar_checker();
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        let batch_results = grammar_check(
+            &markdown,
+            &markdown,
+            "streamed.md",
+            &grammar_checker,
+            &options,
+            &mut handle,
+        )
+        .await;
+        let mut stream = Box::pin(
+            grammar_check_stream(&markdown, &grammar_checker, &options)
+                .await
+                .expect("Expected supported languages"),
+        );
+        let mut streamed_results = Vec::new();
+        while let Some(result) = stream.next().await {
+            streamed_results.push(result);
+        }
+
+        // assert
+        let issues = |results: &[GrammarCheckResult]| {
+            let mut result: Vec<(usize, String)> = results
+                .iter()
+                .map(|value| (value.text_offset(), value.rule_id().to_string()))
+                .collect();
+            result.sort();
+            result
+        };
+        assert!(batch_results.len() > 1);
+        assert_eq!(issues(&streamed_results), issues(&batch_results));
+    }
+
+    #[tokio::test]
+    async fn update_html_reports_invalid_canonical_url() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("canonical.md");
+        let html_path = temp_dir.path().join("canonical.html");
+        fs::write(
+            &markdown_path,
+            "---\ncanonical_url: https://exa mple.com/post/\n---\n\n# Canonical\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let options = MarkwriteOptions::default();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains("[ WARN ] Invalid canonical URL (https://exa mple.com/post/)"));
+        let html = fs::read_to_string(&html_path).expect("Expected HTML output");
+        assert!(!html.contains(r#"rel="canonical""#));
+    }
+
+    #[tokio::test]
+    async fn update_html_adds_header_and_parsed_markdown_footer_around_content() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("index.md");
+        let html_path = temp_dir.path().join("index.html");
+        let header_path = temp_dir.path().join("header.html");
+        let footer_path = temp_dir.path().join("footer.md");
+        fs::write(&markdown_path, "# Title\n\nBody text.\n").expect("Error writing temp markdown");
+        fs::write(&header_path, "<nav>Home</nav>").expect("Error writing temp header");
+        fs::write(&footer_path, "Copyright *Example*\n").expect("Error writing temp footer");
+        let mut options = MarkwriteOptions::default();
+        options.set_header_path(&header_path);
+        options.set_footer_path(&footer_path);
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let html = fs::read_to_string(&html_path).expect("Error reading output HTML");
+        let main_html = &html[html.find("<main>").unwrap()..html.find("</main>").unwrap()];
+        assert!(main_html
+            .trim_start_matches("<main>")
+            .trim_start()
+            .starts_with("<nav>Home</nav>"));
+        assert!(main_html
+            .trim_end()
+            .ends_with("<p>Copyright <em>Example</em></p>"));
+        assert!(main_html.find("<nav>").unwrap() < main_html.find("Body text.").unwrap());
+    }
+
+    #[tokio::test]
+    async fn update_html_serves_unchanged_input_from_render_cache() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("cached.md");
+        let html_path = temp_dir.path().join("cached.html");
+        fs::write(&markdown_path, "# Cached\n\nRendered once.\n")
+            .expect("Error writing temp markdown");
+        let mut options = MarkwriteOptions::default();
+        options.set_cache_dir(temp_dir.path().join("cache"));
+        let mut handle: Vec<u8> = Vec::new();
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+        let first_html = fs::read_to_string(&html_path).expect("Error reading output HTML");
+        fs::remove_file(&html_path).expect("Error removing output HTML");
+        let renders_before = RENDER_COUNT.with(Cell::get);
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+        let cached_renders = RENDER_COUNT.with(Cell::get) - renders_before;
+        let cached_html = fs::read_to_string(&html_path).expect("Error reading output HTML");
+        options.enable_byline();
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+        let changed_options_renders =
+            RENDER_COUNT.with(Cell::get) - renders_before - cached_renders;
+
+        // assert
+        assert_eq!(cached_renders, 0);
+        assert_eq!(changed_options_renders, 1);
+        assert_eq!(cached_html, first_html);
+    }
+
+    #[tokio::test]
+    async fn update_html_rewrites_link_to_markdown_file_created_after_cached_render() {
+        // arrange
+        // Links are only rewritten to targets within the project root, the working directory
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let project_dir = Path::new("target").join(format!(
+            "render-cache-links-{}",
+            temp_dir
+                .path()
+                .file_name()
+                .expect("Expected temp directory name")
+                .to_string_lossy()
+        ));
+        fs::create_dir_all(&project_dir).expect("Error creating project directory");
+        let markdown_path = project_dir.join("index.md");
+        let html_path = project_dir.join("index.html");
+        let target_path = project_dir.join("next.md");
+        fs::write(
+            &markdown_path,
+            "# Index\n\nRead the [next page](next.md).\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut options = MarkwriteOptions::default();
+        options.set_cache_dir(temp_dir.path().join("cache"));
+        let mut handle: Vec<u8> = Vec::new();
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+        let first_html = fs::read_to_string(&html_path).expect("Error reading output HTML");
+
+        // act
+        fs::write(&target_path, "# Next\n").expect("Error writing link target");
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+        let second_html = fs::read_to_string(&html_path).expect("Error reading output HTML");
+        fs::remove_dir_all(&project_dir).expect("Error removing project directory");
+
+        // assert
+        assert!(first_html.contains(r#"<a href="next.md">"#));
+        assert!(second_html.contains(r#"<a href="next.html">"#));
+    }
+
+    #[tokio::test]
+    async fn update_html_keys_render_cache_on_input_directory() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let first_dir = temp_dir.path().join("first");
+        let second_dir = temp_dir.path().join("second");
+        let mut options = MarkwriteOptions::default();
+        options.set_cache_dir(temp_dir.path().join("cache"));
+        let mut handle: Vec<u8> = Vec::new();
+        for directory in [&first_dir, &second_dir] {
+            fs::create_dir(directory).expect("Error creating input directory");
+            fs::write(directory.join("page.md"), "# Page\n\nSame text.\n")
+                .expect("Error writing temp markdown");
+        }
+        update_html(
+            &first_dir.join("page.md"),
+            &first_dir.join("page.html"),
+            &options,
+            None,
+            &mut handle,
+        )
+        .await
+        .expect("Error calling update_html");
+        let renders_before = RENDER_COUNT.with(Cell::get);
+
+        // act
+        update_html(
+            &second_dir.join("page.md"),
+            &second_dir.join("page.html"),
+            &options,
+            None,
+            &mut handle,
+        )
+        .await
+        .expect("Error calling update_html");
+
+        // assert
+        assert_eq!(RENDER_COUNT.with(Cell::get) - renders_before, 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn update_html_sets_requested_mode_on_output_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("shared.md");
+        let html_path = temp_dir.path().join("shared.html");
+        fs::write(&markdown_path, "# Shared\n\nReadable by the web server.\n")
+            .expect("Error writing temp markdown");
+        let mut options = MarkwriteOptions::default();
+        options.set_output_mode(0o640);
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let mode = fs::metadata(&html_path)
+            .expect("Expected HTML output")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, 0o640);
+    }
+
+    /// Contents of the file at `path`, once a command running in the background has written it
+    async fn read_when_written(path: &Path) -> String {
+        for _ in 0..100 {
+            if let Ok(value) = fs::read_to_string(path) {
+                return value;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("Expected command to write {}", path.display());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn update_html_runs_open_in_command_with_output_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("input.md");
+        let html_path = temp_dir.path().join("output.html");
+        let script_path = temp_dir.path().join("record-argument.sh");
+        let record_path = temp_dir.path().join("argument.txt");
+        fs::write(&markdown_path, "# Title\n\nBody.\n").expect("Error writing temp markdown");
+        fs::write(
+            &script_path,
+            format!(
+                "#!/bin/sh\necho \"$1\" > '{0}.part'\nmv '{0}.part' '{0}'\n",
+                record_path.display()
+            ),
+        )
+        .expect("Error writing temp script");
+        fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755))
+            .expect("Error making temp script executable");
+        let mut options = MarkwriteOptions::default();
+        options.set_open_in(&script_path.display().to_string());
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        let argument = read_when_written(&record_path).await;
+        assert_eq!(argument.trim_end(), html_path.display().to_string());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn update_html_returns_while_open_in_command_is_still_running() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("input.md");
+        let html_path = temp_dir.path().join("output.html");
+        let script_path = temp_dir.path().join("stay-open.sh");
+        fs::write(&markdown_path, "# Title\n\nBody.\n").expect("Error writing temp markdown");
+        fs::write(&script_path, "#!/bin/sh\nsleep 5\n").expect("Error writing temp script");
+        fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755))
+            .expect("Error making temp script executable");
+        let mut options = MarkwriteOptions::default();
+        options.set_open_in(&script_path.display().to_string());
+        let mut handle: Vec<u8> = Vec::new();
+        let start = Instant::now();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+
+        // assert
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(html_path.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn update_html_keeps_quoted_open_in_arguments_whole() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("input.md");
+        let html_path = temp_dir.path().join("output.html");
+        let script_path = temp_dir.path().join("record-arguments.sh");
+        let record_path = temp_dir.path().join("arguments.txt");
+        fs::write(&markdown_path, "# Title\n\nBody.\n").expect("Error writing temp markdown");
+        fs::write(
+            &script_path,
+            format!(
+                "#!/bin/sh\nprintf '%s\\n' \"$@\" > '{0}.part'\nmv '{0}.part' '{0}'\n",
+                record_path.display()
+            ),
+        )
+        .expect("Error writing temp script");
+        fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755))
+            .expect("Error making temp script executable");
+        let mut options = MarkwriteOptions::default();
+        options.set_open_in(&format!("'{}' --title \"My Notes\"", script_path.display()));
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
 
-        let Some((_first_line, rest)) = input.split_once('\n') else {
-            return (None, input);
-        };
-        return match rest.split_once("\n---") {
-            Some((frontmatter, body)) => (Some(frontmatter.trim()), body.trim()),
-            None => (None, input),
-        };
+        // assert
+        let arguments = read_when_written(&record_path).await;
+        assert_eq!(
+            arguments.lines().collect::<Vec<&str>>(),
+            vec![
+                "--title",
+                "My Notes",
+                html_path.to_str().expect("Expected UTF-8 path")
+            ]
+        );
     }
-    (None, input)
-}
 
-#[derive(Default)]
-pub struct MarkwriteOptions {
-    check_grammar: bool,
-}
+    #[tokio::test]
+    async fn update_html_warns_about_document_with_only_frontmatter() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let empty_path = temp_dir.path().join("empty.md");
+        let empty_html_path = temp_dir.path().join("empty.html");
+        let full_path = temp_dir.path().join("full.md");
+        let full_html_path = temp_dir.path().join("full.html");
+        fs::write(&empty_path, "---\ntitle: Coming Soon\n---\n\n  \n")
+            .expect("Error writing temp markdown");
+        fs::write(&full_path, "---\ntitle: Here Now\n---\n\nSome content.\n")
+            .expect("Error writing temp markdown");
+        let options = MarkwriteOptions::default();
+        let mut empty_handle: Vec<u8> = Vec::new();
+        let mut full_handle: Vec<u8> = Vec::new();
 
-impl MarkwriteOptions {
-    #[must_use]
-    pub fn check_grammar(&self) -> bool {
-        self.check_grammar
-    }
+        // act
+        update_html(
+            &empty_path,
+            &empty_html_path,
+            &options,
+            None,
+            &mut empty_handle,
+        )
+        .await
+        .expect("Error calling update_html");
+        update_html(
+            &full_path,
+            &full_html_path,
+            &options,
+            None,
+            &mut full_handle,
+        )
+        .await
+        .expect("Error calling update_html");
 
-    pub fn enable_grammar_check(&mut self) {
-        self.check_grammar = true;
+        // assert
+        let empty_output = String::from_utf8(empty_handle).expect("Expected UTF-8 output");
+        let full_output = String::from_utf8(full_handle).expect("Expected UTF-8 output");
+        assert!(empty_output.contains("[ WARN ] Document has no content after the frontmatter"));
+        assert!(empty_html_path.exists());
+        assert!(!full_output.contains("no content"));
     }
-}
 
-///
-/// # Errors
-/// Errors if unable to read input file
-/// # Panics
-/// Panics if output path cannot be created
-pub async fn update_html<P1: AsRef<Path>, P2: AsRef<Path>>(
-    path: &P1,
-    output_path: &P2,
-    markwrite_options: &MarkwriteOptions,
-    stdout_handle: &mut impl Write,
-) -> Result<(), notify::Error> {
-    let options = ParseInputOptions {
-        canonical_root_url: None,
-        enable_smart_punctuation: Some(true),
-        search_term: None,
-    };
-    let markdown = match read_to_string(path) {
-        Ok(value) => value,
-        Err(error) => return Err(error.into()),
-    };
-
-    let (frontmatter_yaml, markdown) = strip_frontmatter(&markdown);
-    let frontmatter = match frontmatter_yaml {
-        Some(value) => match YamlLoader::load_from_str(value) {
-            Ok(frontmatter_value) => {
-                let doc = &frontmatter_value[0];
-                let title = doc["title"].as_str().map(std::string::ToString::to_string);
-                let description = doc["description"]
-                    .as_str()
-                    .map(std::string::ToString::to_string);
-                let canonical_url = doc["canonical_url"]
-                    .as_str()
-                    .map(std::string::ToString::to_string);
-                Frontmatter {
-                    title,
-                    description,
-                    canonical_url,
-                }
-            }
-            Err(_) => Frontmatter {
-                title: None,
-                description: None,
-                canonical_url: None,
-            },
-        },
-        None => Frontmatter {
-            title: None,
-            description: None,
-            canonical_url: None,
-        },
-    };
-    let ParseResults {
-        html, statistics, ..
-    } = markdown_to_processed_html(markdown, &frontmatter, &options);
-    let word_count = if let Some(value) = statistics {
-        value.word_count()
-    } else {
-        0
-    };
+    #[tokio::test]
+    async fn update_html_uses_frontmatter_values_set_in_options_over_document_ones() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let titled_path = temp_dir.path().join("titled.md");
+        let titled_html_path = temp_dir.path().join("titled.html");
+        let untitled_path = temp_dir.path().join("untitled.md");
+        let untitled_html_path = temp_dir.path().join("untitled.html");
+        fs::write(
+            &titled_path,
+            "---\ntitle: Document Title\n---\n\nContent.\n",
+        )
+        .expect("Error writing temp markdown");
+        fs::write(&untitled_path, "Content.\n").expect("Error writing temp markdown");
+        let mut options = MarkwriteOptions::default();
+        options
+            .set_frontmatter_value("title", "Foo")
+            .expect("Expected title to be a frontmatter key");
+        let mut handle: Vec<u8> = Vec::new();
 
-    let display_path = path.as_ref().display().to_string();
-    if markwrite_options.check_grammar() {
-        grammar_check(markdown, &display_path, stdout_handle).await;
-    }
+        // act
+        let unknown_key_result = options.set_frontmatter_value("colour", "blue");
+        update_html(&titled_path, &titled_html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
+        update_html(
+            &untitled_path,
+            &untitled_html_path,
+            &options,
+            None,
+            &mut handle,
+        )
+        .await
+        .expect("Error calling update_html");
 
-    let output_display_path = output_path.as_ref().display().to_string();
-    match html {
-        Some(value) => {
-            let Ok(mut outfile) = File::create(output_path) else {
-                panic!("[ ERROR ] Unable to create the output file!");
-            };
-            outfile
-                .write_all(value.as_bytes())
-                .with_context(|| {
-                    format!("[ ERROR ] Unable to write to output file: {output_display_path}")
-                })
-                .unwrap();
-            info!("Wrote {output_display_path}.");
-            writeln!(
-                stdout_handle,
-                "[ INFO ] Wrote {output_display_path} ({word_count} words)."
-            )?;
+        // assert
+        assert!(unknown_key_result.is_err());
+        for html_path in [&titled_html_path, &untitled_html_path] {
+            let html = fs::read_to_string(html_path).expect("Error reading output HTML");
+            assert!(html.contains("<title>Foo</title>"));
+            assert!(!html.contains("Document Title"));
         }
-        None => eprintln!("[ ERROR ] Unable to parse markdownto HTML"),
-    };
-    stdout_handle.flush()?;
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::{
-        add_word_to_dictionary, load_dictionary, strip_frontmatter, strip_trailing_sentence_stub,
-        update_html, MarkwriteOptions,
-    };
-    use fake::{faker, Fake};
-    use html5ever::{
-        driver::ParseOpts,
-        local_name, namespace_url, ns, parse_document,
-        tendril::{fmt::UTF8, Tendril, TendrilSink},
-        QualName,
-    };
-    use markup5ever_rcdom::{NodeData, RcDom};
-    use std::{
-        collections::HashSet,
-        fs::{self, read_to_string, remove_file, File},
-        io::{self, BufWriter},
-        path::Path,
-    };
+    }
 
-    #[test]
-    fn strip_frontmatter_removes_frontmatter() {
+    #[tokio::test]
+    async fn update_html_adds_author_meta_tag_for_each_listed_author() {
         // arrange
-        let markdown = "---
-title: Test Document
----
-
-# Test
-
-This is a test.";
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("input.md");
+        let html_path = temp_dir.path().join("output.html");
+        fs::write(
+            &markdown_path,
+            "---\nauthors:\n  - Ada Lovelace\n  - Charles Babbage\n---\n\nBody text here.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut options = MarkwriteOptions::default();
+        options.enable_byline();
+        let mut handle: Vec<u8> = Vec::new();
 
         // act
-        let (_, result) = strip_frontmatter(markdown);
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
 
         // assert
-        let expected_result = "# Test
-
-This is a test.";
-        assert_eq!(result, expected_result);
+        let html = fs::read_to_string(&html_path).expect("Error reading output HTML");
+        assert_eq!(html.matches(r#"<meta name="author""#).count(), 2);
+        assert!(html.contains(r#"<meta name="author" content="Ada Lovelace" >"#));
+        assert!(html.contains(r#"<meta name="author" content="Charles Babbage" >"#));
+        assert!(html.contains(
+            r#"<div class="markwrite-byline">By Ada Lovelace and Charles Babbage · 3 words"#
+        ));
     }
-    #[test]
-    fn add_word_to_dictionary_inserts_new_word() {
+
+    #[tokio::test]
+    async fn update_html_treats_single_author_as_one_element_list() {
         // arrange
-        let mut dictionary: HashSet<String> = HashSet::new();
-        let stdout = io::stdout();
-        let handle = BufWriter::new(stdout);
-        let dictionary_path = "fixtures/custom.dict";
-        let dictionary_file = assert_fs::NamedTempFile::new("custom.dict")
-            .expect("Error getting temp dictionary path");
-        let temp_dictionary_path = dictionary_file.path();
-        fs::copy(dictionary_path, temp_dictionary_path).expect("Error copying temp fixture");
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("input.md");
+        let html_path = temp_dir.path().join("output.html");
+        fs::write(
+            &markdown_path,
+            "---\nauthor: Ada Lovelace\n---\n\nBody text here.\n",
+        )
+        .expect("Error writing temp markdown");
+        let options = MarkwriteOptions::default();
+        let mut handle: Vec<u8> = Vec::new();
 
         // act
-        let new_word = "stairs";
-        add_word_to_dictionary(new_word, temp_dictionary_path, &mut dictionary, handle);
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
 
         // assert
-        assert!(dictionary.contains(new_word));
+        let html = fs::read_to_string(&html_path).expect("Error reading output HTML");
+        assert_eq!(html.matches(r#"<meta name="author""#).count(), 1);
+        assert!(html.contains(r#"<meta name="author" content="Ada Lovelace" >"#));
     }
 
-    #[test]
-    fn add_word_to_dictionary_updates_dictionary_file() {
+    /// DOM hook used in tests, adding a `lead` class to every paragraph
+    fn add_paragraph_class(node: &DomHandle) {
+        if let DomNodeData::Element { name, attrs, .. } = &node.data {
+            if name.local == local_name!("p") {
+                attrs.borrow_mut().push(Attribute {
+                    name: QualName::new(None, ns!(), local_name!("class")),
+                    value: "lead".into(),
+                });
+            }
+        }
+        for child in node.children.borrow().iter() {
+            add_paragraph_class(child);
+        }
+    }
+
+    #[tokio::test]
+    async fn update_html_runs_dom_hooks_before_serializing() {
         // arrange
-        let mut dictionary: HashSet<String> = HashSet::new();
-        let stdout = io::stdout();
-        let handle = BufWriter::new(stdout);
-        let dictionary_path = "fixtures/custom.dict";
-        let dictionary_file = assert_fs::NamedTempFile::new("custom.dict")
-            .expect("Error getting temp dictionary path");
-        let temp_dictionary_path = dictionary_file.path();
-        fs::copy(dictionary_path, temp_dictionary_path).expect("Error copying temp fixture");
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("input.md");
+        let html_path = temp_dir.path().join("output.html");
+        fs::write(&markdown_path, "First paragraph.\n\n> Quoted paragraph.\n")
+            .expect("Error writing temp markdown");
+        let mut options = MarkwriteOptions::default();
+        options.enable_fragment();
+        options.add_dom_hook(|dom| add_paragraph_class(&dom.document));
+        let mut handle: Vec<u8> = Vec::new();
 
         // act
-        let new_word = "stairs";
-        add_word_to_dictionary(new_word, temp_dictionary_path, &mut dictionary, handle);
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
 
         // assert
-        let dictionary_file_contents =
-            read_to_string(temp_dictionary_path).expect("Failed to read file to string");
-        assert!(dictionary_file_contents.contains(new_word));
+        let html = fs::read_to_string(&html_path).expect("Error reading output HTML");
+        assert!(html.contains(r#"<p class="lead">First paragraph.</p>"#));
+        assert!(html.contains(r#"<p class="lead">Quoted paragraph.</p>"#));
     }
 
-    #[test]
-    fn load_dictionary_returns_input_dictionary_when_dictionary_file_is_absent() {
-        //arrange
-        let mut dictionary: HashSet<String> = HashSet::new();
-        let stdout = io::stdout();
-        let handle = BufWriter::new(stdout);
+    #[tokio::test]
+    async fn update_html_reports_unterminated_frontmatter_instead_of_rendering_it() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("input.md");
+        let html_path = temp_dir.path().join("output.html");
+        let markdown =
+            "---\ntitle: Post Title\ndescription: Forgot the fence\n\n# Heading\n\nBody.\n";
+        fs::write(&markdown_path, markdown).expect("Error writing temp markdown");
+        let options = MarkwriteOptions::default();
+        let mut handle: Vec<u8> = Vec::new();
 
         // act
-        load_dictionary("nonsense.dict", &mut dictionary, handle);
+        let result = update_html(&markdown_path, &html_path, &options, None, &mut handle).await;
+        let render_result =
+            Markwrite::new(MarkwriteOptions::default(), HashSet::new()).render(markdown);
+        let thematic_break_result = Markwrite::new(MarkwriteOptions::default(), HashSet::new())
+            .render("---\n\nText after a thematic break: kept.\n");
 
-        //assert
-        assert_eq!(dictionary.len(), 0);
+        // assert
+        let error = result.expect_err("Expected unterminated frontmatter error");
+        assert!(error.to_string().contains("Unterminated frontmatter"));
+        assert!(!html_path.exists());
+        assert_eq!(render_result.html(), None);
+        assert_eq!(
+            render_result.errors(),
+            Some(
+                &[String::from(
+                    "Unterminated frontmatter, add a `---` line to close the frontmatter fields"
+                )][..]
+            )
+        );
+        assert!(thematic_break_result
+            .html()
+            .is_some_and(|html| html.contains("<hr>")));
     }
 
-    #[test]
-    fn load_dictionary_adds_words_from_file_to_dictionary() {
-        //arrange
-        let mut dictionary: HashSet<String> = HashSet::new();
-        let stdout = io::stdout();
-        let handle = io::BufWriter::new(stdout);
+    #[tokio::test]
+    async fn update_html_shows_wider_grammar_context_when_configured() {
+        // arrange
+        let url = start_grammar_server();
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("context.md");
+        let html_path = temp_dir.path().join("context.html");
+        fs::write(
+            &markdown_path,
+            "The quick brown foox jumps over the lazy dog, then naps by the fire all afternoon.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut default_handle: Vec<u8> = Vec::new();
+        let mut wide_handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_grammar_check();
+        options.enable_check_only();
+        options.set_grammar_url(&url);
+        let mut wide_options = MarkwriteOptions::default();
+        wide_options.enable_grammar_check();
+        wide_options.enable_check_only();
+        wide_options.set_grammar_url(&url);
+        wide_options.set_grammar_context_width(60);
 
         // act
-        load_dictionary("fixtures/custom.dict", &mut dictionary, handle);
+        update_html(
+            &markdown_path,
+            &html_path,
+            &options,
+            None,
+            &mut default_handle,
+        )
+        .await
+        .expect("Error calling update_html");
+        update_html(
+            &markdown_path,
+            &html_path,
+            &wide_options,
+            None,
+            &mut wide_handle,
+        )
+        .await
+        .expect("Error calling update_html");
 
-        //assert
-        assert_eq!(dictionary.len(), 3);
-        assert!(dictionary.contains("Cheese"));
-        assert!(dictionary.contains("apples"));
+        // assert
+        let default_output = String::from_utf8(default_handle).expect("Expected UTF-8 output");
+        let wide_output = String::from_utf8(wide_handle).expect("Expected UTF-8 output");
+        assert!(!default_output.contains("naps by the fire"));
+        assert!(wide_output.contains("jumps over the lazy dog, then naps by the fire"));
     }
 
-    #[test]
-    fn strip_frontmatter_returns_expected_result_when_frontmatter_is_absent() {
+    #[tokio::test]
+    async fn write_index_links_rendered_documents_newest_first_when_writing_only_gzip_output() {
         // arrange
-        let markdown = "# Test
-
-This is a test.";
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let older_path = temp_dir.path().join("older.md");
+        let newer_path = temp_dir.path().join("newer.md");
+        fs::write(
+            &older_path,
+            "---\ntitle: Older Post\ndate: 2024-01-15\n---\n\n# Older Post\n\nFirst words.\n",
+        )
+        .expect("Error writing temp markdown");
+        fs::write(
+            &newer_path,
+            "---\ntitle: Newer Post\ndate: 2024-06-01\ndescription: The latest news\n---\n\n# Newer Post\n\nMore words.\n",
+        )
+        .expect("Error writing temp markdown");
+        let targets = output_paths(&[temp_dir.path().to_path_buf()], None, None)
+            .expect("Error finding markdown files");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_gzip_only();
+        let mut outcomes = Vec::with_capacity(targets.len());
+        for (path, output_path) in &targets {
+            let outcome = update_html(path, output_path, &options, None, &mut handle)
+                .await
+                .expect("Error calling update_html");
+            outcomes.push((output_path.as_path(), outcome));
+        }
+        let documents: Vec<_> = outcomes
+            .iter()
+            .filter_map(|(output_path, outcome)| Some((*output_path, outcome.document()?)))
+            .collect();
 
         // act
-        let (_, result) = strip_frontmatter(markdown);
+        let index_path =
+            write_index(&documents, &temp_dir.path(), &options).expect("Error writing index");
 
         // assert
-        assert_eq!(result, markdown);
+        let html = read_to_string(index_path).expect("Error reading index");
+        let newer_position = html
+            .find(r#"<a href="newer.html">Newer Post</a> <time datetime="2024-06-01">"#)
+            .expect("Expected link to newer post");
+        let older_position = html
+            .find(r#"<a href="older.html">Older Post</a> <time datetime="2024-01-15">"#)
+            .expect("Expected link to older post");
+        assert!(newer_position < older_position);
+        assert!(html.contains("<p>The latest news</p>"));
+        assert!(html.contains("First words.</p>"));
     }
 
     #[test]
-    fn strip_trailing_sentencte_stub_truncates_long_text_chunk() {
+    fn validate_layout_templates_rejects_unknown_placeholder() {
         // arrange
-        let paragraphs: Vec<String> = faker::lorem::en::Paragraphs(3..5).fake();
-        let text = paragraphs.join("\n\n");
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        fs::write(
+            temp_dir.path().join("landing.html"),
+            "<html><title>{{ title }}</title><body>{{ main_section_html }}</body></html>\n",
+        )
+        .expect("Error writing landing template");
+        fs::write(
+            temp_dir.path().join("post.html"),
+            "<html><title>{{ title }}</title><body>{{ subtitle }}{{ main_section_html }}</body></html>\n",
+        )
+        .expect("Error writing post template");
 
         // act
-        let (text_chunk, length) = strip_trailing_sentence_stub(&text);
+        let result = validate_layout_templates(&temp_dir.path());
 
-        // asert
-        assert!(length <= 1500);
-        assert!(text_chunk.len() == length);
-        let last = &text_chunk[length - 1..];
-        dbg!("LAST: {last}");
-        assert!(last == "." || last == "!" || last == "\n" || last == "?");
+        // assert
+        let message = result.expect_err("Expected unknown placeholder to be rejected");
+        assert!(message.starts_with("Unknown placeholder {{ subtitle }} in layout template ("));
+        assert!(message.contains("post.html"));
+        fs::remove_file(temp_dir.path().join("post.html")).expect("Error removing post template");
+        assert_eq!(validate_layout_templates(&temp_dir.path()), Ok(()));
     }
 
-    #[quickcheck_macros::quickcheck]
-    fn strip_trailing_sentencte_stub_truncates_long_text_as_expected() -> bool {
+    #[tokio::test]
+    async fn write_sitemap_lists_canonical_urls_of_rendered_documents() {
         // arrange
-        let paragraphs: Vec<String> = faker::lorem::en::Paragraphs(3..5).fake();
-        let text = paragraphs.join("\n\n");
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let input_dir = temp_dir.path().join("posts");
+        let output_dir = temp_dir.path().join("public");
+        fs::create_dir(&input_dir).expect("Error creating input directory");
+        fs::write(input_dir.join("first.md"), "# First\n\nFirst post.\n")
+            .expect("Error writing temp markdown");
+        fs::write(
+            input_dir.join("second.md"),
+            "---\ncanonical_url: /blog/second/\n---\n\n# Second\n\nSecond post.\n",
+        )
+        .expect("Error writing temp markdown");
+        fs::write(
+            input_dir.join("draft.md"),
+            "---\ndraft: true\n---\n\n# Draft\n\nNot yet.\n",
+        )
+        .expect("Error writing temp markdown");
+        let targets = output_paths(&[input_dir], None, Some(&output_dir))
+            .expect("Error finding markdown files");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.set_canonical_root_url("https://example.com/");
+        let mut outcomes = Vec::with_capacity(targets.len());
+        for (path, output_path) in &targets {
+            let outcome = update_html(path, output_path, &options, None, &mut handle)
+                .await
+                .expect("Error calling update_html");
+            outcomes.push((output_path.as_path(), outcome));
+        }
+        let documents: Vec<_> = outcomes
+            .iter()
+            .filter_map(|(output_path, outcome)| Some((*output_path, outcome.document()?)))
+            .collect();
 
         // act
-        let (text_chunk, length) = strip_trailing_sentence_stub(&text);
+        let sitemap_path =
+            write_sitemap(&documents, &output_dir, &options).expect("Error writing sitemap");
 
-        // asert
-        let last = &text_chunk[length - 1..];
-        length <= 1500 && (last == "." || last == "!" || last == "\n" || last == "?")
+        // assert
+        let sitemap = read_to_string(sitemap_path).expect("Error reading sitemap");
+        assert!(sitemap.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset "));
+        assert!(sitemap.contains("<url><loc>https://example.com/first.html</loc><lastmod>"));
+        assert!(sitemap.contains("<url><loc>https://example.com/blog/second/</loc><lastmod>"));
+        assert_eq!(sitemap.matches("<url>").count(), 2);
     }
 
     #[tokio::test]
-    async fn update_html_writes_parsed_markdown_to_html_file() {
+    async fn update_html_checks_only_chosen_grammar_blocks() {
         // arrange
-        let markdown_path = Path::new("./fixtures/file.md");
-        let html_path = Path::new("./fixtures/file_a.html");
-        let stdout = io::stdout();
-        let mut handle = io::BufWriter::new(stdout);
-        let options = MarkwriteOptions::default();
+        let url = start_grammar_server_with(|body| {
+            let mut response = languagetool_response_fixture();
+            let matches: Vec<serde_json::Value> = ["foox", "dogg"]
+                .iter()
+                .filter(|word| body.contains(*word))
+                .map(|word| {
+                    let mut result = response["matches"][0].clone();
+                    result["rule"]["id"] =
+                        serde_json::json!(format!("{}_RULE", word.to_uppercase()));
+                    result
+                })
+                .collect();
+            response["matches"] = serde_json::json!(matches);
+            response
+        });
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("blocks.md");
+        let html_path = temp_dir.path().join("blocks.html");
+        fs::write(
+            &markdown_path,
+            "# A heading about a foox\n\nThe lazy dogg sleeps all afternoon.\n",
+        )
+        .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_grammar_check();
+        options.enable_check_only();
+        options.set_grammar_url(&url);
+        options.set_grammar_report_format(GrammarReportFormat::Json);
+        options.add_grammar_block(BlockKind::Paragraph);
 
         // act
-        update_html(&markdown_path, &html_path, &options, &mut handle)
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
             .await
             .expect("Error calling update_html");
 
         // assert
-        let mut html_file = File::open(html_path).unwrap();
-        let parse_result = parse_document(RcDom::default(), ParseOpts::default())
-            .from_utf8()
-            .read_from(&mut html_file)
-            .expect("Error parsing generated HTML file");
-        assert_eq!(parse_result.errors.len(), 0);
-
-        // cleanup
-        remove_file(html_path).expect("Unable to delete HTML output in cleanup");
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains(r#""ruleId": "DOGG_RULE""#));
+        assert!(!output.contains("FOOX_RULE"));
     }
 
     #[tokio::test]
-    async fn update_html_output_has_expected_tags_set() {
+    async fn update_html_writes_crlf_line_endings() {
         // arrange
-        let markdown_path = Path::new("./fixtures/file.md");
-        let html_path = Path::new("./fixtures/file_b.html");
-        let stdout = io::stdout();
-        let mut handle = io::BufWriter::new(stdout);
-        let options = MarkwriteOptions::default();
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("windows.md");
+        let html_path = temp_dir.path().join("windows.html");
+        fs::write(&markdown_path, "# Windows\n\nFirst line.\n\nSecond line.\n")
+            .expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.set_output_line_endings(LineEnding::Crlf);
 
         // act
-        update_html(&markdown_path, &html_path, &options, &mut handle)
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
             .await
             .expect("Error calling update_html");
 
         // assert
-        let mut html_file = File::open(html_path).unwrap();
-        let parse_result = parse_document(RcDom::default(), ParseOpts::default())
-            .from_utf8()
-            .read_from(&mut html_file)
-            .expect("Error parsing generated HTML file");
-
-        let document_nodes = parse_result.document.children.borrow();
-        let mut document_nodes_iterator = document_nodes.iter();
-        let doctype_node = document_nodes_iterator.next().unwrap();
-
-        // check DOCTYPE element
-        let NodeData::Doctype { ref name, .. } = doctype_node.data else {
-            unimplemented!("Expected DOCTYPE element to exist")
-        };
-        assert_eq!(name, &Tendril::<UTF8>::from_slice("html"));
+        let html = fs::read(&html_path).expect("Error reading generated HTML");
+        let html = String::from_utf8(html).expect("Expected UTF-8 output");
+        assert!(html.contains("\r\n"));
+        assert_eq!(html.matches('\n').count(), html.matches("\r\n").count());
+        assert!(!html.starts_with('\u{feff}'));
+    }
 
-        // check html element
-        let html_node = document_nodes_iterator.next().unwrap();
-        let NodeData::Element {
-            ref attrs,
-            ref name,
-            ..
-        } = html_node.data
-        else {
-            unimplemented!("Expected html element to exist")
-        };
-        assert_eq!(name, &QualName::new(None, ns!(html), local_name!("html")));
+    #[tokio::test]
+    async fn update_html_prepends_byte_order_mark() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp directory");
+        let markdown_path = temp_dir.path().join("bom.md");
+        let html_path = temp_dir.path().join("bom.html");
+        fs::write(&markdown_path, "# Marked\n\nSome text.\n").expect("Error writing temp markdown");
+        let mut handle: Vec<u8> = Vec::new();
+        let mut options = MarkwriteOptions::default();
+        options.enable_output_bom();
 
-        // check html lang is set
-        let mut attrs = attrs.borrow_mut();
-        let lang = match attrs.iter_mut().find(|val| &*val.name.local == "lang") {
-            Some(value) => &value.value,
-            None => unimplemented!("Expected lang attribute to be set on html element"),
-        };
-        assert_eq!(lang, &Tendril::<UTF8>::from_slice("en"));
+        // act
+        update_html(&markdown_path, &html_path, &options, None, &mut handle)
+            .await
+            .expect("Error calling update_html");
 
-        // cleanup
-        remove_file(html_path).expect("Unable to delete HTML output in cleanup");
+        // assert
+        let html = fs::read(&html_path).expect("Error reading generated HTML");
+        assert!(html.starts_with(&[0xef, 0xbb, 0xbf]));
+        assert!(html[3..].starts_with(b"<!DOCTYPE html>"));
+        assert!(!html.windows(2).any(|pair| pair == b"\r\n"));
     }
 }
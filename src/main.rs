@@ -1,20 +1,47 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::{info, trace};
-use notify::RecursiveMode;
-use notify_debouncer_mini::new_debouncer;
+use notify::{RecursiveMode, Watcher};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
 use std::{
     collections::HashSet,
-    fs::File,
+    fs::{self, File},
     io::{self, Write},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
-    time::Duration,
+    sync::mpsc::{self, Receiver},
+    time::{Duration, SystemTime},
 };
 
 #[derive(Parser)]
 #[clap(author,version,about,long_about=None)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    path: PathBuf,
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Markdown files, or directories of them, to render
+    #[clap(required_unless_present = "print_config")]
+    paths: Vec<PathBuf>,
+
+    /// Print the settings in effect, from defaults, environment variables and flags, as JSON and
+    /// exit
+    #[clap(long = "print-config")]
+    print_config: bool,
+
+    /// Set a frontmatter field for every document, overriding any value in the document, for
+    /// example --set title="Release Notes" (repeatable)
+    #[clap(long, value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    set: Vec<(String, String)>,
+
+    /// Print an outline of the document headings, indented by level, and exit without writing
+    /// output
+    #[clap(long = "list-headings")]
+    list_headings: bool,
+
+    /// Also print the slug each heading links to, with --list-headings
+    #[clap(long = "heading-slugs", requires = "list_headings")]
+    heading_slugs: bool,
 
     #[clap(flatten)]
     verbose: clap_verbosity_flag::Verbosity,
@@ -23,44 +50,623 @@ struct Cli {
     #[clap(short, long)]
     spelling: bool,
 
+    /// Custom dictionary file, repeat or separate with commas to merge several dictionaries
+    #[clap(long, value_delimiter = ',', default_value = ".markwrite/custom.dict")]
+    dictionary: Vec<PathBuf>,
+
+    /// Skip the bundled dictionary of common technical terms, loaded before any custom dictionary
+    #[clap(long = "no-default-dictionary")]
+    no_default_dictionary: bool,
+
+    /// After checking spelling, fix misspellings with a single suggestion in the markdown source,
+    /// keeping the original as a .bak file
+    #[clap(long, requires = "spelling")]
+    autofix: bool,
+
+    /// LanguageTool server check endpoint, defaults to the public API
+    #[clap(long = "grammar-url", env = "MARKWRITE_LANGUAGETOOL_URL")]
+    grammar_url: Option<String>,
+
+    /// LanguageTool account username, used along with an API key
+    #[clap(
+        long = "grammar-username",
+        env = "MARKWRITE_LANGUAGETOOL_USERNAME",
+        requires = "grammar_api_key"
+    )]
+    grammar_username: Option<String>,
+
+    /// LanguageTool account API key, used along with a username
+    #[clap(
+        long = "grammar-api-key",
+        env = "MARKWRITE_LANGUAGETOOL_API_KEY",
+        hide_env_values = true,
+        requires = "grammar_username"
+    )]
+    grammar_api_key: Option<String>,
+
+    /// Never contact the LanguageTool server, skipping spelling and grammar checks even when enabled
+    #[clap(
+        long = "languagetool-disabled",
+        env = "MARKWRITE_LANGUAGETOOL_DISABLED"
+    )]
+    languagetool_disabled: bool,
+
+    /// Maximum replacement suggestions shown for each spelling or grammar issue, 0 hides them
+    #[clap(long = "max-replacements", value_parser)]
+    max_replacements: Option<usize>,
+
+    /// Spelling and grammar check requests allowed in flight at once, across all files
+    #[clap(long = "grammar-concurrency", value_parser)]
+    grammar_concurrency: Option<NonZeroUsize>,
+
+    /// Most spelling and grammar check requests sent over the whole run, for example to stay
+    /// within a daily quota. Later checks are skipped, while HTML is still written.
+    #[clap(long = "max-grammar-requests", value_parser)]
+    max_grammar_requests: Option<usize>,
+
+    /// Sentences from the end of each spelling and grammar check request repeated at the start of
+    /// the next, so issues spanning requests are found, defaults to 2
+    #[clap(long = "grammar-chunk-overlap", value_parser)]
+    grammar_chunk_overlap: Option<usize>,
+
+    /// Characters of text shown either side of each spelling and grammar issue, instead of the
+    /// context returned by the grammar server
+    #[clap(long = "grammar-context", value_name = "CHARACTERS", value_parser)]
+    grammar_context: Option<usize>,
+
+    /// Output format for spelling and grammar check results, and the summary of a multi-file run
+    #[clap(
+        long = "grammar-format",
+        visible_alias = "format",
+        value_enum,
+        default_value_t
+    )]
+    grammar_format: markwrite::GrammarReportFormat,
+
+    /// How grammar check chunks find sentence ends: a quick heuristic, or local sentence
+    /// segmentation, which splits mid-sentence less often
+    #[clap(long = "sentence-segmentation", value_enum, default_value_t)]
+    sentence_segmentation: markwrite::SentenceSegmentation,
+
+    /// Characters ending a sentence when splitting text for grammar checks, for example ".!?。",
+    /// in place of the default full stops, question and exclamation marks, including CJK and
+    /// Arabic forms
+    #[clap(long = "sentence-terminators", value_name = "CHARACTERS")]
+    sentence_terminators: Option<String>,
+
+    /// Only check the text of these kinds of block for spelling and grammar, for example
+    /// paragraph,block-quote, leaving out headings, list items and table cells, which are often
+    /// fragments [default: all]
+    #[clap(long = "grammar-blocks", value_enum, value_delimiter = ',')]
+    grammar_blocks: Vec<markwrite::BlockKind>,
+
+    /// Language variants preferred when the grammar server detects the language, for example
+    /// en-US,de-AT. Regions with a language marker other than auto are then rejected.
+    #[clap(long = "grammar-preferred-variants", value_delimiter = ',')]
+    grammar_preferred_variants: Vec<String>,
+
+    /// Warn when the detected document language confidence, from 0 to 1, is below this value
+    #[clap(long = "min-language-confidence", value_parser)]
+    min_language_confidence: Option<f64>,
+
+    /// Skip spelling and grammar checks in a category, for example TYPOGRAPHY (repeatable)
+    #[clap(long = "disable-grammar-category", value_parser)]
+    disable_grammar_category: Vec<String>,
+
+    /// File listing spelling and grammar rule, or category, ids to leave out of results, one on
+    /// each line [default: .markwrite-grammar-ignore, when present]
+    #[clap(long = "grammar-ignore-file", value_name = "PATH")]
+    grammar_ignore_file: Option<PathBuf>,
+
     #[clap(short, long)]
     watch: bool,
 
+    /// Render every input, then write an index.html, to the output directory or directory input,
+    /// linking to each document with its title, date and description, newest first. The index is
+    /// rewritten after each update.
+    #[clap(long)]
+    index: bool,
+
+    /// Render every input, then write a sitemap.xml, to the output directory or directory input,
+    /// listing the canonical URL and last modified date of each document. The sitemap is
+    /// rewritten after each update.
+    #[clap(long)]
+    sitemap: bool,
+
     #[clap(short, long, value_parser)]
     output: Option<PathBuf>,
+
+    /// Run this command on each output after it is written, with the output path as its last
+    /// argument, or the HTML on standard input when an argument is -, such as --open-in
+    /// "wkhtmltopdf - out.pdf". Quote arguments containing spaces, as in a shell
+    #[clap(long = "open-in", value_name = "COMMAND")]
+    open_in: Option<String>,
+
+    /// Set the permissions of written output files to this octal mode, such as 644, on Unix
+    #[clap(long, value_name = "OCTAL", value_parser = parse_mode)]
+    chmod: Option<u32>,
+
+    /// Line endings of written output files, crlf for tools on Windows which expect them
+    #[clap(long = "output-line-endings", value_enum, default_value_t)]
+    output_line_endings: markwrite::LineEnding,
+
+    /// Start written output files with a UTF-8 byte order mark
+    #[clap(long = "output-bom")]
+    output_bom: bool,
+
+    /// Only process inputs modified since this RFC 3339 timestamp, such as 2024-05-01T09:30:00Z, or
+    /// within this duration, such as 36h or 7d
+    #[clap(long, value_parser = parse_since)]
+    since: Option<SystemTime>,
+
+    /// Directory to write rendered HTML into, mirroring the layout of directory inputs
+    #[clap(long = "output-dir", value_parser, conflicts_with = "output")]
+    output_dir: Option<PathBuf>,
+
+    /// Directory of layout templates, chosen by the frontmatter layout field, for example layout:
+    /// landing renders with landing.html
+    #[clap(long = "templates-dir", value_parser)]
+    templates_dir: Option<PathBuf>,
+
+    /// Reuse HTML rendered from the same source and options, kept in this directory, instead of
+    /// rendering again
+    #[clap(long = "cache-dir", value_parser, env = "MARKWRITE_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Write the plaintext sent for spelling and grammar checks to this file, for debugging
+    #[clap(long = "dump-plaintext", value_parser)]
+    dump_plaintext: Option<PathBuf>,
+
+    /// Title for documents with no frontmatter title or h1 heading
+    #[clap(long = "default-title", value_parser)]
+    default_title: Option<String>,
+
+    /// Site root URL, such as https://example.com, which root-relative links and canonical URLs
+    /// are resolved against, overriding any frontmatter site_url
+    #[clap(long = "canonical-root-url", value_parser)]
+    canonical_root_url: Option<String>,
+
+    /// Site subdirectory, such as /blog, prepended to root-relative link and image URLs
+    #[clap(long = "base-path", value_parser)]
+    base_path: Option<String>,
+
+    /// Deepest heading level, from 1 to 6, given an id and permalink
+    #[clap(long = "max-heading-id-level", value_parser = clap::value_parser!(u8).range(1..=6))]
+    max_heading_id_level: Option<u8>,
+
+    /// Inline stylesheet into the output head, after the default styles (repeatable)
+    #[clap(long = "extra-css", value_parser)]
+    extra_css: Vec<PathBuf>,
+
+    /// Add the HTML, or markdown for `.md` files, at PATH before the content of every document
+    #[clap(long, value_name = "PATH", value_parser)]
+    header: Option<PathBuf>,
+
+    /// Add the HTML, or markdown for `.md` files, at PATH after the content of every document
+    #[clap(long, value_name = "PATH", value_parser)]
+    footer: Option<PathBuf>,
+
+    /// Inline local images into the output as base64 data URIs
+    #[clap(long = "embed-images")]
+    embed_images: bool,
+
+    /// Maximum size, in bytes, of an image to inline with --embed-images
+    #[clap(long = "embed-images-max-size", value_parser)]
+    embed_images_max_size: Option<u64>,
+
+    /// Print time spent parsing, processing HTML and checking grammar on each update
+    #[clap(long)]
+    timings: bool,
+
+    /// Show the word count and estimated reading time at the top of the document
+    #[clap(long)]
+    byline: bool,
+
+    /// Render an "On this page" sidebar linking to the document headings
+    #[clap(long = "sidebar-toc")]
+    sidebar_toc: bool,
+
+    /// Expand GitHub-style emoji shortcodes, such as :smile:, outside code
+    #[clap(long = "emoji-shortcodes")]
+    emoji_shortcodes: bool,
+
+    /// Render ~subscript~ and ^superscript^ spans, such as H~2~O and x^2^, outside code
+    #[clap(long = "subscript-superscript")]
+    subscript_superscript: bool,
+
+    /// Open external links in the same tab, without target="_blank". External links still get
+    /// rel="noopener noreferrer".
+    #[clap(long = "external-links-same-tab")]
+    external_links_same_tab: bool,
+
+    /// Render single newlines within a paragraph as line breaks, instead of joining the lines
+    #[clap(long = "hard-line-breaks")]
+    hard_line_breaks: bool,
+
+    /// Prefix headings, and their sidebar contents entries, with section numbers such as 1.2.1
+    #[clap(long = "number-headings")]
+    number_headings: bool,
+
+    /// Base URL, for example a CDN, for relative image sources, leaving relative links unchanged
+    #[clap(long = "images-base-url")]
+    images_base_url: Option<String>,
+
+    /// Rewrite `.md` links matching GLOB, such as `docs/**/*.md`, to `.html` even when the target
+    /// is not a local file (repeatable). Other `.md` links are only rewritten when the target
+    /// exists in the project.
+    #[clap(long = "internal-link-pattern", value_name = "GLOB")]
+    internal_link_patterns: Vec<String>,
+
+    /// Generate 480, 960 and 1440 pixel wide copies of larger local images, listed in a srcset
+    #[clap(long = "responsive-images")]
+    responsive_images: bool,
+
+    /// Replace local images wider than this many pixels with downscaled copies, written next to the
+    /// output
+    #[clap(long = "max-image-width", value_name = "PIXELS")]
+    max_image_width: Option<u32>,
+
+    /// Replace local images taller than this many pixels with downscaled copies, written next to
+    /// the output
+    #[clap(long = "max-image-height", value_name = "PIXELS")]
+    max_image_height: Option<u32>,
+
+    /// Embed the markdown source in the output, in a `<script type="text/markdown">` element
+    #[clap(long = "embed-source")]
+    embed_source: bool,
+
+    /// Write only the processed content HTML, without the full document, for use in other templates
+    #[clap(long)]
+    fragment: bool,
+
+    /// Indent nested elements in the output HTML, instead of writing it compactly
+    #[clap(long)]
+    pretty: bool,
+
+    /// Also write a gzipped copy of the output, ending `.html.gz`, for static hosts serving
+    /// pre-compressed files
+    #[clap(long)]
+    gzip: bool,
+
+    /// Gzip compression level, from 0 (none) to 9 (best)
+    #[clap(long = "gzip-level", value_parser = clap::value_parser!(u32).range(0..=9))]
+    gzip_level: Option<u32>,
+
+    /// Write only the gzipped copy of the output, and not the uncompressed HTML
+    #[clap(long = "gzip-only")]
+    gzip_only: bool,
+
+    /// Write output for documents marked `draft: true` in their frontmatter
+    #[clap(long = "include-drafts")]
+    include_drafts: bool,
+
+    /// Keep the document frontmatter in the output, as an HTML comment at the top
+    #[clap(long = "include-frontmatter-in-output")]
+    include_frontmatter_in_output: bool,
+
+    /// Check the document and report any problems, without writing output or watching for changes
+    #[clap(long = "check-only")]
+    check_only: bool,
+
+    /// Warn about trailing whitespace, runs of spaces in prose, hard tabs, code fences mixing tab
+    /// and space indentation, undefined link references, long sentences, skipped heading levels,
+    /// links sharing text but not targets, and reused images in the markdown source
+    #[clap(long)]
+    lint: bool,
+
+    /// Most words in a sentence before --lint warns about it, defaults to 40
+    #[clap(long = "max-sentence-words", value_parser)]
+    max_sentence_words: Option<u32>,
+
+    /// Treat markdown parse warnings as errors and skip writing output when there are any
+    #[clap(long)]
+    strict: bool,
+
+    /// Remove HTML comments, such as <!-- TODO --> notes, from the output
+    #[clap(long = "strip-comments")]
+    strip_comments: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Remove generated HTML and sidecar files for markdown sources in a directory
+    Clean {
+        directory: PathBuf,
+
+        /// List files which would be removed, without removing them
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Check spelling, punctuation and grammar only, printing the results without writing HTML
+    Grammar {
+        /// Markdown files, or directories of them, to check
+        #[clap(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Output format for the check results
+        #[clap(long, value_enum, default_value_t)]
+        format: markwrite::GrammarReportFormat,
+    },
+}
+
+fn parse_since(value: &str) -> Result<SystemTime, String> {
+    markwrite::parse_since(value, SystemTime::now())
+}
+
+/// Key and value from a `key=value` argument
+fn parse_key_value(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("Invalid value: {value}, expected KEY=VALUE"))
+}
+
+/// File permissions from an octal mode, such as `644` or `0o644`
+fn parse_mode(value: &str) -> Result<u32, String> {
+    let digits = value.strip_prefix("0o").unwrap_or(value);
+    match u32::from_str_radix(digits, 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(mode),
+        _ => Err(format!(
+            "Invalid mode: {value}, expected an octal mode such as 644"
+        )),
+    }
+}
+
+/// Whether a watch event for `event_path` concerns the input at `path`
+fn is_event_for_path(event_path: &Path, path: &Path) -> bool {
+    event_path == path
+        || match (event_path.canonicalize(), path.canonicalize()) {
+            (Ok(event_path), Ok(path)) => event_path == path,
+            _ => event_path.ends_with(path),
+        }
+}
+
+/// Path watched for changes to the input at `path`. Symlinks are resolved, so edits to the target
+/// of a symlinked input are picked up.
+fn watch_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn watch_targets(watcher: &mut dyn Watcher, targets: &[(PathBuf, PathBuf)]) -> notify::Result<()> {
+    for (path, _) in targets {
+        watcher.watch(&watch_path(path), RecursiveMode::NonRecursive)?;
+    }
+    Ok(())
+}
+
+/// Messages handled by the watch loop
+enum WatchMessage {
+    /// Debounced file system events, or a watch error
+    Changes(DebounceEventResult),
+
+    /// Stop watching, sent on Ctrl-C
+    Shutdown,
 }
 
-async fn debounce_watch<P1: AsRef<Path>, P2: AsRef<Path>>(
-    path: P1,
-    output_path: P2,
-    options: &markwrite::MarkwriteOptions,
+async fn debounce_watch(
+    targets: &[(PathBuf, PathBuf)],
+    markwrite: &markwrite::Markwrite,
+    listing_pages: Option<&mut ListingPages>,
+    show_summary: bool,
     stdout_handle: &mut impl Write,
-) {
-    let (tx, rx) = std::sync::mpsc::channel();
-
-    let mut debouncer = new_debouncer(Duration::from_millis(250), tx).unwrap();
-
-    debouncer
-        .watcher()
-        .watch(path.as_ref(), RecursiveMode::NonRecursive)
-        .unwrap();
-
-    for events in rx {
-        match events {
-            Ok(event) => {
-                trace!("{:?}", event);
-
-                // Editor may temporarily rename the input file while saving it
-                if markwrite::update_html(&path, &output_path, options, stdout_handle)
-                    .await
-                    .is_err()
-                {
-                    info!("[ INFO ] Looks like the input file was renamed.");
-                };
+) -> io::Result<()> {
+    let (tx, rx) = mpsc::channel();
+
+    let changes_tx = tx.clone();
+    let mut debouncer = new_debouncer(Duration::from_millis(250), move |result| {
+        let _ = changes_tx.send(WatchMessage::Changes(result));
+    })
+    .unwrap();
+
+    watch_targets(debouncer.watcher(), targets).unwrap();
+
+    if let Err(error) = ctrlc::set_handler(move || {
+        let _ = tx.send(WatchMessage::Shutdown);
+    }) {
+        eprintln!("[ ERROR ] Unable to handle Ctrl-C, stop with another signal: {error:?}.");
+    }
+
+    watch_loop(
+        &rx,
+        targets,
+        markwrite,
+        listing_pages,
+        show_summary,
+        stdout_handle,
+    )
+    .await
+}
+
+/// Updates outputs for each batch of changes until a shutdown message arrives. Messages are
+/// handled one at a time, so an update in progress finishes writing its output before shutdown.
+/// Any `listing_pages` are updated with each outcome and rewritten after each batch. With
+/// `show_summary`, a batch updating several inputs ends with a [`markwrite::BatchSummary`].
+async fn watch_loop(
+    rx: &Receiver<WatchMessage>,
+    targets: &[(PathBuf, PathBuf)],
+    markwrite: &markwrite::Markwrite,
+    mut listing_pages: Option<&mut ListingPages>,
+    show_summary: bool,
+    stdout_handle: &mut impl Write,
+) -> io::Result<()> {
+    for message in rx {
+        match message {
+            WatchMessage::Shutdown => break,
+            WatchMessage::Changes(Ok(events)) => {
+                trace!("{:?}", events);
+
+                let mut summary = markwrite::BatchSummary::default();
+                let mut update_count = 0;
+                for (path, output_path) in targets.iter().filter(|(path, _)| {
+                    targets.len() == 1
+                        || events
+                            .iter()
+                            .any(|event| is_event_for_path(&event.path, path))
+                }) {
+                    let result = markwrite
+                        .update_file(path, output_path, stdout_handle)
+                        .await;
+                    // Editor may temporarily rename the input file while saving it
+                    if result.is_err() {
+                        info!("[ INFO ] Looks like the input file was renamed.");
+                    };
+                    if let Some(value) = listing_pages.as_deref_mut() {
+                        value.record(path, &result);
+                    }
+                    summary.record(&result);
+                    update_count += 1;
+                }
+                if show_summary && update_count > 1 {
+                    let format = markwrite.options().grammar_report_format();
+                    summary.display(format, stdout_handle)?;
+                }
+                if let Some(value) = &listing_pages {
+                    value.write(markwrite.options(), stdout_handle)?;
+                }
+            }
+            WatchMessage::Changes(Err(e)) => eprintln!("[ ERROR ] watch error: {:?}.", e),
+        }
+    }
+    writeln!(stdout_handle, "[ INFO ] stopped watching for file changes.")?;
+    stdout_handle.flush()
+}
+
+/// Pages listing every rendered document, written to the output directory after each update
+struct ListingPages {
+    /// Every target, including any left out of rendering by --since
+    targets: Vec<(PathBuf, PathBuf)>,
+
+    /// Listing for each of `targets`, or `None` when it has no output
+    documents: Vec<Option<markwrite::ListedDocument>>,
+    directory: PathBuf,
+    index: bool,
+    sitemap: bool,
+}
+
+impl ListingPages {
+    fn new(
+        targets: Vec<(PathBuf, PathBuf)>,
+        directory: PathBuf,
+        index: bool,
+        sitemap: bool,
+    ) -> Self {
+        ListingPages {
+            documents: vec![None; targets.len()],
+            targets,
+            directory,
+            index,
+            sitemap,
+        }
+    }
+
+    /// Lists targets not in `rendered` from their sources, as output by an earlier run
+    fn read_unrendered(
+        &mut self,
+        rendered: &[(PathBuf, PathBuf)],
+        options: &markwrite::MarkwriteOptions,
+    ) {
+        for ((path, _), document) in self.targets.iter().zip(self.documents.iter_mut()) {
+            if rendered
+                .iter()
+                .any(|(rendered_path, _)| rendered_path == path)
+            {
+                continue;
+            }
+            match markwrite::ListedDocument::read(path, options) {
+                Ok(value) => *document = value,
+                Err(error) => eprintln!("[ ERROR ] Unable to read {}: {error}.", path.display()),
+            }
+        }
+    }
+
+    /// Keeps the listing from updating the input at `path`, dropping it when no output was written
+    fn record<E>(&mut self, path: &Path, result: &Result<markwrite::UpdateOutcome, E>) {
+        if let Some(index) = self.targets.iter().position(|(value, _)| value == path) {
+            self.documents[index] = result
+                .as_ref()
+                .ok()
+                .and_then(|outcome| outcome.document().cloned());
+        }
+    }
+
+    /// Rendered outputs, with their listings
+    fn documents(&self) -> Vec<(&Path, &markwrite::ListedDocument)> {
+        self.targets
+            .iter()
+            .zip(&self.documents)
+            .filter_map(|((_, output_path), document)| {
+                Some((output_path.as_path(), document.as_ref()?))
+            })
+            .collect()
+    }
+
+    /// Writes the pages, reporting rather than returning any error, so watching carries on
+    fn write(
+        &self,
+        options: &markwrite::MarkwriteOptions,
+        stdout_handle: &mut impl Write,
+    ) -> io::Result<()> {
+        let documents = self.documents();
+        if self.index {
+            match markwrite::write_index(&documents, &self.directory, options) {
+                Ok(path) => writeln!(stdout_handle, "[ INFO ] Wrote index {}.", path.display())?,
+                Err(error) => eprintln!("[ ERROR ] Unable to write index: {error}."),
+            }
+        }
+        if self.sitemap {
+            match markwrite::write_sitemap(&documents, &self.directory, options) {
+                Ok(path) => writeln!(stdout_handle, "[ INFO ] Wrote sitemap {}.", path.display())?,
+                Err(error) => eprintln!("[ ERROR ] Unable to write sitemap: {error}."),
             }
-            Err(e) => eprintln!("[ ERROR ] watch error: {:?}.", e),
         }
+        stdout_handle.flush()
+    }
+}
+
+/// Prints a heading outline for each target input, headed by its path when there are several
+fn list_headings(
+    targets: &[(PathBuf, PathBuf)],
+    options: markwrite::MarkwriteOptions,
+    show_slugs: bool,
+    stdout_handle: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Rendering headings needs no spelling dictionary
+    let markwrite = markwrite::Markwrite::new(options, HashSet::new());
+    for (path, _output_path) in targets {
+        let source = fs::read_to_string(path)
+            .map_err(|error| format!("[ ERROR ] Unable to read {}: {error}.", path.display()))?;
+        if targets.len() > 1 {
+            writeln!(stdout_handle, "{}", path.display())?;
+        }
+        let results = markwrite.render(&source);
+        markwrite::display_heading_outline(
+            results.headings().unwrap_or_default(),
+            show_slugs,
+            stdout_handle,
+        )?;
+    }
+    Ok(())
+}
+
+/// Checks the grammar of each target input, without rendering or writing any output
+async fn check_grammar_only(
+    paths: &[PathBuf],
+    options: markwrite::MarkwriteOptions,
+    dictionary: HashSet<String>,
+    stdout_handle: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let targets = markwrite::output_paths(paths, None, None)
+        .map_err(|error| format!("[ ERROR ] {error}."))?;
+    let markwrite = markwrite::Markwrite::new(options, dictionary);
+    for (path, _output_path) in &targets {
+        markwrite
+            .check_file_grammar(path, stdout_handle)
+            .await
+            .map_err(|error| format!("[ ERROR ] Unable to check {}: {error}.", path.display()))?;
     }
+    stdout_handle.flush()?;
+    Ok(())
 }
 
 #[tokio::main]
@@ -69,47 +675,485 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::Builder::new()
         .filter_level(cli.verbose.log_level_filter())
         .init();
-    let path = &cli.path;
+
+    if let Some(Command::Clean { directory, dry_run }) = &cli.command {
+        let stdout = io::stdout();
+        let mut stdout_handle = io::BufWriter::new(stdout);
+        markwrite::clean_generated_outputs(directory, *dry_run, &mut stdout_handle)?;
+        return Ok(());
+    }
 
     let mut options = markwrite::MarkwriteOptions::default();
 
     if cli.spelling {
         options.enable_grammar_check()
     }
+    if cli.autofix {
+        options.enable_autofix();
+    }
+    if cli.languagetool_disabled {
+        options.disable_languagetool();
+    }
+    if let Some(value) = cli.max_replacements {
+        options.set_grammar_replacement_limit(value);
+    }
+    options.set_grammar_report_format(cli.grammar_format);
+    options.set_sentence_segmentation(cli.sentence_segmentation);
+    if let Some(value) = &cli.sentence_terminators {
+        options.set_sentence_terminators(value);
+    }
+    if let Some(value) = cli.grammar_chunk_overlap {
+        options.set_grammar_chunk_overlap(value);
+    }
+    if let Some(value) = cli.grammar_context {
+        options.set_grammar_context_width(value);
+    }
+    if let Some(value) = &cli.grammar_url {
+        options.set_grammar_url(value);
+    }
+    if let (Some(username), Some(api_key)) = (&cli.grammar_username, &cli.grammar_api_key) {
+        options.set_grammar_api_credentials(username, api_key);
+    }
+    if let Some(value) = cli.grammar_concurrency {
+        options.set_grammar_concurrency(value.get());
+    }
+    if let Some(value) = cli.max_grammar_requests {
+        options.set_max_grammar_requests(value);
+    }
+    if let Some(value) = cli.min_language_confidence {
+        options.set_min_language_confidence(value);
+    }
+    for kind in &cli.grammar_blocks {
+        options.add_grammar_block(*kind);
+    }
+    for variant in &cli.grammar_preferred_variants {
+        options.add_grammar_preferred_variant(variant);
+    }
+    for category_id in &cli.disable_grammar_category {
+        options.add_disabled_grammar_category(category_id);
+    }
+    if let Some(value) = &cli.templates_dir {
+        markwrite::validate_layout_templates(value)
+            .map_err(|error| format!("[ ERROR ] {error}."))?;
+        options.set_templates_dir(value);
+    }
+    for (key, value) in &cli.set {
+        options
+            .set_frontmatter_value(key, value)
+            .map_err(|error| format!("[ ERROR ] {error}."))?;
+    }
+    if let Some(value) = cli.chmod {
+        options.set_output_mode(value);
+    }
+    options.set_output_line_endings(cli.output_line_endings);
+    if cli.output_bom {
+        options.enable_output_bom();
+    }
+    if let Some(value) = &cli.open_in {
+        options.set_open_in(value);
+    }
+    if let Some(value) = &cli.cache_dir {
+        options.set_cache_dir(value);
+    }
+    if let Some(value) = &cli.dump_plaintext {
+        options.set_dump_plaintext_path(value);
+    }
+    if let Some(value) = &cli.default_title {
+        options.set_default_title(value);
+    }
+    if let Some(value) = &cli.base_path {
+        options.set_base_path(value);
+    }
+    if let Some(value) = &cli.canonical_root_url {
+        options.set_canonical_root_url(value);
+    }
+    if let Some(value) = cli.max_heading_id_level {
+        options.set_max_heading_id_level(value);
+    }
+    for extra_css_path in &cli.extra_css {
+        options.add_extra_css_path(extra_css_path);
+    }
+    if let Some(value) = &cli.header {
+        options.set_header_path(value);
+    }
+    if let Some(value) = &cli.footer {
+        options.set_footer_path(value);
+    }
+    if cli.strict {
+        options.enable_strict();
+    }
+    if cli.strip_comments {
+        options.enable_strip_comments();
+    }
+    if cli.byline {
+        options.enable_byline();
+    }
+    if cli.sidebar_toc {
+        options.enable_sidebar_toc();
+    }
+    if cli.emoji_shortcodes {
+        options.enable_emoji_shortcodes();
+    }
+    if cli.subscript_superscript {
+        options.enable_subscript_superscript();
+    }
+    if cli.external_links_same_tab {
+        options.enable_external_links_same_tab();
+    }
+    if cli.hard_line_breaks {
+        options.enable_hard_line_breaks();
+    }
+    if cli.number_headings {
+        options.enable_number_headings();
+    }
+    if let Some(value) = &cli.images_base_url {
+        options.set_images_base_url(value);
+    }
+    for pattern in &cli.internal_link_patterns {
+        options.add_internal_link_pattern(pattern);
+    }
+    if cli.responsive_images {
+        options.enable_responsive_images();
+    }
+    if let Some(value) = cli.max_image_width {
+        options.set_max_image_width(value);
+    }
+    if let Some(value) = cli.max_image_height {
+        options.set_max_image_height(value);
+    }
+    if cli.embed_source {
+        options.enable_embed_source();
+    }
+    if cli.fragment {
+        options.enable_fragment();
+    }
+    if cli.pretty {
+        options.enable_pretty();
+    }
+    if cli.gzip {
+        options.enable_gzip();
+    }
+    if let Some(value) = cli.gzip_level {
+        options.set_gzip_level(value);
+    }
+    if cli.gzip_only {
+        options.enable_gzip_only();
+    }
+    if cli.include_drafts {
+        options.enable_include_drafts();
+    }
+    if cli.include_frontmatter_in_output {
+        options.enable_include_frontmatter_in_output();
+    }
+    if cli.lint {
+        options.enable_lint();
+    }
+    if let Some(value) = cli.max_sentence_words {
+        options.set_max_sentence_words(value);
+    }
+    if cli.check_only {
+        options.enable_check_only();
+    }
+    if cli.timings {
+        options.enable_timings();
+    }
+    if cli.embed_images {
+        options.enable_embed_images();
+    }
+    if let Some(value) = cli.embed_images_max_size {
+        options.set_embed_images_max_size(value);
+    }
+    let grammar_ignore_path = cli
+        .grammar_ignore_file
+        .as_deref()
+        .unwrap_or(Path::new(markwrite::DEFAULT_GRAMMAR_IGNORE_PATH));
+    match markwrite::load_grammar_ignore_file(grammar_ignore_path) {
+        Ok(ids) => ids
+            .iter()
+            .for_each(|id| options.add_ignored_grammar_rule(id)),
+        // The default ignore file is optional, but one given on the command line must be readable
+        Err(error) if cli.grammar_ignore_file.is_some() => {
+            return Err(format!(
+                "[ ERROR ] Unable to read grammar ignore file ({}): {error}.",
+                grammar_ignore_path.display()
+            )
+            .into());
+        }
+        Err(_) => {}
+    }
+    if cli.print_config {
+        let mut config = options.effective_config();
+        config["dictionaries"] = serde_json::json!(cli.dictionary);
+        config["defaultDictionary"] = serde_json::json!(!cli.no_default_dictionary);
+        writeln!(io::stdout(), "{}", serde_json::to_string_pretty(&config)?)?;
+        return Ok(());
+    }
 
-    let mut default_output_path = PathBuf::from(path);
-    default_output_path.set_extension("html");
-    let output_path = match &cli.output {
-        Some(value) => value,
-        None => &default_output_path,
-    };
+    if let Some(Command::Grammar { paths, format }) = &cli.command {
+        options.set_grammar_report_format(*format);
+        let stdout = io::stdout();
+        let mut stdout_handle = io::BufWriter::new(stdout);
+        let dictionary = markwrite::load_dictionaries(
+            &cli.dictionary,
+            !cli.no_default_dictionary,
+            &mut stdout_handle,
+        );
+        return check_grammar_only(paths, options, dictionary, &mut stdout_handle).await;
+    }
 
-    /* Check input file exists. Do the check here, rather than handle on each
+    /* Check input files exist. Do the check here, rather than handle on each
      * modification since, text editor may temporarily rename the original file
      * on saving it.
      */
-    if File::open(path).is_err() {
-        let error_message = match path.to_str() {
-            Some(value) => {
-                format!("[ ERROR ] Unable to open input ({value}), check the path is correct.")
-            }
-            None => "[ ERROR ] Unable to open input, check the path is correct.".to_string(),
-        };
-        return Err(error_message.into());
+    for path in &cli.paths {
+        // Symlinks are resolved, as they are for watching, while messages keep the given path
+        if path.canonicalize().and_then(File::open).is_err() {
+            let error_message = match path.to_str() {
+                Some(value) => {
+                    format!("[ ERROR ] Unable to open input ({value}), check the path is correct.")
+                }
+                None => "[ ERROR ] Unable to open input, check the path is correct.".to_string(),
+            };
+            return Err(error_message.into());
+        }
     }
+    let targets =
+        markwrite::output_paths(&cli.paths, cli.output.as_deref(), cli.output_dir.as_deref())
+            .map_err(|error| format!("[ ERROR ] {error}."))?;
+
+    let mut listing_pages = if cli.index || cli.sitemap {
+        let directory = cli
+            .output_dir
+            .clone()
+            .or_else(|| cli.paths.iter().find(|path| path.is_dir()).cloned())
+            .ok_or("[ ERROR ] --index and --sitemap need a directory input, or --output-dir.")?;
+        Some(ListingPages::new(
+            targets.clone(),
+            directory,
+            cli.index,
+            cli.sitemap,
+        ))
+    } else {
+        None
+    };
 
     let stdout = io::stdout();
     let mut stdout_handle = io::BufWriter::new(stdout);
-    let mut dictionary: HashSet<String> = HashSet::new();
-    markwrite::load_dictionary(
-        ".markwrite/custom.dict",
-        &mut dictionary,
+    let targets = if let Some(cutoff) = cli.since {
+        let (targets, skipped_count) = markwrite::modified_since(targets, cutoff);
+        writeln!(
+            stdout_handle,
+            "[ INFO ] Skipped {skipped_count} input(s) not modified since the --since cutoff."
+        )?;
+        if targets.is_empty() {
+            stdout_handle.flush()?;
+            return Ok(());
+        }
+        targets
+    } else {
+        targets
+    };
+    if cli.list_headings {
+        list_headings(&targets, options, cli.heading_slugs, &mut stdout_handle)?;
+        stdout_handle.flush()?;
+        return Ok(());
+    }
+    let dictionary = markwrite::load_dictionaries(
+        &cli.dictionary,
+        !cli.no_default_dictionary,
         &mut stdout_handle,
     );
+
+    if cli.spelling && options.languagetool_disabled() {
+        writeln!(
+            stdout_handle,
+            "[ INFO ] LanguageTool is disabled, skipping spelling and grammar checks."
+        )?;
+    }
+
+    // One renderer, and its grammar checker, is shared by every update, so it is configured once
+    // and reuses connections
+    let markwrite = markwrite::Markwrite::new(options, dictionary);
+    if markwrite.options().check_only() {
+        let mut summary = markwrite::BatchSummary::default();
+        for (path, output_path) in &targets {
+            let result = markwrite
+                .update_file(path, output_path, &mut stdout_handle)
+                .await;
+            summary.record(&result);
+        }
+        if targets.len() > 1 && !cli.verbose.is_silent() {
+            summary.display(cli.grammar_format, &mut stdout_handle)?;
+        }
+        stdout_handle.flush()?;
+        let failed_count = summary.failed();
+        if failed_count > 0 {
+            return Err(format!("[ ERROR ] Check failed for {failed_count} input(s).").into());
+        }
+        return Ok(());
+    }
+
+    // In strict mode, refuse to start watching documents which already have warnings. Listing
+    // pages only include rendered documents, so they are rendered before the pages are written.
+    if let Some(value) = &mut listing_pages {
+        value.read_unrendered(&targets, markwrite.options());
+    }
+    let show_summary = !cli.verbose.is_silent();
+    if markwrite.options().strict() || listing_pages.is_some() {
+        let mut summary = markwrite::BatchSummary::default();
+        for (path, output_path) in &targets {
+            let result = markwrite
+                .update_file(path, output_path, &mut stdout_handle)
+                .await;
+            if markwrite.options().strict() {
+                if let Err(error) = &result {
+                    let display_path = path.display();
+                    return Err(
+                        format!("[ ERROR ] Unable to update {display_path}: {error}.").into(),
+                    );
+                }
+            }
+            if let Some(value) = &mut listing_pages {
+                value.record(path, &result);
+            }
+            summary.record(&result);
+        }
+        if targets.len() > 1 && show_summary {
+            summary.display(cli.grammar_format, &mut stdout_handle)?;
+        }
+    }
+    if let Some(value) = &listing_pages {
+        value.write(markwrite.options(), &mut stdout_handle)?;
+    }
+
     // Watch for input file modifications and generate HTML when they occur.
     writeln!(stdout_handle, "[ INFO ] waiting for file changes.")?;
     stdout_handle.flush()?;
 
-    debounce_watch(path, output_path, &options, &mut stdout_handle).await;
+    debounce_watch(
+        &targets,
+        &markwrite,
+        listing_pages.as_mut(),
+        show_summary,
+        &mut stdout_handle,
+    )
+    .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{watch_loop, watch_targets, WatchMessage};
+    use notify_debouncer_mini::{new_debouncer, DebouncedEvent, DebouncedEventKind};
+    use std::{collections::HashSet, fs, sync::mpsc, time::Duration};
+
+    #[tokio::test]
+    async fn watch_loop_exits_cleanly_when_shutdown_message_arrives() {
+        // arrange
+        let (tx, rx) = mpsc::channel();
+        tx.send(WatchMessage::Changes(Ok(Vec::new())))
+            .expect("Error sending changes");
+        tx.send(WatchMessage::Shutdown)
+            .expect("Error sending shutdown");
+        let markwrite =
+            markwrite::Markwrite::new(markwrite::MarkwriteOptions::default(), HashSet::new());
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        let result = watch_loop(&rx, &[], &markwrite, None, true, &mut handle).await;
+
+        // assert
+        assert!(result.is_ok());
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert_eq!(output, "[ INFO ] stopped watching for file changes.\n");
+        // The sender is still open, so the loop stopped on the shutdown message
+        drop(tx);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn watch_loop_rebuilds_symlinked_input_when_target_changes() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp dir");
+        let target_path = temp_dir.path().join("target.md");
+        let link_path = temp_dir.path().join("link.md");
+        let other_path = temp_dir.path().join("other.md");
+        fs::write(&target_path, "# Target\n").expect("Error writing target markdown");
+        fs::write(&other_path, "# Other\n").expect("Error writing other markdown");
+        std::os::unix::fs::symlink(&target_path, &link_path).expect("Error creating symlink");
+        let targets = vec![
+            (link_path.clone(), temp_dir.path().join("link.html")),
+            (other_path, temp_dir.path().join("other.html")),
+        ];
+        let (events_tx, events_rx) = mpsc::channel();
+        let mut debouncer = new_debouncer(Duration::from_millis(50), move |result| {
+            let _ = events_tx.send(result);
+        })
+        .expect("Error creating debouncer");
+        watch_targets(debouncer.watcher(), &targets).expect("Error watching targets");
+        let markwrite =
+            markwrite::Markwrite::new(markwrite::MarkwriteOptions::default(), HashSet::new());
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        fs::write(&target_path, "# Target\n\nEdited.\n").expect("Error editing target markdown");
+        let events = events_rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Expected a change event for the symlink target");
+        let (tx, rx) = mpsc::channel();
+        tx.send(WatchMessage::Changes(events))
+            .expect("Error sending changes");
+        tx.send(WatchMessage::Shutdown)
+            .expect("Error sending shutdown");
+        let result = watch_loop(&rx, &targets, &markwrite, None, true, &mut handle).await;
+
+        // assert
+        assert!(result.is_ok());
+        let html = fs::read_to_string(&targets[0].1).expect("Expected rebuilt output");
+        assert!(html.contains("Edited."));
+        assert!(!targets[1].1.exists());
+    }
+
+    #[tokio::test]
+    async fn watch_loop_prints_summary_for_batch_updating_several_inputs() {
+        // arrange
+        let temp_dir = assert_fs::TempDir::new().expect("Error creating temp dir");
+        let first_path = temp_dir.path().join("first.md");
+        let second_path = temp_dir.path().join("second.md");
+        let missing_path = temp_dir.path().join("missing.md");
+        fs::write(&first_path, "# First\n\nOne two three.\n").expect("Error writing markdown");
+        fs::write(&second_path, "# Second\n\nFour five.\n").expect("Error writing markdown");
+        let targets: Vec<_> = [first_path, missing_path, second_path]
+            .into_iter()
+            .map(|path| {
+                let output_path = path.with_extension("html");
+                (path, output_path)
+            })
+            .collect();
+        let events = targets
+            .iter()
+            .map(|(path, _)| DebouncedEvent {
+                path: path.clone(),
+                kind: DebouncedEventKind::Any,
+            })
+            .collect();
+        let (tx, rx) = mpsc::channel();
+        tx.send(WatchMessage::Changes(Ok(events)))
+            .expect("Error sending changes");
+        tx.send(WatchMessage::Shutdown)
+            .expect("Error sending shutdown");
+        let markwrite =
+            markwrite::Markwrite::new(markwrite::MarkwriteOptions::default(), HashSet::new());
+        let mut handle: Vec<u8> = Vec::new();
+
+        // act
+        let result = watch_loop(&rx, &targets, &markwrite, None, true, &mut handle).await;
+
+        // assert
+        assert!(result.is_ok());
+        let output = String::from_utf8(handle).expect("Expected UTF-8 output");
+        assert!(output.contains(
+            "[ INFO ] Summary: 2 succeeded (2 rendered, 0 checked), 0 skipped, 1 failed;"
+        ));
+    }
+}
@@ -4,19 +4,63 @@ mod tests;
 use crate::{
     inline_html::{parse_node as parse_inline_html_node, InlineHTMLTagType},
     url_utility::relative_url,
-    utilities::stack::Stack,
+    utilities::{sentences::ends_with_abbreviation, slug::slugify, stack::Stack},
 };
 
-use deunicode::deunicode;
 use pulldown_cmark::{
-    html, CowStr,
-    Event::{self, Code, End, InlineHtml, SoftBreak, Start, Text},
-    Options, Parser, Tag, TagEnd,
+    html, BrokenLink, CodeBlockKind, CowStr,
+    Event::{self, Code, End, Html, InlineHtml, SoftBreak, Start, Text},
+    LinkType, Options, Parser, Tag, TagEnd, TextMergeStream,
+};
+use pulldown_cmark_escape::{escape_html, StrWrite};
+use std::{
+    collections::HashMap,
+    io::{self, Cursor},
+    iter, mem,
+    ops::Range,
 };
-use pulldown_cmark_escape::StrWrite;
-use std::io::{self, Cursor};
 use textwrap::wrap;
 
+/// Marks the start of a region excluded from grammar checking
+const GRAMMAR_IGNORE_START: &str = "<!-- markwrite:grammar-ignore-start -->";
+
+/// Marks the end of a region excluded from grammar checking
+const GRAMMAR_IGNORE_END: &str = "<!-- markwrite:grammar-ignore-end -->";
+
+fn is_grammar_ignore_start(event: &Event) -> bool {
+    match event {
+        Html(html) | InlineHtml(html) => html.trim() == GRAMMAR_IGNORE_START,
+        _ => false,
+    }
+}
+
+fn is_grammar_ignore_end(event: &Event) -> bool {
+    match event {
+        Html(html) | InlineHtml(html) => html.trim() == GRAMMAR_IGNORE_END,
+        _ => false,
+    }
+}
+
+/// Starts a region checked in another language, for example `<!-- markwrite:lang=fr -->`, with
+/// `lang=default` switching back to the default language
+const LANGUAGE_MARKER_PREFIX: &str = "markwrite:lang=";
+
+/// Returns the language a marker comment switches to, which is `default` or empty to switch back
+/// to the default language, or `None` when the event is not a language marker
+fn language_marker<'a>(event: &'a Event) -> Option<&'a str> {
+    let (Html(html) | InlineHtml(html)) = event else {
+        return None;
+    };
+    Some(
+        html.trim()
+            .strip_prefix("<!--")?
+            .strip_suffix("-->")?
+            .trim()
+            .strip_prefix(LANGUAGE_MARKER_PREFIX)?
+            .trim(),
+    )
+}
+
 /// Reading time in minutes from number of words, assumes 180 wpm reading speed from a device
 fn reading_time_from_words(words: u32) -> u32 {
     let result = (f64::from(words) / 180.0).round();
@@ -29,144 +73,747 @@ fn reading_time_from_words(words: u32) -> u32 {
     }
 }
 
-/// Emoji are not included in word count and hyphenated, compound words (half-time) are one word
+/// Chinese and Japanese scripts do not separate words with spaces, so each of these characters
+/// counts as a word.  Korean Hangul is space-separated and counted like Latin text.
+fn is_cjk_character(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30ff}' // Hiragana and Katakana
+            | '\u{31f0}'..='\u{31ff}' // Katakana phonetic extensions
+            | '\u{3400}'..='\u{4dbf}' // CJK unified ideographs extension A
+            | '\u{4e00}'..='\u{9fff}' // CJK unified ideographs
+            | '\u{f900}'..='\u{faff}' // CJK compatibility ideographs
+            | '\u{ff66}'..='\u{ff9f}' // Halfwidth Katakana
+            | '\u{20000}'..='\u{2ebef}' // CJK unified ideographs extensions B to F
+    )
+}
+
+/// Emoji are not included in word count and hyphenated, compound words (half-time) are one word,
+/// whichever hyphen character joins them.  En and em dashes separate words, and each Chinese or
+/// Japanese character is a word.
 fn words(text: &str) -> u32 {
-    text.split(|c| char::is_whitespace(c) || c == '/')
+    text.split(|c| char::is_whitespace(c) || matches!(c, '/' | '\u{2013}' | '\u{2014}'))
         .fold(0, |acc, x| {
+            let cjk_words: u32 = x
+                .chars()
+                .filter(|c| is_cjk_character(*c))
+                .count()
+                .try_into()
+                .unwrap_or(u32::MAX);
             // only count as a word if there is at least one alphanumeric character or is &
-            if x.contains(char::is_alphanumeric) || x == "&" {
-                acc + 1
-            } else {
-                acc
-            }
+            let other_words: u32 = x.split(is_cjk_character).fold(0, |segment_acc, segment| {
+                if segment.contains(char::is_alphanumeric) || segment == "&" {
+                    segment_acc + 1
+                } else {
+                    segment_acc
+                }
+            });
+            acc + cjk_words + other_words
         })
 }
 
-fn slugified_title(title: &str) -> String {
-    let deunicoded_title = deunicode(title);
-    let mut result = String::with_capacity(deunicoded_title.len());
-    let mut last_was_replaced = true;
-    let remove_characters = "?'`:[]()!";
-    let replace_characters = " -/.,";
-    for chars in deunicoded_title.chars() {
-        if replace_characters.contains(chars) {
-            if !last_was_replaced {
-                last_was_replaced = true;
-                result.push('-');
+/// Failure rendering markdown, located by the byte offset, within the parsed markdown, of the
+/// construct which caused it
+#[derive(Debug, Eq, PartialEq)]
+pub struct ParseError {
+    message: String,
+    offset: usize,
+}
+
+impl ParseError {
+    fn new(message: &str, offset: usize) -> Self {
+        ParseError {
+            message: message.to_string(),
+            offset,
+        }
+    }
+
+    /// One-based line and column, counted in characters, of the error within `markdown`, which
+    /// should be the markdown that was parsed
+    #[must_use]
+    pub fn line_column(&self, markdown: &str) -> (usize, usize) {
+        let mut offset = self.offset.min(markdown.len());
+        while !markdown.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let preceding = &markdown[..offset];
+        let line_start = preceding.rfind('\n').map_or(0, |index| index + 1);
+        (
+            preceding.matches('\n').count() + 1,
+            preceding[line_start..].chars().count() + 1,
+        )
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Collects the word count of each sentence in prose, along with the byte offset of the text
+/// event it starts in
+#[derive(Default)]
+struct SentenceLengths {
+    current_start: Option<usize>,
+    current_words: u32,
+    lengths: Vec<(usize, u32)>,
+}
+
+impl SentenceLengths {
+    fn add_words(&mut self, text: &str, offset: usize) {
+        let count = words(text);
+        if count > 0 {
+            self.current_start.get_or_insert(offset);
+            self.current_words += count;
+        }
+    }
+
+    /// Adds text from an event starting at `offset`, ending a sentence after each full stop,
+    /// question mark or exclamation mark followed by whitespace or the end of the text, unless the
+    /// full stop ends an abbreviation
+    fn push_text(&mut self, text: &str, offset: usize) {
+        let mut sentence_start = 0;
+        let mut characters = text.char_indices().peekable();
+        while let Some((index, character)) = characters.next() {
+            if matches!(character, '.' | '!' | '?')
+                && characters
+                    .peek()
+                    .map_or(true, |(_, next)| next.is_whitespace())
+                && !(character == '.' && ends_with_abbreviation(&text[..=index]))
+            {
+                self.add_words(&text[sentence_start..=index], offset);
+                self.end_sentence();
+                sentence_start = index + 1;
             }
-        } else if !remove_characters.contains(chars) {
-            last_was_replaced = false;
-            result.push_str(&chars.to_lowercase().to_string());
         }
+        self.add_words(&text[sentence_start..], offset);
+    }
+
+    fn end_sentence(&mut self) {
+        if let Some(start) = self.current_start.take() {
+            self.lengths.push((start, self.current_words));
+        }
+        self.current_words = 0;
     }
-    result
+}
+
+/// Byte offset and word count of each sentence in the paragraphs, list items and table cells of
+/// `markdown`. Headings and code are skipped and the end of a block always ends a sentence.
+fn sentence_lengths(markdown: &str, options: Options) -> Vec<(usize, u32)> {
+    let mut sentences = SentenceLengths::default();
+    let mut skip_depth: u32 = 0;
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        match event {
+            Start(Tag::CodeBlock(_) | Tag::Heading { .. }) => skip_depth += 1,
+            End(TagEnd::CodeBlock | TagEnd::Heading(_)) => skip_depth -= 1,
+            Text(value) if skip_depth == 0 => sentences.push_text(&value, range.start),
+            End(TagEnd::Paragraph | TagEnd::Item | TagEnd::TableCell) => sentences.end_sentence(),
+            _ => {}
+        }
+    }
+    sentences.end_sentence();
+    sentences.lengths
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct TextStatistics {
     reading_time: u32,
     word_count: u32,
+
+    /// Byte offset and word count of each sentence
+    sentence_lengths: Vec<(usize, u32)>,
 }
 
 impl TextStatistics {
-    #[allow(dead_code)]
     pub fn new(word_count: u32) -> TextStatistics {
         let reading_time = reading_time_from_words(word_count);
         TextStatistics {
             reading_time,
             word_count,
+            sentence_lengths: Vec::new(),
         }
     }
 
+    /// Mean number of words in each sentence, or zero for text with no sentences
+    pub fn average_sentence_length(&self) -> f64 {
+        if self.sentence_lengths.is_empty() {
+            return 0.0;
+        }
+        let total_words: u32 = self.sentence_lengths.iter().map(|(_, words)| words).sum();
+        let sentence_count = u32::try_from(self.sentence_lengths.len()).unwrap_or(u32::MAX);
+        f64::from(total_words) / f64::from(sentence_count)
+    }
+
+    /// Number of words in the longest sentence, or zero for text with no sentences
+    pub fn longest_sentence_words(&self) -> u32 {
+        self.sentence_lengths
+            .iter()
+            .map(|(_, words)| *words)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Byte offset, in the markdown, and word count of each sentence with more than `max_words`
+    /// words
+    pub fn long_sentences(&self, max_words: u32) -> impl Iterator<Item = (usize, u32)> + '_ {
+        self.sentence_lengths
+            .iter()
+            .copied()
+            .filter(move |(_, words)| *words > max_words)
+    }
+
     pub fn word_count(&self) -> u32 {
         self.word_count
     }
+
+    /// Estimated reading time, in minutes
+    pub fn reading_time(&self) -> u32 {
+        self.reading_time
+    }
+}
+
+/// Byte range of some plaintext, paired with the byte range of the markdown it came from
+type SourceSpan = (Range<usize>, Range<usize>);
+
+/// Plain text for a region of a document, with the grammar check language set for the region by a
+/// language marker, if any
+#[derive(Debug, Eq, PartialEq)]
+pub struct LanguageRegion {
+    language: Option<String>,
+    text: String,
+
+    /// Whether the text is image alt text, collected from the region, with one image per paragraph
+    alt_text: bool,
+
+    /// Spans of `text` which came from markdown text, in order, with their markdown ranges
+    source_spans: Vec<SourceSpan>,
+}
+
+impl LanguageRegion {
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    pub fn is_alt_text(&self) -> bool {
+        self.alt_text
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Byte range in the markdown of `range`, a byte range of the region text. An end in text with
+    /// no source of its own, like a list item marker, moves to the nearest markdown text inside
+    /// `range`, and an end in text which differs from its source, like an HTML entity, moves out to
+    /// cover the whole source.
+    pub fn source_range(&self, range: Range<usize>) -> Option<Range<usize>> {
+        let start = self
+            .source_spans
+            .iter()
+            .find(|(text, _)| range.start < text.end)
+            .map(|(text, source)| {
+                if text.len() == source.len() && range.start >= text.start {
+                    source.start + range.start - text.start
+                } else {
+                    source.start
+                }
+            })?;
+        let end = self
+            .source_spans
+            .iter()
+            .rev()
+            .find(|(text, _)| text.start < range.end)
+            .map(|(text, source)| {
+                if text.len() == source.len() && range.end <= text.end {
+                    source.start + range.end - text.start
+                } else {
+                    source.end
+                }
+            })?;
+        (start <= end).then_some(start..end)
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Heading {
-    heading: String,
+    text: String,
     id: String,
+
+    /// Heading level, 1 for `h1` through to 6 for `h6`
+    level: u8,
+
+    /// Byte offset of the start of the heading in the markdown source
+    source_offset: usize,
+
+    /// Hierarchical section number, such as `1.2.1`, when heading numbering is enabled
+    number: Option<String>,
 }
 
 impl Heading {
-    pub fn new(heading: &str, id: &str) -> Heading {
+    #[must_use]
+    pub fn new(heading: &str, id: &str, level: u8, source_offset: usize) -> Heading {
         Heading {
-            heading: heading.into(),
+            text: heading.into(),
             id: id.into(),
+            level,
+            source_offset,
+            number: None,
         }
     }
 
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    #[must_use]
     pub fn id(&self) -> &str {
         &self.id
     }
+
+    #[must_use]
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Slug used as the heading `id` attribute in the output HTML, so `#{slug}` links to it
+    #[must_use]
+    pub fn slug(&self) -> &str {
+        &self.id
+    }
+
+    #[must_use]
+    pub fn source_offset(&self) -> usize {
+        self.source_offset
+    }
+
+    #[must_use]
+    pub fn number(&self) -> Option<&str> {
+        self.number.as_deref()
+    }
 }
 
-pub fn parse_markdown_to_html(
-    markdown: &str,
-) -> io::Result<(String, Vec<Heading>, TextStatistics)> {
-    let mut bytes = Vec::new();
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_SMART_PUNCTUATION);
+/// Gives each heading a hierarchical section number, such as `1.2.1`, from its position in the
+/// heading structure. A skipped level nests directly under the closest shallower heading, so
+/// `h1` followed by `h3` numbers `1` and `1.1`.
+fn number_headings(headings: &mut [Heading]) {
+    // Level and count of the current heading at each depth of nesting
+    let mut counters: Vec<(u8, u32)> = Vec::new();
+    for heading in headings {
+        let mut previous_count = 0;
+        while let Some(&(level, count)) = counters.last() {
+            if level <= heading.level {
+                break;
+            }
+            previous_count = count;
+            counters.pop();
+        }
+        match counters.last_mut() {
+            Some((level, count)) if *level == heading.level => *count += 1,
+            // A heading closing a deeper, skipped level continues on from its sibling
+            _ => counters.push((heading.level, previous_count + 1)),
+        }
+        heading.number = Some(
+            counters
+                .iter()
+                .map(|(_, count)| count.to_string())
+                .collect::<Vec<String>>()
+                .join("."),
+        );
+    }
+}
+
+/// Smart typography conversions, which can be enabled independently of each other
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SmartPunctuation {
+    quotes: bool,
+    dashes: bool,
+    ellipses: bool,
+}
+
+impl Default for SmartPunctuation {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl SmartPunctuation {
+    /// Curly quotes, en and em dashes and ellipses all enabled
+    #[must_use]
+    pub fn all() -> Self {
+        SmartPunctuation {
+            quotes: true,
+            dashes: true,
+            ellipses: true,
+        }
+    }
+
+    #[must_use]
+    pub fn none() -> Self {
+        SmartPunctuation {
+            quotes: false,
+            dashes: false,
+            ellipses: false,
+        }
+    }
+
+    /// Convert straight quotes to curly quotes
+    #[must_use]
+    pub fn quotes(mut self, value: bool) -> Self {
+        self.quotes = value;
+        self
+    }
+
+    /// Convert `--` to an en dash and `---` to an em dash
+    #[must_use]
+    pub fn dashes(mut self, value: bool) -> Self {
+        self.dashes = value;
+        self
+    }
+
+    /// Convert `...` to an ellipsis
+    #[must_use]
+    pub fn ellipses(mut self, value: bool) -> Self {
+        self.ellipses = value;
+        self
+    }
+
+    fn any(self) -> bool {
+        self.quotes || self.dashes || self.ellipses
+    }
+
+    fn parser_options(self) -> Options {
+        let mut options = Options::empty();
+        if self.any() {
+            options.insert(Options::ENABLE_SMART_PUNCTUATION);
+        }
+        options
+    }
 
+    /*
+     * The parser converts all smart punctuation or none of it, so disabled conversions are undone
+     * here.  The parser emits each converted character as its own text event, and comparing with
+     * the source distinguishes these from literal typographic characters and HTML entities.
+     */
+    fn revert_disabled<'a>(
+        self,
+        event: Event<'a>,
+        range: Range<usize>,
+        markdown: &'a str,
+    ) -> Event<'a> {
+        let Text(ref text) = event else {
+            return event;
+        };
+        let source = &markdown[range];
+        if source == &text[..] {
+            return event;
+        }
+        let enabled = if source.chars().all(|c| c == '\'' || c == '"')
+            && text.chars().all(|c| "‘’“”".contains(c))
+        {
+            self.quotes
+        } else if source.chars().all(|c| c == '-') && text.chars().all(|c| "–—".contains(c)) {
+            self.dashes
+        } else if source == "..." && &text[..] == "…" {
+            self.ellipses
+        } else {
+            true
+        };
+        if enabled {
+            event
+        } else {
+            Text(CowStr::Borrowed(source))
+        }
+    }
+}
+
+/// Replaces GitHub-style emoji shortcodes in `text`, such as `:smile:`, with the emoji they name.
+/// Colon-delimited words which are not known shortcodes, like times or ratios, are left as they
+/// are. Returns `None` when there is nothing to replace.
+fn expand_emoji_shortcodes(text: &str) -> Option<String> {
+    if !text.contains(':') {
+        return None;
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut replaced = false;
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        let after_colon = &rest[start + 1..];
+        let name_length = after_colon
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "_+-".contains(c)))
+            .unwrap_or(after_colon.len());
+        let emoji = Some(&after_colon[..name_length])
+            .filter(|name| !name.is_empty() && after_colon[name_length..].starts_with(':'))
+            .and_then(emojis::get_by_shortcode);
+        if let Some(value) = emoji {
+            result.push_str(&rest[..start]);
+            result.push_str(value.as_str());
+            rest = &after_colon[name_length + 1..];
+            replaced = true;
+        } else {
+            result.push_str(&rest[..=start]);
+            rest = after_colon;
+        }
+    }
+    result.push_str(rest);
+    replaced.then_some(result)
+}
+
+/// Renders Pandoc-style subscript and superscript spans in `text`, such as `H~2~O` and `x^2^`, as
+/// HTML, escaping the text around them. Spans are not empty and contain no whitespace, so a lone
+/// `~` or `^` in prose is left as it is. Doubled delimiters, as in `~~strikethrough~~`, and
+/// footnote-like `^[...]` spans are also left alone. Returns `None` when there is nothing to
+/// replace.
+fn render_subscript_superscript(text: &str) -> Option<String> {
+    if !text.contains(['~', '^']) {
+        return None;
+    }
+    let bytes = text.as_bytes();
+    let mut result = String::with_capacity(text.len() + 11);
+    let mut copied = 0;
+    let mut index = 0;
+    while let Some(offset) = text[index..].find(['~', '^']) {
+        let start = index + offset;
+        let delimiter = bytes[start];
+        let is_delimiter = |position: usize| bytes.get(position) == Some(&delimiter);
+        let end = text[start + 1..]
+            .find(char::from(delimiter))
+            .map(|length| start + 1 + length)
+            .filter(|end| {
+                let content = &text[start + 1..*end];
+                let doubled = (start > 0 && is_delimiter(start - 1)) || is_delimiter(end + 1);
+                !(doubled
+                    || content.is_empty()
+                    || content.contains(char::is_whitespace)
+                    || content.starts_with('['))
+            });
+        let Some(end) = end else {
+            index = start + 1;
+            continue;
+        };
+        let (open_tag, close_tag) = if delimiter == b'~' {
+            ("<sub>", "</sub>")
+        } else {
+            ("<sup>", "</sup>")
+        };
+        escape_html(&mut result, &text[copied..start])
+            .expect("Writing to a string shouldn't fail (expect on OOM)");
+        result.push_str(open_tag);
+        escape_html(&mut result, &text[start + 1..end])
+            .expect("Writing to a string shouldn't fail (expect on OOM)");
+        result.push_str(close_tag);
+        copied = end + 1;
+        index = end + 1;
+    }
+    if copied == 0 {
+        return None;
+    }
+    escape_html(&mut result, &text[copied..])
+        .expect("Writing to a string shouldn't fail (expect on OOM)");
+    Some(result)
+}
+
+/// Prose text event with any enabled emoji shortcode expansion and subscript and superscript
+/// rendering applied, or `None` when `text` is unchanged
+fn prose_text_event<'a>(text: &str, markdown_options: &ParseMarkdownOptions) -> Option<Event<'a>> {
+    let expanded = markdown_options
+        .emoji_shortcodes
+        .then(|| expand_emoji_shortcodes(text))
+        .flatten();
+    let text = expanded.as_deref().unwrap_or(text);
+    if let Some(value) = markdown_options
+        .subscript_superscript
+        .then(|| render_subscript_superscript(text))
+        .flatten()
+    {
+        return Some(InlineHtml(CowStr::from(value)));
+    }
+    expanded.map(|value| Text(CowStr::from(value)))
+}
+
+/// Checks each grammar ignore start marker in `markdown` is closed by an end marker, and each end
+/// marker closes an open region
+fn check_grammar_ignore_markers(markdown: &str, options: Options) -> Result<(), ParseError> {
+    let mut start_offset: Option<usize> = None;
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        if is_grammar_ignore_start(&event) {
+            start_offset.get_or_insert(range.start);
+        } else if is_grammar_ignore_end(&event) && start_offset.take().is_none() {
+            return Err(ParseError::new(
+                "grammar ignore end marker without a matching start marker",
+                range.start,
+            ));
+        }
+    }
+    match start_offset {
+        Some(offset) => Err(ParseError::new(
+            "grammar ignore start marker without a matching end marker",
+            offset,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Headings in `markdown`, with their ids, along with its word count
+fn headings_and_word_count(
+    markdown: &str,
+    smart_punctuation: SmartPunctuation,
+) -> (Vec<Heading>, u32) {
+    let options = smart_punctuation.parser_options();
     let mut headings: Vec<Heading> = Vec::new();
     let mut current_id_fragments = String::new();
+    let mut current_heading_level: u8 = 1;
+    let mut current_heading_offset: usize = 0;
     let mut parsing_heading = false;
     let mut word_count: u32 = 0;
 
-    let heading_parser = Parser::new_ext(markdown, options).inspect(|event| match event {
-        Event::Start(Tag::Heading { .. }) => {
-            parsing_heading = true;
-        }
-        Event::Text(value) => {
-            word_count += words(value);
-            if parsing_heading {
-                current_id_fragments.push_str(value);
+    let heading_parser = Parser::new_ext(markdown, options)
+        .into_offset_iter()
+        .inspect(|(event, range)| match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                parsing_heading = true;
+                current_heading_level = *level as u8;
+                current_heading_offset = range.start;
             }
-        }
-        Event::Code(value) => {
-            if parsing_heading {
+            Event::Text(value) => {
+                word_count += words(value);
+                if parsing_heading {
+                    current_id_fragments.push_str(value);
+                }
+            }
+            Event::Code(value) if parsing_heading => {
                 current_id_fragments.push_str(value);
             }
-        }
-        Event::End(TagEnd::Heading(_heading_level)) => {
-            let heading = &current_id_fragments;
-            let id = slugified_title(&current_id_fragments);
-            headings.push(Heading::new(heading, &id));
-            current_id_fragments = String::new();
-            parsing_heading = false;
-        }
-        _ => {}
-    });
-    html::write_html(Cursor::new(&mut bytes), heading_parser)?;
+            Event::End(TagEnd::Heading(_heading_level)) => {
+                let heading = &current_id_fragments;
+                let id = slugify(&current_id_fragments);
+                headings.push(Heading::new(
+                    heading,
+                    &id,
+                    current_heading_level,
+                    current_heading_offset,
+                ));
+                current_id_fragments = String::new();
+                parsing_heading = false;
+            }
+            _ => {}
+        })
+        .map(|(event, range)| smart_punctuation.revert_disabled(event, range, markdown));
+    html::write_html(io::sink(), heading_parser).expect("Writing to a sink shouldn't fail");
+    (headings, word_count)
+}
+
+/// Renders `markdown` to HTML, also returning its headings and text statistics
+///
+/// # Errors
+/// Errors when a grammar ignore start marker has no matching end marker, or the reverse
+pub fn parse_markdown_to_html(
+    markdown: &str,
+    markdown_options: &ParseMarkdownOptions,
+) -> Result<(String, Vec<Heading>, TextStatistics), ParseError> {
+    let mut bytes = Vec::new();
+    let smart_punctuation = markdown_options.smart_punctuation;
+    let options = smart_punctuation.parser_options();
+    check_grammar_ignore_markers(markdown, options)?;
+
+    let (mut headings, word_count) = headings_and_word_count(markdown, smart_punctuation);
+    if markdown_options.number_headings {
+        number_headings(&mut headings);
+    }
     let reading_time = reading_time_from_words(word_count);
     let statistics = TextStatistics {
         reading_time,
         word_count,
+        sentence_lengths: sentence_lengths(markdown, options),
     };
 
+    // Headings deeper than the limit render without an id, so are not returned for linking to
+    let max_heading_id_level = markdown_options.max_heading_id_level.unwrap_or(6);
     let mut heading_iterator = headings.iter();
-    let parser = Parser::new_ext(markdown, options).map(|event| match &event {
+    let mut in_code_block = false;
+    // Image alt text is written as plain text, so is left unchanged
+    let mut in_image = false;
+    // Adjacent text is merged so a shortcode or subscript is never split across events
+    let parser = TextMergeStream::new(
+        Parser::new_ext(markdown, options)
+            .into_offset_iter()
+            .map(|(event, range)| smart_punctuation.revert_disabled(event, range, markdown)),
+    )
+    .filter(|event| {
+        !is_grammar_ignore_start(event)
+            && !is_grammar_ignore_end(event)
+            && language_marker(event).is_none()
+    })
+    .flat_map(|event| match &event {
         Event::Start(Tag::Heading { level, .. }) => {
-            let heading_identifier = heading_iterator.next();
-            Event::Start(Tag::Heading {
+            let heading = heading_iterator.next();
+            let heading_identifier =
+                heading.filter(|heading| heading.level() <= max_heading_id_level);
+            let number = heading.and_then(Heading::number).map(|value| {
+                Event::InlineHtml(CowStr::from(format!(
+                    "<span class=\"heading-number\">{value}</span> "
+                )))
+            });
+            iter::once(Event::Start(Tag::Heading {
                 level: *level,
                 id: heading_identifier.map(|x| CowStr::from(x.id())),
                 classes: Vec::new(),
                 attrs: Vec::new(),
-            })
+            }))
+            .chain(number)
+        }
+        _ => iter::once(event).chain(None),
+    })
+    .map(|event| match event {
+        Start(Tag::CodeBlock(_)) => {
+            in_code_block = true;
+            event
+        }
+        End(TagEnd::CodeBlock) => {
+            in_code_block = false;
+            event
+        }
+        Start(Tag::Image { .. }) => {
+            in_image = true;
+            event
+        }
+        End(TagEnd::Image) => {
+            in_image = false;
+            event
+        }
+        SoftBreak if markdown_options.hard_line_breaks => Event::HardBreak,
+        Text(ref text) if !in_code_block && !in_image => {
+            prose_text_event(text, markdown_options).unwrap_or(event)
         }
         _ => event,
     });
 
-    match html::write_html(Cursor::new(&mut bytes), parser) {
-        Ok(()) => Ok((
-            String::from_utf8_lossy(&bytes).to_string(),
-            headings,
-            statistics,
-        )),
-        Err(error) => Err(error),
-    }
+    html::write_html(Cursor::new(&mut bytes), parser)
+        .expect("Writing to a string shouldn't fail (expect on OOM)");
+    Ok((
+        String::from_utf8_lossy(&bytes).to_string(),
+        headings
+            .into_iter()
+            .filter(|heading| heading.level() <= max_heading_id_level)
+            .collect(),
+        statistics,
+    ))
+}
+
+/// How links are written in plaintext output
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LinkRendering {
+    /// Link text alone, dropping the URL
+    TextOnly,
+
+    /// URL alone, in place of the link text
+    #[allow(dead_code)]
+    UrlOnly,
+
+    /// Link text followed by the URL in parentheses
+    #[default]
+    TextAndUrl,
 }
 
 struct PlaintextWriter<'a, I, W> {
@@ -184,6 +831,8 @@ struct PlaintextWriter<'a, I, W> {
 
     current_link: Option<String>,
 
+    link_rendering: LinkRendering,
+
     /// Preferred length of wrapped out lines, currently fixed at 72
     line_length: usize,
 
@@ -193,19 +842,29 @@ struct PlaintextWriter<'a, I, W> {
     /// Optionally prepended to relative URLs
     canonical_root_url: Option<&'a str>,
 
-    /// Optionally prepended to relative URLs
+    /// Leave code blocks out of the output
     skip_code_blocks: bool,
+
+    /// Bytes written so far
+    written: usize,
+
+    /// Spans of the current line which came from markdown text, with their markdown ranges
+    line_spans: Vec<SourceSpan>,
+
+    /// Spans of the output which came from markdown text, with their markdown ranges
+    source_spans: Vec<SourceSpan>,
 }
 
 impl<'a, I, W> PlaintextWriter<'a, I, W>
 where
-    I: Iterator<Item = Event<'a>>,
+    I: Iterator<Item = (Event<'a>, Range<usize>)>,
     W: StrWrite,
 {
     fn new(
         iter: I,
         writer: W,
         canonical_root_url: Option<&'a str>,
+        link_rendering: LinkRendering,
         skip_code_blocks: bool,
     ) -> Self {
         Self {
@@ -214,48 +873,86 @@ where
             end_newline: true,
             current_line: String::new(),
             current_link: None,
+            link_rendering,
             line_length: 72,
             ignore_tags: vec!["tool-tip"],
             canonical_root_url,
             skip_code_blocks,
+            written: 0,
+            line_spans: Vec::new(),
+            source_spans: Vec::new(),
         }
     }
 
     /// Writes a new line.
     fn write_newline(&mut self) -> io::Result<()> {
         self.end_newline = true;
+        self.written += 1;
         self.writer.write_str("\n")
     }
 
     /// Wraps the current line on input to preferred length and writes the wrapped lines
     #[inline]
     fn write(&mut self) -> io::Result<()> {
-        let lines = wrap(&self.current_line, self.line_length);
-        for line in &lines {
-            self.writer.write_str(line)?;
+        let current_line = mem::take(&mut self.current_line);
+        let line_spans = mem::take(&mut self.line_spans);
+        let mut line_start = 0;
+        for line in wrap(&current_line, self.line_length) {
+            // Wrapping only drops whitespace where lines break, so each line follows the last
+            if let Some(index) = current_line[line_start..].find(&*line) {
+                line_start += index;
+                self.add_source_spans(&line_spans, line_start..line_start + line.len());
+            }
+            self.writer.write_str(&line)?;
             self.writer.write_str("\n")?;
+            self.written += line.len() + 1;
         }
 
-        self.current_line = String::new();
         if !self.current_line.is_empty() {
             self.end_newline = self.current_line.ends_with('\n');
         }
         Ok(())
     }
 
-    fn run(mut self) -> io::Result<()> {
-        while let Some(event) = self.iter.next() {
+    /// Adds the parts of `line_spans` inside `line`, a range of the current line about to be
+    /// written, to the output source spans. Text which differs from its source keeps the whole
+    /// source range.
+    fn add_source_spans(&mut self, line_spans: &[SourceSpan], line: Range<usize>) {
+        for (text, source) in line_spans {
+            let start = text.start.max(line.start);
+            let end = text.end.min(line.end);
+            if start >= end {
+                continue;
+            }
+            let source = if text.len() == source.len() {
+                source.start + start - text.start..source.start + end - text.start
+            } else {
+                source.clone()
+            };
+            let output_start = self.written + start - line.start;
+            self.source_spans
+                .push((output_start..output_start + end - start, source));
+        }
+    }
+
+    /// Writes the plaintext, returning spans of it which came from markdown text
+    fn run(mut self) -> io::Result<Vec<SourceSpan>> {
+        while let Some((event, range)) = self.iter.next() {
+            if is_grammar_ignore_start(&event) {
+                for (ignored_event, _) in self.iter.by_ref() {
+                    if is_grammar_ignore_end(&ignored_event) {
+                        break;
+                    }
+                }
+                continue;
+            }
             match event {
                 Start(tag) => match tag {
-                    Tag::CodeBlock(_) => {
-                        if self.skip_code_blocks {
-                            for html_event in self.iter.by_ref() {
-                                if let End(TagEnd::CodeBlock) = html_event {
-                                    break;
-                                }
+                    Tag::CodeBlock(_) if self.skip_code_blocks => {
+                        for (html_event, _) in self.iter.by_ref() {
+                            if let End(TagEnd::CodeBlock) = html_event {
+                                break;
                             }
-                        } else {
-                            self.start_tag(tag)?;
                         }
                     }
                     _ => {
@@ -265,8 +962,14 @@ where
                 End(tag) => {
                     self.end_tag(tag)?;
                 }
+                Text(_) | Code(_)
+                    if self.current_link.is_some()
+                        && self.link_rendering == LinkRendering::UrlOnly => {}
                 Text(text) | Code(text) => {
+                    let start = self.current_line.len();
                     self.current_line.push_str(&text);
+                    self.line_spans
+                        .push((start..self.current_line.len(), range));
                     self.end_newline = text.ends_with('\n');
                 }
                 InlineHtml(inline_html) => {
@@ -276,7 +979,7 @@ where
                         if self.ignore_tags.contains(&value.as_ref()) {
                             let mut open_tags: Stack<String> = Stack::new();
                             open_tags.push(value);
-                            for html_event in self.iter.by_ref() {
+                            for (html_event, _) in self.iter.by_ref() {
                                 if let InlineHtml(nested_inline_html) = html_event {
                                     match parse_inline_html_node(&nested_inline_html) {
                                         Some(InlineHTMLTagType::Opening(open_tag_value)) => {
@@ -304,7 +1007,7 @@ where
                 _ => {}
             }
         }
-        Ok(())
+        Ok(self.source_spans)
     }
 
     /// Handles the start of an HTML tag.
@@ -344,25 +1047,26 @@ where
 
     fn end_tag(&mut self, tag: TagEnd) -> io::Result<()> {
         match tag {
-            TagEnd::Paragraph => {
-                self.write()?;
-            }
-            TagEnd::Heading(_level) => {
-                self.write()?;
-            }
-            TagEnd::Item => {
+            TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item => {
                 self.write()?;
             }
             TagEnd::Link => {
-                if let Some(value) = &self.current_link {
-                    self.current_line.push_str(" (");
+                if let Some(value) = self.current_link.take() {
+                    if self.link_rendering == LinkRendering::TextOnly {
+                        return Ok(());
+                    }
+                    if self.link_rendering == LinkRendering::TextAndUrl {
+                        self.current_line.push_str(" (");
+                    }
                     if let Some(root_url_value) = self.canonical_root_url {
-                        if relative_url(value) {
+                        if relative_url(&value) {
                             self.current_line.push_str(root_url_value);
                         }
                     }
-                    self.current_line.push_str(value);
-                    self.current_line.push(')');
+                    self.current_line.push_str(&value);
+                    if self.link_rendering == LinkRendering::TextAndUrl {
+                        self.current_line.push(')');
+                    }
                 }
             }
             _ => {}
@@ -371,51 +1075,165 @@ where
     }
 }
 
-#[allow(unused)]
+/// Writes plaintext for the markdown `iter` events, with their source ranges, to `s`, returning
+/// spans of the plaintext which came from markdown text
 fn push_plaintext<'a, I>(
     s: &mut String,
     iter: I,
     canonical_root_url: Option<&'a str>,
+    link_rendering: LinkRendering,
     skip_code_blocks: bool,
-) where
-    I: Iterator<Item = Event<'a>>,
+) -> Vec<SourceSpan>
+where
+    I: Iterator<Item = (Event<'a>, Range<usize>)>,
 {
-    PlaintextWriter::new(iter, s, canonical_root_url, skip_code_blocks)
-        .run()
-        .unwrap();
+    PlaintextWriter::new(
+        iter,
+        s,
+        canonical_root_url,
+        link_rendering,
+        skip_code_blocks,
+    )
+    .run()
+    .unwrap()
+}
+
+/// Kind of markdown block, for choosing which text goes into plaintext output. Text belongs to
+/// the nearest enclosing list item, block quote, table or heading, so a paragraph in a list item
+/// is list item text, and only other paragraphs are paragraph text.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum BlockKind {
+    Paragraph,
+    Heading,
+    ListItem,
+    BlockQuote,
+    Table,
 }
 
-#[derive(Debug)]
+/// Drops blocks whose kind is not one of `block_kinds`, along with everything in them, from
+/// `events`. Every block is kept when `block_kinds` is `None`.
+fn keep_block_kinds<'a, 'b>(
+    events: impl Iterator<Item = (Event<'a>, Range<usize>)> + 'b,
+    block_kinds: Option<&'b [BlockKind]>,
+) -> impl Iterator<Item = (Event<'a>, Range<usize>)> + 'b {
+    // Depth of nested tags within a dropped block, and number of kept blocks enclosing the event
+    let mut skip_depth: usize = 0;
+    let mut container_depth: usize = 0;
+    events.filter(move |(event, _)| {
+        let Some(block_kinds) = block_kinds else {
+            return true;
+        };
+        if skip_depth > 0 {
+            match event {
+                Start(_) => skip_depth += 1,
+                End(_) => skip_depth -= 1,
+                _ => {}
+            }
+            return false;
+        }
+        match event {
+            Start(tag) => {
+                let kind = match tag {
+                    Tag::Heading { .. } => BlockKind::Heading,
+                    Tag::Item => BlockKind::ListItem,
+                    Tag::BlockQuote => BlockKind::BlockQuote,
+                    Tag::Table(_) => BlockKind::Table,
+                    Tag::Paragraph if container_depth == 0 => BlockKind::Paragraph,
+                    _ => return true,
+                };
+                if !block_kinds.contains(&kind) {
+                    skip_depth = 1;
+                    return false;
+                }
+                if kind != BlockKind::Paragraph {
+                    container_depth += 1;
+                }
+            }
+            End(TagEnd::Heading(_) | TagEnd::Item | TagEnd::BlockQuote | TagEnd::Table) => {
+                container_depth = container_depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+        true
+    })
+}
+
+#[derive(Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct ParseMarkdownOptions<'a> {
+    /// Kinds of block whose text goes into plaintext output, all when `None`
+    block_kinds: Option<&'a [BlockKind]>,
+
     #[allow(unused)]
     canonical_root_url: Option<&'a str>,
 
-    #[allow(unused)]
-    enable_smart_punctuation: bool,
+    /// Expand GitHub-style emoji shortcodes, such as `:smile:`, outside code
+    emoji_shortcodes: bool,
+
+    /// Render single newlines within a paragraph as `<br>`, instead of joining the lines
+    hard_line_breaks: bool,
+
+    /// How links are written in plaintext output
+    link_rendering: LinkRendering,
+
+    /// Deepest heading level (1 to 6) given an `id`, all levels when `None`
+    max_heading_id_level: Option<u8>,
+
+    /// Prefix each heading with its hierarchical section number, such as `1.2.1`
+    number_headings: bool,
+
+    smart_punctuation: SmartPunctuation,
 
     skip_code_blocks: bool,
-}
 
-impl Default for ParseMarkdownOptions<'_> {
-    fn default() -> Self {
-        ParseMarkdownOptions {
-            canonical_root_url: None,
-            enable_smart_punctuation: true,
-            skip_code_blocks: false,
-        }
-    }
+    /// Render `~subscript~` and `^superscript^` spans, such as `H~2~O` and `x^2^`, outside code
+    subscript_superscript: bool,
 }
 
 impl<'a> ParseMarkdownOptions<'a> {
+    pub fn block_kinds(&mut self, value: Option<&'a [BlockKind]>) -> &mut Self {
+        self.block_kinds = value;
+        self
+    }
+
     #[allow(unused)]
     pub fn canonical_root_url(&mut self, value: Option<&'a str>) -> &mut Self {
         self.canonical_root_url = value;
         self
     }
 
-    #[allow(unused)]
-    pub fn enable_smart_punctuation(&mut self, value: bool) -> &mut Self {
-        self.enable_smart_punctuation = value;
+    pub fn emoji_shortcodes(&mut self, value: bool) -> &mut Self {
+        self.emoji_shortcodes = value;
+        self
+    }
+
+    pub fn hard_line_breaks(&mut self, value: bool) -> &mut Self {
+        self.hard_line_breaks = value;
+        self
+    }
+
+    pub fn link_rendering(&mut self, value: LinkRendering) -> &mut Self {
+        self.link_rendering = value;
+        self
+    }
+
+    pub fn max_heading_id_level(&mut self, value: Option<u8>) -> &mut Self {
+        self.max_heading_id_level = value;
+        self
+    }
+
+    pub fn number_headings(&mut self, value: bool) -> &mut Self {
+        self.number_headings = value;
+        self
+    }
+
+    pub fn smart_punctuation(&mut self, value: SmartPunctuation) -> &mut Self {
+        self.smart_punctuation = value;
+        self
+    }
+
+    pub fn subscript_superscript(&mut self, value: bool) -> &mut Self {
+        self.subscript_superscript = value;
         self
     }
 
@@ -427,23 +1245,393 @@ impl<'a> ParseMarkdownOptions<'a> {
 
 pub fn parse_markdown_to_plaintext(markdown: &str, options: &ParseMarkdownOptions) -> String {
     let ParseMarkdownOptions {
+        block_kinds,
         canonical_root_url,
-        enable_smart_punctuation,
+        link_rendering,
+        smart_punctuation,
         skip_code_blocks,
+        ..
     } = *options;
 
-    let mut parser_options = Options::empty();
-    if enable_smart_punctuation {
-        parser_options.insert(Options::ENABLE_SMART_PUNCTUATION);
-    }
-    let parser = Parser::new_ext(markdown, parser_options);
+    let parser = keep_block_kinds(
+        Parser::new_ext(markdown, smart_punctuation.parser_options())
+            .into_offset_iter()
+            .map(|(event, range)| {
+                (
+                    smart_punctuation.revert_disabled(event, range.clone(), markdown),
+                    range,
+                )
+            }),
+        block_kinds,
+    );
 
     let mut plaintext_buf = String::new();
     push_plaintext(
         &mut plaintext_buf,
         parser,
         canonical_root_url,
+        link_rendering,
         skip_code_blocks,
     );
     plaintext_buf
 }
+
+/// Whether each line of `markdown` is exempt from all source lint checks, being in a code block or
+/// frontmatter, and whether it is not prose, so exempt from the consecutive spaces check too
+fn lint_exempt_lines(markdown: &str) -> (Vec<bool>, Vec<bool>) {
+    let line_count = markdown.split('\n').count();
+    let mut exempt = vec![false; line_count];
+    let mut not_prose = vec![false; line_count];
+    let line_index = |offset: usize| markdown[..offset].matches('\n').count();
+    let options = Options::ENABLE_TABLES | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        let Start(tag) = event else {
+            continue;
+        };
+        if range.is_empty() {
+            continue;
+        }
+        let lines = line_index(range.start)..=line_index(range.end - 1);
+        match tag {
+            Tag::CodeBlock(_) | Tag::MetadataBlock(_) => {
+                exempt[lines.clone()].fill(true);
+                not_prose[lines].fill(true);
+            }
+            Tag::HtmlBlock | Tag::Table(_) => not_prose[lines].fill(true),
+            _ => {}
+        }
+    }
+    (exempt, not_prose)
+}
+
+/// Warnings for fenced code blocks which indent some lines with tabs and others with spaces, or
+/// mix both on one line, since these render unevenly and can break syntax highlighting. Each
+/// warning gives the line of the opening fence.
+fn mixed_code_indentation_warnings(markdown: &str, line_offset: usize) -> Vec<String> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    let mut result = Vec::new();
+    let mut fence_line_number = None;
+    let (mut tab_indented, mut space_indented) = (false, false);
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        match event {
+            Start(Tag::CodeBlock(CodeBlockKind::Fenced(_))) => {
+                fence_line_number =
+                    Some(markdown[..range.start].matches('\n').count() + line_offset + 1);
+                (tab_indented, space_indented) = (false, false);
+            }
+            // Code block text has any list or block quote indentation removed already
+            Text(text) if fence_line_number.is_some() => {
+                for line in text.lines() {
+                    let content = line.trim_start_matches([' ', '\t']);
+                    if content.is_empty() {
+                        continue;
+                    }
+                    let indentation = &line[..line.len() - content.len()];
+                    tab_indented |= indentation.contains('\t');
+                    space_indented |= indentation.contains(' ');
+                }
+            }
+            End(TagEnd::CodeBlock) => {
+                if let Some(line_number) = fence_line_number.take() {
+                    if tab_indented && space_indented {
+                        result.push(format!(
+                            "Mixed tab and space indentation in code fence on line {line_number}"
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Warnings for links with the same visible text as an earlier link, but a different target, and
+/// for images with the same source as an earlier image. Either can be intended, so these are only
+/// hints to check the document.
+fn duplicate_link_warnings(markdown: &str, line_offset: usize) -> Vec<String> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    let line_number = |offset: usize| markdown[..offset].matches('\n').count() + line_offset + 1;
+    let mut result = Vec::new();
+    let mut first_links: HashMap<String, (String, usize)> = HashMap::new();
+    let mut first_images: HashMap<String, usize> = HashMap::new();
+    let mut open_link: Option<(String, usize, String)> = None;
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        match event {
+            Start(Tag::Link { dest_url, .. }) => {
+                open_link = Some((
+                    dest_url.to_string(),
+                    line_number(range.start),
+                    String::new(),
+                ));
+            }
+            Text(text) | Code(text) => {
+                if let Some((_, _, link_text)) = &mut open_link {
+                    link_text.push_str(&text);
+                }
+            }
+            End(TagEnd::Link) => {
+                let Some((href, line, link_text)) = open_link.take() else {
+                    continue;
+                };
+                let link_text = link_text.trim();
+                if link_text.is_empty() {
+                    continue;
+                }
+                match first_links.get(link_text) {
+                    Some((first_href, first_line)) if *first_href != href => {
+                        result.push(format!(
+                            "Link text \"{link_text}\" on line {line} points to {href}, but to \
+{first_href} on line {first_line}"
+                        ));
+                    }
+                    Some(_) => {}
+                    None => {
+                        first_links.insert(link_text.to_string(), (href, line));
+                    }
+                }
+            }
+            Start(Tag::Image { dest_url, .. }) => {
+                let line = line_number(range.start);
+                if let Some(first_line) = first_images.get(&*dest_url) {
+                    result.push(format!(
+                        "Image {dest_url} on line {line} is already used on line {first_line}"
+                    ));
+                } else {
+                    first_images.insert(dest_url.to_string(), line);
+                }
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Warnings for full (`[text][ref]`) and collapsed (`[ref][]`) reference links with no matching
+/// definition anywhere in the document. Shortcut references (`[ref]`) are not flagged, since
+/// undefined ones are just bracketed text.
+fn undefined_reference_warnings(markdown: &str, line_offset: usize) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut broken_link_callback = |link: BrokenLink| {
+        if matches!(link.link_type, LinkType::Reference | LinkType::Collapsed) {
+            let line_number = markdown[..link.span.start].matches('\n').count() + line_offset + 1;
+            result.push(format!(
+                "No definition for link reference [{}] on line {line_number}",
+                link.reference
+            ));
+        }
+        None
+    };
+    let options = Options::ENABLE_TABLES | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    Parser::new_with_broken_link_callback(markdown, options, Some(&mut broken_link_callback))
+        .for_each(drop);
+    result
+}
+
+/// Warnings for untidy whitespace in the `markdown` source: trailing whitespace, runs of spaces
+/// within prose, hard tabs and code fences mixing tab and space indentation, for reference links
+/// with no definition, and for links and images which may have been reused by mistake. Code block content, beyond its indentation, and frontmatter are not
+/// checked. Line numbers count from the first line of `markdown`, plus `line_offset` lines, such
+/// as frontmatter stripped before it.
+pub fn lint_markdown(markdown: &str, line_offset: usize) -> Vec<String> {
+    let (exempt, not_prose) = lint_exempt_lines(markdown);
+    let mut result = Vec::new();
+    for (index, line) in markdown.split('\n').enumerate() {
+        if exempt[index] {
+            continue;
+        }
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let line_number = index + line_offset + 1;
+        if line.ends_with([' ', '\t']) {
+            result.push(format!("Trailing whitespace on line {line_number}"));
+        }
+        if !not_prose[index] && line.trim().contains("  ") {
+            result.push(format!("Multiple consecutive spaces on line {line_number}"));
+        }
+        if line.contains('\t') {
+            result.push(format!("Hard tab on line {line_number}"));
+        }
+    }
+    result.extend(mixed_code_indentation_warnings(markdown, line_offset));
+    result.extend(undefined_reference_warnings(markdown, line_offset));
+    result.extend(duplicate_link_warnings(markdown, line_offset));
+    result
+}
+
+/// Byte ranges of code blocks, inline code and raw HTML in `markdown`, which spelling fixes must
+/// not change
+pub fn code_ranges(markdown: &str) -> Vec<Range<usize>> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    Parser::new_ext(markdown, options)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Start(Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_))
+            | Code(_)
+            | Html(_)
+            | InlineHtml(_) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Destinations of links in `markdown`, including the `href` values of anchors in raw HTML
+pub fn link_destinations(markdown: &str) -> Vec<String> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    let mut result = Vec::new();
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Start(Tag::Link { dest_url, .. }) => result.push(dest_url.to_string()),
+            Html(html) | InlineHtml(html) => result.extend(html_hrefs(&html)),
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Values of `href` attributes in the raw `html`, quoted or not
+fn html_hrefs(html: &str) -> Vec<String> {
+    let lowercase = html.to_ascii_lowercase();
+    let mut result = Vec::new();
+    for (index, _) in lowercase.match_indices("href=") {
+        let value = &html[index + "href=".len()..];
+        let href = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next(),
+            _ => value
+                .split(|character: char| character.is_whitespace() || character == '>')
+                .next(),
+        };
+        result.extend(href.map(ToString::to_string));
+    }
+    result
+}
+
+/// Region of `alt_texts`, each with the spans of it which came from markdown text, separated
+/// into paragraphs
+fn alt_text_region(
+    language: Option<String>,
+    alt_texts: Vec<(String, Vec<SourceSpan>)>,
+) -> LanguageRegion {
+    let mut text = String::new();
+    let mut source_spans = Vec::new();
+    for (alt_text, spans) in alt_texts {
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        let offset = text.len();
+        source_spans.extend(
+            spans
+                .into_iter()
+                .map(|(span, source)| (span.start + offset..span.end + offset, source)),
+        );
+        text.push_str(&alt_text);
+    }
+    text.push('\n');
+    LanguageRegion {
+        language,
+        text,
+        alt_text: true,
+        source_spans,
+    }
+}
+
+/// Plain text for the document, as [`parse_markdown_to_plaintext`], split into regions at language
+/// markers. Image alt text is taken out of the surrounding text and follows each region in an
+/// alt text region of its own, so it is checked as separate sentences.
+pub fn parse_markdown_to_plaintext_regions(
+    markdown: &str,
+    options: &ParseMarkdownOptions,
+) -> Vec<LanguageRegion> {
+    let ParseMarkdownOptions {
+        block_kinds,
+        canonical_root_url,
+        link_rendering,
+        smart_punctuation,
+        skip_code_blocks,
+        ..
+    } = *options;
+
+    let mut regions = Vec::new();
+    let mut push_region = |language: Option<String>,
+                           events: Vec<(Event, Range<usize>)>,
+                           alt_texts: Vec<(String, Vec<SourceSpan>)>| {
+        let mut text = String::new();
+        let source_spans = push_plaintext(
+            &mut text,
+            events.into_iter(),
+            canonical_root_url,
+            link_rendering,
+            skip_code_blocks,
+        );
+        if !text.trim().is_empty() {
+            regions.push(LanguageRegion {
+                language: language.clone(),
+                text,
+                alt_text: false,
+                source_spans,
+            });
+        }
+        if !alt_texts.is_empty() {
+            regions.push(alt_text_region(language, alt_texts));
+        }
+    };
+
+    let mut language: Option<String> = None;
+    let mut events = Vec::new();
+    let mut alt_texts: Vec<(String, Vec<SourceSpan>)> = Vec::new();
+    let mut current_alt_text: Option<(String, Vec<SourceSpan>)> = None;
+    let mut ignoring = false;
+    for (event, range) in keep_block_kinds(
+        Parser::new_ext(markdown, smart_punctuation.parser_options())
+            .into_offset_iter()
+            .map(|(event, range)| {
+                (
+                    smart_punctuation.revert_disabled(event, range.clone(), markdown),
+                    range,
+                )
+            }),
+        block_kinds,
+    ) {
+        if is_grammar_ignore_start(&event) {
+            ignoring = true;
+        } else if is_grammar_ignore_end(&event) {
+            ignoring = false;
+        } else if !ignoring {
+            if let Some(value) = language_marker(&event) {
+                let value = Some(value)
+                    .filter(|value| !value.is_empty() && *value != "default")
+                    .map(ToString::to_string);
+                push_region(
+                    mem::replace(&mut language, value),
+                    mem::take(&mut events),
+                    mem::take(&mut alt_texts),
+                );
+                continue;
+            }
+            match (&event, current_alt_text.as_mut()) {
+                (Start(Tag::Image { .. }), None) => {
+                    current_alt_text = Some((String::new(), Vec::new()));
+                    continue;
+                }
+                (End(TagEnd::Image), Some(_)) => {
+                    if let Some(value) = current_alt_text
+                        .take()
+                        .filter(|(value, _)| !value.trim().is_empty())
+                    {
+                        alt_texts.push(value);
+                    }
+                    continue;
+                }
+                (Text(text) | Code(text), Some((value, spans))) => {
+                    spans.push((value.len()..value.len() + text.len(), range));
+                    value.push_str(text);
+                    continue;
+                }
+                (_, Some(_)) => continue,
+                _ => {}
+            }
+        }
+        events.push((event, range));
+    }
+    push_region(language, events, alt_texts);
+    regions
+}
@@ -1,6 +1,7 @@
 use crate::markdown::{
-    parse_markdown_to_html, parse_markdown_to_plaintext, reading_time_from_words, slugified_title,
-    words, ParseMarkdownOptions,
+    link_destinations, lint_markdown, parse_markdown_to_html, parse_markdown_to_plaintext,
+    parse_markdown_to_plaintext_regions, reading_time_from_words, words, BlockKind, Heading,
+    LinkRendering, ParseError, ParseMarkdownOptions, SmartPunctuation,
 };
 
 #[test]
@@ -37,22 +38,54 @@ fn test_words() {
     assert_eq!(words(text), 3);
 }
 
+#[test]
+fn words_counts_each_cjk_character_as_a_word() {
+    let text = "我爱写作";
+    assert_eq!(words(text), 4);
+
+    let text = "日本語のテキストです。";
+    assert_eq!(words(text), 10);
+
+    let text = "我爱Rust编程";
+    assert_eq!(words(text), 5);
+
+    let text = "안녕하세요 세계";
+    assert_eq!(words(text), 2);
+}
+
+#[test]
+fn words_counts_hyphenated_compounds_consistently() {
+    let text = "state-of-the-art";
+    assert_eq!(words(text), 1);
+
+    let text = "well\u{2010}known non\u{2011}breaking";
+    assert_eq!(words(text), 2);
+
+    let text = "half - time";
+    assert_eq!(words(text), 2);
+
+    let text = "pages 10\u{2013}12";
+    assert_eq!(words(text), 3);
+
+    let text = "writing\u{2014}and editing";
+    assert_eq!(words(text), 3);
+}
+
 #[test]
 fn test_parse_markdown_to_html() {
-    let markdown = r#"
+    let markdown = r"
 hello
 =====
 
 * alpha
 * beta
-"#;
+";
 
-    let result =
-        if let Some((result, _headings, _statistics)) = parse_markdown_to_html(markdown).ok() {
-            result
-        } else {
-            panic!("Result expected");
-        };
+    let Ok((result, _headings, _statistics)) =
+        parse_markdown_to_html(markdown, &ParseMarkdownOptions::default())
+    else {
+        panic!("Result expected");
+    };
     let expected = String::from(
         r#"<h1 id="hello">hello</h1>
 <ul>
@@ -77,12 +110,11 @@ First paragraph.
 Second paragraph
 "#;
 
-    let result =
-        if let Some((result, _headings, _statistics)) = parse_markdown_to_html(markdown).ok() {
-            result
-        } else {
-            panic!("Result expected");
-        };
+    let Ok((result, _headings, _statistics)) =
+        parse_markdown_to_html(markdown, &ParseMarkdownOptions::default())
+    else {
+        panic!("Result expected");
+    };
     let expected = String::from(
         r#"<h1 id="hello">hello</h1>
 <p>First paragraph.</p>
@@ -134,6 +166,27 @@ pub fn parse_markdown_to_plaintext_applies_canonical_root_url() {
     assert_eq!(result, expected);
 }
 
+#[test]
+pub fn parse_markdown_to_plaintext_renders_links_in_each_link_rendering_mode() {
+    let markdown = "[Contact us](https://example.com/contact) to find out more.";
+
+    let mut options = ParseMarkdownOptions::default();
+    options.link_rendering(LinkRendering::TextOnly);
+    let result = parse_markdown_to_plaintext(markdown, &options);
+    assert_eq!(result, "Contact us to find out more.\n");
+
+    options.link_rendering(LinkRendering::UrlOnly);
+    let result = parse_markdown_to_plaintext(markdown, &options);
+    assert_eq!(result, "https://example.com/contact to find out more.\n");
+
+    options.link_rendering(LinkRendering::TextAndUrl);
+    let result = parse_markdown_to_plaintext(markdown, &options);
+    assert_eq!(
+        result,
+        "Contact us (https://example.com/contact) to find out more.\n"
+    );
+}
+
 #[test]
 pub fn parse_markdown_to_plaintext_outputs_relative_urls_when_canonical_root_url_absent() {
     let markdown = "[Contact us](/contact) to find out more.";
@@ -146,16 +199,468 @@ pub fn parse_markdown_to_plaintext_outputs_relative_urls_when_canonical_root_url
 }
 
 #[test]
-pub fn test_slugified_title() {
-    let title = "Heading One";
-    assert_eq!(slugified_title(title), "heading-one");
+pub fn parse_markdown_to_html_heading_slugs_match_injected_ids() {
+    let markdown = "# Heading One
+
+Some text.
+
+## 🌟 Heading Two
+
+More text.
+
+### Heading `Three`
+";
+
+    let Ok((html, headings, _statistics)) =
+        parse_markdown_to_html(markdown, &ParseMarkdownOptions::default())
+    else {
+        panic!("Result expected");
+    };
+    assert_eq!(headings.len(), 3);
+    let mut last_position = 0;
+    for heading in &headings {
+        let id_attribute = format!(r#"id="{}""#, heading.slug());
+        let position = html[last_position..]
+            .find(&id_attribute)
+            .expect("Expected heading slug to be injected as id attribute");
+        last_position += position + id_attribute.len();
+    }
+    assert_eq!(headings[1].slug(), "heading-two");
+    assert_eq!(headings[2].text(), "Heading Three");
+}
+
+#[test]
+pub fn parse_markdown_to_html_records_heading_source_offsets() {
+    let markdown = "Intro paragraph.
+
+## First
+
+Text.
+
+Second
+------
+";
+
+    let Ok((_html, headings, _statistics)) =
+        parse_markdown_to_html(markdown, &ParseMarkdownOptions::default())
+    else {
+        panic!("Result expected");
+    };
+    assert_eq!(headings[0].source_offset(), 18);
+    assert!(markdown[headings[0].source_offset()..].starts_with("## First"));
+    assert!(markdown[headings[1].source_offset()..].starts_with("Second\n------"));
+}
+
+#[test]
+pub fn parse_markdown_to_plaintext_applies_all_smart_punctuation_by_default() {
+    let markdown = r#"She said "wait" -- then left... It's 9---5."#;
+
+    let result = parse_markdown_to_plaintext(markdown, &ParseMarkdownOptions::default());
+    let expected = String::from("She said “wait” – then left… It’s 9—5.\n");
+    assert_eq!(result, expected);
+}
+
+#[test]
+pub fn parse_markdown_to_plaintext_skips_disabled_smart_quotes() {
+    let markdown = r#"She said "wait" -- then left... It's 9---5."#;
+
+    let mut options = ParseMarkdownOptions::default();
+    options.smart_punctuation(SmartPunctuation::all().quotes(false));
+    let result = parse_markdown_to_plaintext(markdown, &options);
+    let expected = String::from("She said \"wait\" – then left… It's 9—5.\n");
+    assert_eq!(result, expected);
+}
+
+#[test]
+pub fn parse_markdown_to_plaintext_skips_disabled_smart_dashes() {
+    let markdown = r#"She said "wait" -- then left... It's 9---5."#;
+
+    let mut options = ParseMarkdownOptions::default();
+    options.smart_punctuation(SmartPunctuation::all().dashes(false));
+    let result = parse_markdown_to_plaintext(markdown, &options);
+    let expected = String::from("She said “wait” -- then left… It’s 9---5.\n");
+    assert_eq!(result, expected);
+}
+
+#[test]
+pub fn parse_markdown_to_plaintext_skips_disabled_smart_ellipses() {
+    let markdown = r#"She said "wait" -- then left... It's 9---5."#;
+
+    let mut options = ParseMarkdownOptions::default();
+    options.smart_punctuation(SmartPunctuation::all().ellipses(false));
+    let result = parse_markdown_to_plaintext(markdown, &options);
+    let expected = String::from("She said “wait” – then left... It’s 9—5.\n");
+    assert_eq!(result, expected);
+}
+
+#[test]
+pub fn parse_markdown_to_html_keeps_literal_typographic_characters_when_disabled() {
+    let markdown = "“Curly” &ldquo;entity&rdquo; and \"straight\" -- dash";
 
-    let title = "🌟 Heading Two";
-    assert_eq!(slugified_title(title), "*-heading-two");
+    let mut options = ParseMarkdownOptions::default();
+    options.smart_punctuation(SmartPunctuation::none());
+    let Ok((result, _headings, _statistics)) = parse_markdown_to_html(markdown, &options) else {
+        panic!("Result expected");
+    };
+    let expected = "<p>“Curly” “entity” and \"straight\" -- dash</p>\n";
+    assert_eq!(result, expected);
+}
+
+#[test]
+pub fn parse_markdown_to_plaintext_skips_grammar_ignore_regions() {
+    let markdown = "Checked paragraph.
+
+<!-- markwrite:grammar-ignore-start -->
+
+Ignored paragraph with teh typo.
+
+> Ignored quotation.
+
+<!-- markwrite:grammar-ignore-end -->
+
+Another checked paragraph with <!-- markwrite:grammar-ignore-start -->inline ignored text<!-- markwrite:grammar-ignore-end --> in it.
+";
+
+    let result = parse_markdown_to_plaintext(markdown, &ParseMarkdownOptions::default());
+    let expected = String::from("Checked paragraph.\n\nAnother checked paragraph with  in it.\n");
+    assert_eq!(result, expected);
+}
+
+#[test]
+pub fn parse_markdown_to_html_removes_grammar_ignore_markers() {
+    let markdown = "<!-- markwrite:grammar-ignore-start -->
 
-    let title = "💫 Heading Three";
-    assert_eq!(slugified_title(title), "dizzy-heading-three");
+Ignored paragraph.
 
-    let title = "Heading Four!";
-    assert_eq!(slugified_title(title), "heading-four");
+<!-- markwrite:grammar-ignore-end -->
+";
+
+    let Ok((result, _headings, _statistics)) =
+        parse_markdown_to_html(markdown, &ParseMarkdownOptions::default())
+    else {
+        panic!("Result expected");
+    };
+    assert_eq!(result, "<p>Ignored paragraph.</p>\n");
+}
+
+#[test]
+pub fn parse_markdown_to_html_omits_ids_below_max_heading_id_level() {
+    let markdown = "## Section\n\n### Subsection\n\n#### Detail\n";
+
+    let mut options = ParseMarkdownOptions::default();
+    options.max_heading_id_level(Some(3));
+    let Ok((result, headings, _statistics)) = parse_markdown_to_html(markdown, &options) else {
+        panic!("Result expected");
+    };
+    let expected = r#"<h2 id="section">Section</h2>
+<h3 id="subsection">Subsection</h3>
+<h4>Detail</h4>
+"#;
+    assert_eq!(result, expected);
+    assert_eq!(
+        headings.iter().map(Heading::id).collect::<Vec<&str>>(),
+        vec!["section", "subsection"]
+    );
+}
+
+#[test]
+pub fn parse_markdown_to_plaintext_regions_separates_image_alt_text() {
+    let markdown = "Look at this ![A sleepy foox](fox.png) photo.
+
+![Second *image*](second.png)
+";
+    let regions = parse_markdown_to_plaintext_regions(markdown, &ParseMarkdownOptions::default());
+    assert_eq!(regions.len(), 2);
+    assert_eq!(regions[0].text(), "Look at this  photo.\n\n\n");
+    assert!(!regions[0].is_alt_text());
+    assert_eq!(regions[1].text(), "A sleepy foox\n\nSecond image\n");
+    assert!(regions[1].is_alt_text());
+}
+
+#[test]
+pub fn parse_markdown_to_plaintext_keeps_only_chosen_block_kinds() {
+    let markdown = "# Heading\n\nA paragraph.\n\n- A list item\n- Another\n\n> A quote.\n";
+    let mut options = ParseMarkdownOptions::default();
+    options.block_kinds(Some(&[BlockKind::Paragraph, BlockKind::BlockQuote]));
+    let result = parse_markdown_to_plaintext(markdown, &options);
+    assert!(result.contains("A paragraph."));
+    assert!(result.contains("A quote."));
+    assert!(!result.contains("Heading"));
+    assert!(!result.contains("list item"));
+}
+
+#[test]
+pub fn lint_markdown_warns_on_trailing_spaces() {
+    let markdown = "# Heading\n\nA line with trailing spaces.   \nA tidy line.\n";
+    let result = lint_markdown(markdown, 0);
+    assert_eq!(result, vec![String::from("Trailing whitespace on line 3")]);
+}
+
+#[test]
+pub fn lint_markdown_ignores_tab_inside_code_fence() {
+    let markdown = "Some prose.\n\n```rust\nfn main() {\n\tprintln!(\"hi\");\n}\n```\n";
+    let result = lint_markdown(markdown, 0);
+    assert!(result.is_empty());
+}
+
+#[test]
+pub fn lint_markdown_warns_on_code_fence_mixing_tab_and_space_indentation() {
+    let markdown =
+        "Some prose.\n\n```rust\nfn main() {\n\tlet a = 1;\n    println!(\"{a}\");\n}\n```\n";
+    let result = lint_markdown(markdown, 2);
+    assert_eq!(
+        result,
+        vec![String::from(
+            "Mixed tab and space indentation in code fence on line 5"
+        )]
+    );
+}
+
+#[test]
+pub fn lint_markdown_accepts_code_fence_with_consistent_indentation() {
+    let markdown = "- Item\n\n  ```rust\n  fn main() {\n      let a = 1;\n  }\n  ```\n\n```go\nfunc main() {\n\tfmt.Println(1)\n}\n```\n";
+    let result = lint_markdown(markdown, 0);
+    assert!(result.is_empty());
+}
+
+#[test]
+pub fn parse_markdown_to_html_resolves_references_defined_anywhere() {
+    let markdown = "[top]: https://example.com/top\n\nSee [the top][top] and [the bottom][bottom].\n\n[bottom]: https://example.com/bottom\n";
+    let (html, _headings, _statistics) =
+        parse_markdown_to_html(markdown, &ParseMarkdownOptions::default()).unwrap();
+    assert_eq!(
+        html,
+        "<p>See <a href=\"https://example.com/top\">the top</a> and <a href=\"https://example.com/bottom\">the bottom</a>.</p>\n"
+    );
+    assert!(lint_markdown(markdown, 0).is_empty());
+}
+
+#[test]
+pub fn lint_markdown_warns_on_undefined_link_reference() {
+    let markdown = "See [the docs][docs] and [this][].\n\nA [shortcut] stays as text.\n";
+    let result = lint_markdown(markdown, 3);
+    assert_eq!(
+        result,
+        vec![
+            String::from("No definition for link reference [docs] on line 4"),
+            String::from("No definition for link reference [this] on line 4")
+        ]
+    );
+}
+
+#[test]
+pub fn lint_markdown_warns_on_links_sharing_text_with_different_targets() {
+    let markdown = "See [the docs](https://example.com/docs) and [`setup`](/setup).\n\nRead [the docs](https://example.com/v2/docs), [`setup`](/setup) again.\n\n![Chart](chart.png)\n\n![Later chart](chart.png)\n";
+    let result = lint_markdown(markdown, 0);
+    assert_eq!(
+        result,
+        vec![
+            String::from("Link text \"the docs\" on line 3 points to https://example.com/v2/docs, but to https://example.com/docs on line 1"),
+            String::from("Image chart.png on line 7 is already used on line 5")
+        ]
+    );
+}
+
+#[test]
+pub fn lint_markdown_accepts_consistent_links() {
+    let markdown = "See [the docs](https://example.com/docs).\n\nRead [the docs](https://example.com/docs) and [more](https://example.com/more).\n";
+    let result = lint_markdown(markdown, 0);
+    assert!(result.is_empty());
+}
+
+#[test]
+pub fn lint_markdown_warns_on_spaces_and_tabs_in_prose_skipping_frontmatter() {
+    let markdown = "---\ntitle:  Spaced\t\n---\n\nTwo  spaces and a\ttab.\n";
+    let result = lint_markdown(markdown, 2);
+    assert_eq!(
+        result,
+        vec![
+            String::from("Multiple consecutive spaces on line 7"),
+            String::from("Hard tab on line 7")
+        ]
+    );
+}
+
+#[test]
+pub fn parse_markdown_to_html_expands_emoji_shortcodes_in_prose() {
+    let markdown = "Launch day :rocket: at 10:30, see you there :smile:\n";
+    let mut options = ParseMarkdownOptions::default();
+    options.emoji_shortcodes(true);
+    let Ok((result, _headings, _statistics)) = parse_markdown_to_html(markdown, &options) else {
+        panic!("Result expected");
+    };
+    assert_eq!(result, "<p>Launch day 🚀 at 10:30, see you there 😄</p>\n");
+}
+
+#[test]
+pub fn parse_markdown_to_html_keeps_emoji_shortcodes_in_code_literal() {
+    let markdown = "Type `:smile:` for a smile.\n\n```\n:smile:\n```\n";
+    let mut options = ParseMarkdownOptions::default();
+    options.emoji_shortcodes(true);
+    let Ok((result, _headings, _statistics)) = parse_markdown_to_html(markdown, &options) else {
+        panic!("Result expected");
+    };
+    assert_eq!(
+        result,
+        "<p>Type <code>:smile:</code> for a smile.</p>\n<pre><code>:smile:\n</code></pre>\n"
+    );
+}
+
+#[test]
+pub fn parse_markdown_to_html_leaves_emoji_shortcodes_by_default() {
+    let markdown = "Hello :smile:\n";
+    let Ok((result, _headings, _statistics)) =
+        parse_markdown_to_html(markdown, &ParseMarkdownOptions::default())
+    else {
+        panic!("Result expected");
+    };
+    assert_eq!(result, "<p>Hello :smile:</p>\n");
+}
+
+#[test]
+pub fn parse_markdown_to_html_renders_subscript_and_superscript() {
+    let markdown = "Water is H~2~O & E = mc^2^, not `H~2~O`.\n";
+    let mut options = ParseMarkdownOptions::default();
+    options.subscript_superscript(true);
+    let Ok((result, _headings, _statistics)) = parse_markdown_to_html(markdown, &options) else {
+        panic!("Result expected");
+    };
+    assert_eq!(
+        result,
+        "<p>Water is H<sub>2</sub>O &amp; E = mc<sup>2</sup>, not <code>H~2~O</code>.</p>\n"
+    );
+}
+
+#[test]
+pub fn parse_markdown_to_html_leaves_strikethrough_lone_tildes_and_footnote_carets() {
+    let markdown = "Keep ~~struck out~~ text, about ~5 to ~10 items and a note^[aside]^ here.\n";
+    let mut options = ParseMarkdownOptions::default();
+    options.subscript_superscript(true);
+    let Ok((result, _headings, _statistics)) = parse_markdown_to_html(markdown, &options) else {
+        panic!("Result expected");
+    };
+    let Ok((default_result, _headings, _statistics)) =
+        parse_markdown_to_html(markdown, &ParseMarkdownOptions::default())
+    else {
+        panic!("Result expected");
+    };
+    assert_eq!(result, default_result);
+    assert!(result.contains("~~struck out~~"));
+}
+
+#[test]
+pub fn parse_markdown_to_html_errors_on_unmatched_grammar_ignore_markers() {
+    let markdown = "# Title\n\nSome text.\n\n<!-- markwrite:grammar-ignore-start -->\n\nIgnored.\n";
+    let result = parse_markdown_to_html(markdown, &ParseMarkdownOptions::default());
+    let Err(error) = result else {
+        panic!("Error expected");
+    };
+    assert_eq!(error.line_column(markdown), (5, 1));
+
+    let markdown = "Some text, then an end marker <!-- markwrite:grammar-ignore-end -->.\n";
+    let result = parse_markdown_to_html(markdown, &ParseMarkdownOptions::default());
+    let Err(error) = result else {
+        panic!("Error expected");
+    };
+    assert_eq!(error.line_column(markdown), (1, 31));
+    assert_eq!(
+        error.to_string(),
+        "grammar ignore end marker without a matching start marker"
+    );
+}
+
+#[test]
+pub fn parse_error_line_column_counts_characters() {
+    let error = ParseError::new("example", "Crème\nbrûlée ".len());
+    assert_eq!(error.line_column("Crème\nbrûlée here"), (2, 8));
+}
+
+#[test]
+pub fn parse_markdown_to_html_computes_sentence_statistics() {
+    let markdown = "# A heading which is not a sentence
+
+First sentence has five words. Short one! Is this, e.g. a question?
+
+- List item without a full stop
+";
+    let Ok((_html, _headings, statistics)) =
+        parse_markdown_to_html(markdown, &ParseMarkdownOptions::default())
+    else {
+        panic!("Result expected");
+    };
+    assert_eq!(statistics.longest_sentence_words(), 6);
+    assert!((statistics.average_sentence_length() - 4.5).abs() < f64::EPSILON);
+    assert_eq!(
+        statistics.long_sentences(5).collect::<Vec<_>>(),
+        vec![(markdown.find("List").unwrap(), 6)]
+    );
+}
+
+#[test]
+pub fn parse_markdown_to_html_renders_soft_breaks_as_line_breaks_when_enabled() {
+    let markdown = "Roses are red,\nViolets are blue.\n";
+
+    let Ok((default_result, _headings, _statistics)) =
+        parse_markdown_to_html(markdown, &ParseMarkdownOptions::default())
+    else {
+        panic!("Result expected");
+    };
+    assert_eq!(default_result, "<p>Roses are red,\nViolets are blue.</p>\n");
+
+    let mut options = ParseMarkdownOptions::default();
+    options.hard_line_breaks(true);
+    let Ok((result, _headings, _statistics)) = parse_markdown_to_html(markdown, &options) else {
+        panic!("Result expected");
+    };
+    assert_eq!(result, "<p>Roses are red,<br />\nViolets are blue.</p>\n");
+}
+
+#[test]
+pub fn parse_markdown_to_html_numbers_headings_hierarchically() {
+    let markdown = "# Manual\n\n## Install\n\n## Configure\n\n### Options\n";
+
+    let mut options = ParseMarkdownOptions::default();
+    options.number_headings(true);
+    let Ok((result, headings, _statistics)) = parse_markdown_to_html(markdown, &options) else {
+        panic!("Result expected");
+    };
+    assert_eq!(
+        headings
+            .iter()
+            .filter_map(Heading::number)
+            .collect::<Vec<&str>>(),
+        vec!["1", "1.1", "1.2", "1.2.1"]
+    );
+    assert!(result
+        .contains(r#"<h3 id="options"><span class="heading-number">1.2.1</span> Options</h3>"#));
+    assert_eq!(headings[3].text(), "Options");
+}
+
+#[test]
+pub fn parse_markdown_to_html_numbers_headings_with_skipped_levels() {
+    let markdown = "## Start\n\n#### Deep\n\n### Middle\n\n# Top\n\n### Nested\n";
+
+    let mut options = ParseMarkdownOptions::default();
+    options.number_headings(true);
+    let Ok((_result, headings, _statistics)) = parse_markdown_to_html(markdown, &options) else {
+        panic!("Result expected");
+    };
+    assert_eq!(
+        headings
+            .iter()
+            .filter_map(Heading::number)
+            .collect::<Vec<&str>>(),
+        vec!["1", "1.1", "1.2", "2", "2.1"]
+    );
+}
+
+#[test]
+pub fn link_destinations_include_raw_html_anchors() {
+    let markdown = "See [one](one.md) and <a href=\"two.md#top\">two</a>.\n\n\
+        <p><a class=\"more\" HREF='three.md'>three</a></p>\n\n[ref]: four.md\n\n[Four][ref]\n";
+
+    assert_eq!(
+        link_destinations(markdown),
+        vec!["one.md", "two.md#top", "three.md", "four.md"]
+    );
 }
@@ -7,9 +7,138 @@ pub fn relative_url(url: &str) -> bool {
     }
 }
 
+/// Whether `url` is an absolute `http` or `https` URL, or a protocol-relative one, on a host other
+/// than that of `root_url`, a site root such as `https://example.com`. Relative URLs, and other
+/// schemes, such as `mailto:`, are never external.
+pub fn external_url(url: &str, root_url: Option<&str>) -> bool {
+    let parsed = if url.starts_with("//") {
+        Url::parse(&format!("https:{url}"))
+    } else {
+        Url::parse(url)
+    };
+    let Ok(parsed) = parsed else {
+        return false;
+    };
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return false;
+    }
+    let root_host = root_url
+        .and_then(|value| Url::parse(value).ok())
+        .and_then(|value| value.host_str().map(ToString::to_string));
+    parsed
+        .host_str()
+        .is_some_and(|host| root_host.as_deref() != Some(host))
+}
+
+/// Absolute form of `canonical_url`, a canonical link target. Absolute URLs are used as they are,
+/// while relative ones are resolved against `root_url`, a site root such as `https://example.com`
+/// without a trailing slash.
+///
+/// # Errors
+/// Errors with a message when `canonical_url` is not a valid URL, does not use `http` or `https`,
+/// or is relative with no `root_url` to resolve it against
+pub fn absolute_canonical_url(
+    canonical_url: &str,
+    root_url: Option<&str>,
+) -> Result<String, String> {
+    let invalid_url_message = |error| format!("Invalid canonical URL ({canonical_url}): {error}");
+    let result = match Url::parse(canonical_url) {
+        Ok(value) => value,
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            let Some(root_url) = root_url else {
+                return Err(format!(
+                    "Relative canonical URL ({canonical_url}) needs a site_url, or \
+                     --canonical-root-url, to resolve against"
+                ));
+            };
+            let resolved_url = if canonical_url.starts_with("//") {
+                Url::parse(root_url).and_then(|root| root.join(canonical_url))
+            } else {
+                Url::parse(&format!(
+                    "{root_url}/{}",
+                    canonical_url.trim_start_matches('/')
+                ))
+            };
+            resolved_url.map_err(invalid_url_message)?
+        }
+        Err(error) => return Err(invalid_url_message(error)),
+    };
+    if !matches!(result.scheme(), "http" | "https") {
+        return Err(format!(
+            "Canonical URL ({canonical_url}) should use http or https"
+        ));
+    }
+    Ok(result.to_string())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::relative_url;
+    use super::{absolute_canonical_url, external_url, relative_url};
+
+    #[test]
+    fn external_url_is_true_only_for_web_urls_on_other_hosts() {
+        // arrange
+        let root_url = Some("https://example.com");
+
+        // act
+        let other_host_result = external_url("https://example.org/about", root_url);
+        let protocol_relative_result = external_url("//cdn.example.org/file.pdf", root_url);
+        let same_host_result = external_url("https://Example.com/about", root_url);
+        let relative_result = external_url("/about", root_url);
+        let mailto_result = external_url("mailto:hello@example.org", root_url);
+
+        // assert
+        assert!(other_host_result);
+        assert!(protocol_relative_result);
+        assert!(!same_host_result);
+        assert!(!relative_result);
+        assert!(!mailto_result);
+    }
+
+    #[test]
+    fn absolute_canonical_url_passes_absolute_url_through() {
+        // arrange
+        let url = "https://example.com/blog/post/";
+
+        // act
+        let result = absolute_canonical_url(url, Some("https://example.org"));
+
+        // assert
+        assert_eq!(result, Ok(String::from("https://example.com/blog/post/")));
+    }
+
+    #[test]
+    fn absolute_canonical_url_resolves_relative_url_against_root() {
+        // arrange
+        let root_url = Some("https://example.com/blog");
+
+        // act
+        let root_relative_result = absolute_canonical_url("/post/", root_url);
+        let path_relative_result = absolute_canonical_url("post/", root_url);
+        let missing_root_result = absolute_canonical_url("/post/", None);
+
+        // assert
+        let expected = Ok(String::from("https://example.com/blog/post/"));
+        assert_eq!(root_relative_result, expected);
+        assert_eq!(path_relative_result, expected);
+        assert!(missing_root_result.is_err());
+    }
+
+    #[test]
+    fn absolute_canonical_url_rejects_invalid_url() {
+        // act
+        let invalid_host_result = absolute_canonical_url("https://exa mple.com/", None);
+        let scheme_result = absolute_canonical_url("javascript:alert(1)", None);
+
+        // assert
+        assert_eq!(
+            invalid_host_result,
+            Err(String::from(
+                "Invalid canonical URL (https://exa mple.com/): invalid international domain name"
+            ))
+        );
+        assert!(scheme_result.is_err());
+    }
 
     #[test]
     fn relative_url_returns_false_for_full_url() {
@@ -0,0 +1,29 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub fn is_markdown_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("md"))
+}
+
+/// Markdown files within `directory` and its subdirectories, sorted by path
+pub fn markdown_files(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut result = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(current_directory) = directories.pop() {
+        for entry in fs::read_dir(&current_directory)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                directories.push(path);
+            } else if is_markdown_file(&path) {
+                result.push(path);
+            }
+        }
+    }
+    result.sort();
+    Ok(result)
+}
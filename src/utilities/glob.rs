@@ -0,0 +1,60 @@
+/// Whether `text`, a `/` separated path, matches the glob `pattern`. `?` matches any one character
+/// and `*` any run of characters, neither crossing a `/`, while `**` also matches across `/`, so
+/// `docs/**/*.md` matches markdown files at any depth under `docs`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let boundaries = || {
+        text.char_indices()
+            .map(|(index, _)| index)
+            .chain([text.len()])
+    };
+    if let Some(rest) = pattern.strip_prefix("**/") {
+        // Zero or more whole directories
+        return glob_match(rest, text)
+            || text
+                .match_indices('/')
+                .any(|(index, _)| glob_match(rest, &text[index + 1..]));
+    }
+    if let Some(rest) = pattern.strip_prefix("**") {
+        return boundaries().any(|index| glob_match(rest, &text[index..]));
+    }
+    if let Some(rest) = pattern.strip_prefix('*') {
+        return boundaries()
+            .take_while(|index| !text[..*index].contains('/'))
+            .any(|index| glob_match(rest, &text[index..]));
+    }
+    let mut pattern_characters = pattern.chars();
+    let mut text_characters = text.chars();
+    match (pattern_characters.next(), text_characters.next()) {
+        (None, None) => true,
+        (Some(pattern_character), Some(text_character))
+            if pattern_character == text_character
+                || (pattern_character == '?' && text_character != '/') =>
+        {
+            glob_match(pattern_characters.as_str(), text_characters.as_str())
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn glob_match_keeps_single_stars_within_a_directory() {
+        // assert
+        assert!(glob_match("*.md", "post.md"));
+        assert!(!glob_match("*.md", "drafts/post.md"));
+        assert!(glob_match("drafts/?ost.md", "drafts/post.md"));
+        assert!(!glob_match("*.md", "post.html"));
+    }
+
+    #[test]
+    fn glob_match_matches_double_stars_across_directories() {
+        // assert
+        assert!(glob_match("docs/**/*.md", "docs/intro.md"));
+        assert!(glob_match("docs/**/*.md", "docs/guide/setup/intro.md"));
+        assert!(glob_match("**", "any/path.md"));
+        assert!(!glob_match("docs/**/*.md", "blog/intro.md"));
+    }
+}
@@ -1 +1,6 @@
+pub mod files;
+pub mod glob;
+pub mod sentences;
+pub mod slug;
 pub mod stack;
+pub mod time;
@@ -0,0 +1,304 @@
+/// Closing quotes and brackets which may follow the punctuation ending a sentence
+const SENTENCE_CLOSERS: [char; 7] = ['"', '\'', ')', ']', '”', '’', '»'];
+
+/// Whether the full stop ending `text` follows a single letter initial, such as the `J.` in
+/// `J. Smith`, rather than ending a sentence
+fn ends_with_initial(text: &str) -> bool {
+    let mut characters = text.chars().rev().skip(1);
+    characters.next().is_some_and(char::is_uppercase)
+        && !characters.next().is_some_and(char::is_alphanumeric)
+}
+
+/// Offsets in `text` where each sentence after the first starts, found by splitting after
+/// `terminators` and ellipses, with any closing quotes or brackets, and at blank lines. Terminators
+/// outside ASCII, such as `。`, also split when the next sentence follows with no space. Full stops
+/// ending abbreviations and initials, or followed by a lowercase word, do not end a sentence.
+/// Offsets include whitespace after the sentence, so a chunk ending at one keeps the sentence and
+/// its trailing space.
+pub fn sentence_ends(text: &str, terminators: &[char]) -> Vec<usize> {
+    let is_terminator = |character: &char| *character == '…' || terminators.contains(character);
+    let mut result = Vec::new();
+    let mut characters = text.char_indices().peekable();
+    while let Some((index, character)) = characters.next() {
+        let paragraph_break =
+            character == '\n' && characters.peek().is_some_and(|(_, next)| *next == '\n');
+        if !paragraph_break && !is_terminator(&character) {
+            continue;
+        }
+        let mut punctuation_end = index + character.len_utf8();
+        while let Some((next_index, next)) =
+            characters.next_if(|(_, next)| is_terminator(next) || SENTENCE_CLOSERS.contains(next))
+        {
+            punctuation_end = next_index + next.len_utf8();
+        }
+        let Some(&(whitespace_start, _)) = characters.peek() else {
+            break;
+        };
+        if whitespace_start != punctuation_end
+            || (character.is_ascii() && !text[whitespace_start..].starts_with(char::is_whitespace))
+        {
+            continue;
+        }
+        if character == '.' && !paragraph_break {
+            let sentence = &text[..=index];
+            let next_word_lowercase = text[whitespace_start..]
+                .trim_start()
+                .starts_with(char::is_lowercase);
+            if ends_with_abbreviation(sentence)
+                || ends_with_initial(sentence)
+                || next_word_lowercase
+            {
+                continue;
+            }
+        }
+        while characters
+            .next_if(|(_, next)| next.is_whitespace())
+            .is_some()
+        {}
+        if let Some((next_index, _)) = characters.peek() {
+            result.push(*next_index);
+        }
+    }
+    result
+}
+
+/// Abbreviations ending in a full stop, which do not end a sentence
+const NON_TERMINAL_ABBREVIATIONS: [&str; 8] =
+    ["e.g.", "i.e.", "etc.", "vs.", "Dr.", "Mr.", "Mrs.", "Ms."];
+
+/// Whether `text` ends with one of [`NON_TERMINAL_ABBREVIATIONS`], as a whole word
+pub fn ends_with_abbreviation(text: &str) -> bool {
+    NON_TERMINAL_ABBREVIATIONS.iter().any(|abbreviation| {
+        let Some(start) = text.len().checked_sub(abbreviation.len()) else {
+            return false;
+        };
+        text.get(start..)
+            .is_some_and(|tail| tail.eq_ignore_ascii_case(abbreviation))
+            && !text[..start]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Whether `character`, at `index` in `text` and followed by `next`, ends a sentence. ASCII
+/// terminators need whitespace after them, to avoid splitting 10.1, for example, while others,
+/// such as `。`, also end a sentence followed straight on by the next, as in Chinese and Japanese
+/// text. A full stop ending an abbreviation, like e.g., never ends a sentence.
+fn is_sentence_end(
+    text: &str,
+    index: usize,
+    character: char,
+    next: Option<char>,
+    terminators: &[char],
+) -> bool {
+    terminators.contains(&character)
+        && match next {
+            Some(value) if value.is_whitespace() => {
+                !(character == '.' && ends_with_abbreviation(&text[..=index]))
+            }
+            Some(_) => !character.is_ascii(),
+            None => false,
+        }
+}
+
+/* Text is trimmed into 1500 character chunks for grammar check.  This function
+ * was written to help truncate each chunk, so that the chunk ends with
+ * complete sentence, ended by one of `terminators`, or two new line characters.
+ */
+pub fn strip_trailing_sentence_stub<'a>(text: &'a str, terminators: &[char]) -> (&'a str, usize) {
+    let mut next = None;
+    for (index, character) in text.char_indices().rev() {
+        // no point trimming right back to the start of the string, so just send everything
+        if index == 0 {
+            break;
+        }
+
+        let end = if character == '\n' {
+            text[..index].ends_with('\n').then_some(index + 1)
+        } else if is_sentence_end(text, index, character, next, terminators) {
+            // keep any whitespace character after the sentence with it
+            Some(
+                index
+                    + character.len_utf8()
+                    + next.map_or(0, |value| {
+                        if value.is_whitespace() {
+                            value.len_utf8()
+                        } else {
+                            0
+                        }
+                    }),
+            )
+        } else {
+            None
+        };
+        if let Some(value) = end {
+            return (&text[..value], value);
+        }
+        next = Some(character);
+    }
+    (text, text.len())
+}
+
+/// Characters ending a sentence, when grammar check chunks are trimmed back to a sentence end,
+/// unless set in the options: full stops, question and exclamation marks, including the Chinese
+/// and Japanese full-width forms, and the Arabic question mark
+pub const DEFAULT_SENTENCE_TERMINATORS: [char; 7] = ['.', '!', '?', '。', '！', '？', '؟'];
+
+/// Offset in `text` where its last `sentences` sentences start, or zero when it has no more
+/// sentences than that
+pub fn trailing_sentences_start(text: &str, sentences: usize, terminators: &[char]) -> usize {
+    if sentences == 0 {
+        return text.len();
+    }
+    let mut sentence_starts = vec![0];
+    let mut characters = text.char_indices().peekable();
+    while let Some((index, character)) = characters.next() {
+        let next = characters.peek().map(|(_, next)| *next);
+        if is_sentence_end(text, index, character, next, terminators) {
+            while characters
+                .next_if(|(_, next)| next.is_whitespace())
+                .is_some()
+            {}
+            if let Some((next_index, _)) = characters.peek() {
+                sentence_starts.push(*next_index);
+            }
+        }
+    }
+    sentence_starts[sentence_starts.len().saturating_sub(sentences)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sentence_ends, strip_trailing_sentence_stub, DEFAULT_SENTENCE_TERMINATORS};
+    use fake::{faker, Fake};
+
+    #[test]
+    fn sentence_ends_splits_after_closing_quotes() {
+        // arrange
+        let text = "She said \"Stop!\" Then she left. Nobody followed (at first.) Later they did.";
+
+        // act
+        let result = sentence_ends(text, &DEFAULT_SENTENCE_TERMINATORS);
+
+        // assert
+        assert_eq!(
+            result
+                .iter()
+                .map(|end| &text[..*end])
+                .collect::<Vec<&str>>(),
+            vec![
+                "She said \"Stop!\" ",
+                "She said \"Stop!\" Then she left. ",
+                "She said \"Stop!\" Then she left. Nobody followed (at first.) "
+            ]
+        );
+    }
+
+    #[test]
+    fn sentence_ends_skips_initials_abbreviations_and_lowercase_continuations() {
+        // arrange
+        let text = "J. R. Smith met Dr. Jones at approx. noon, e.g. for lunch. They ate.";
+
+        // act
+        let result = sentence_ends(text, &DEFAULT_SENTENCE_TERMINATORS);
+
+        // assert
+        assert_eq!(result, vec![text.find("They").unwrap()]);
+    }
+
+    #[test]
+    fn sentence_ends_splits_at_blank_lines() {
+        // arrange
+        let text = "A heading\n\nFirst paragraph, no full stop\n\nSecond.";
+
+        // act
+        let result = sentence_ends(text, &DEFAULT_SENTENCE_TERMINATORS);
+
+        // assert
+        assert_eq!(
+            result,
+            vec![text.find("First").unwrap(), text.find("Second").unwrap()]
+        );
+    }
+
+    #[test]
+    fn strip_trailing_sentencte_stub_truncates_long_text_chunk() {
+        // arrange
+        let paragraphs: Vec<String> = faker::lorem::en::Paragraphs(3..5).fake();
+        let text = paragraphs.join("\n\n");
+
+        // act
+        let (text_chunk, length) =
+            strip_trailing_sentence_stub(&text, &DEFAULT_SENTENCE_TERMINATORS);
+
+        // asert
+        assert!(length <= 1500);
+        assert!(text_chunk.len() == length);
+        let last = &text_chunk[length - 1..];
+        dbg!("LAST: {last}");
+        assert!(last == "." || last == "!" || last == "\n" || last == "?");
+    }
+
+    #[test]
+    fn strip_trailing_sentence_stub_uses_given_terminators() {
+        // arrange
+        let text = "هل أنت بخير؟ نعم أنا بخير";
+
+        // act
+        let (default_chunk, _length) =
+            strip_trailing_sentence_stub(text, &DEFAULT_SENTENCE_TERMINATORS);
+        let (ascii_chunk, ascii_length) = strip_trailing_sentence_stub(text, &['.', '!', '?']);
+
+        // assert
+        assert_eq!(default_chunk, "هل أنت بخير؟ ");
+        assert_eq!(ascii_chunk, text);
+        assert_eq!(ascii_length, text.len());
+    }
+
+    #[test]
+    fn strip_trailing_sentence_stub_does_not_split_after_abbreviations() {
+        for abbreviation in ["e.g.", "i.e.", "etc.", "Dr.", "Mr."] {
+            // arrange
+            let text = format!(
+                "The first sentence ends here. Then mention {abbreviation} something and trail off"
+            );
+
+            // act
+            let (text_chunk, length) =
+                strip_trailing_sentence_stub(&text, &DEFAULT_SENTENCE_TERMINATORS);
+
+            // assert
+            assert_eq!(text_chunk, "The first sentence ends here. ");
+            assert_eq!(length, text_chunk.len());
+        }
+    }
+
+    #[test]
+    fn strip_trailing_sentence_stub_splits_after_word_ending_like_abbreviation() {
+        // arrange
+        let text = "We met Amr. Then something else";
+
+        // act
+        let (text_chunk, _length) =
+            strip_trailing_sentence_stub(text, &DEFAULT_SENTENCE_TERMINATORS);
+
+        // assert
+        assert_eq!(text_chunk, "We met Amr. ");
+    }
+
+    #[quickcheck_macros::quickcheck]
+    fn strip_trailing_sentencte_stub_truncates_long_text_as_expected() -> bool {
+        // arrange
+        let paragraphs: Vec<String> = faker::lorem::en::Paragraphs(3..5).fake();
+        let text = paragraphs.join("\n\n");
+
+        // act
+        let (text_chunk, length) =
+            strip_trailing_sentence_stub(&text, &DEFAULT_SENTENCE_TERMINATORS);
+
+        // asert
+        let last = &text_chunk[length - 1..];
+        length <= 1500 && (last == "." || last == "!" || last == "\n" || last == "?")
+    }
+}
@@ -0,0 +1,99 @@
+use deunicode::deunicode;
+
+/// Slug returned when `input` has no characters which survive slugification
+pub const FALLBACK_SLUG: &str = "section";
+
+/// URL and id-safe slug for `input`: accented characters are transliterated to ASCII and
+/// lowercased, punctuation is dropped and runs of whitespace, hyphens and separators collapse to
+/// a single hyphen, with no leading or trailing hyphen. Returns [`FALLBACK_SLUG`] when nothing
+/// remains.
+#[must_use]
+pub fn slugify(input: &str) -> String {
+    let deunicoded_input = deunicode(input);
+    let mut result = String::with_capacity(deunicoded_input.len());
+    let mut pending_separator = false;
+    for character in deunicoded_input.chars() {
+        if character.is_ascii_alphanumeric() {
+            if pending_separator && !result.is_empty() {
+                result.push('-');
+            }
+            pending_separator = false;
+            result.push(character.to_ascii_lowercase());
+        } else if character.is_whitespace() || "-_/.,".contains(character) {
+            pending_separator = true;
+        }
+    }
+
+    if result.is_empty() {
+        FALLBACK_SLUG.to_string()
+    } else {
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slugify, FALLBACK_SLUG};
+
+    #[test]
+    fn slugify_slugifies_headings_with_emoji_and_punctuation() {
+        // act
+        let results = [
+            "Heading One",
+            "🌟 Heading Two",
+            "💫 Heading Three",
+            "Heading Four!",
+        ]
+        .map(slugify);
+
+        // assert
+        // The star emoji transliterates to `*`, which is dropped along with other punctuation
+        assert_eq!(
+            results,
+            [
+                "heading-one",
+                "heading-two",
+                "dizzy-heading-three",
+                "heading-four"
+            ]
+        );
+    }
+
+    #[test]
+    fn slugify_transliterates_accented_characters() {
+        // act
+        let result = slugify("Crème Brûlée à la Façon");
+
+        // assert
+        assert_eq!(result, "creme-brulee-a-la-facon");
+    }
+
+    #[test]
+    fn slugify_strips_punctuation() {
+        // act
+        let result = slugify("What's new? (Part 2: the [sequel]!)");
+
+        // assert
+        assert_eq!(result, "whats-new-part-2-the-sequel");
+    }
+
+    #[test]
+    fn slugify_collapses_separators_and_trims_hyphens() {
+        // act
+        let result = slugify("  --Hello -- / world,  again--  ");
+
+        // assert
+        assert_eq!(result, "hello-world-again");
+    }
+
+    #[test]
+    fn slugify_returns_fallback_for_empty_or_punctuation_only_input() {
+        // act
+        let empty_result = slugify("");
+        let punctuation_result = slugify("?!-- ()");
+
+        // assert
+        assert_eq!(empty_result, FALLBACK_SLUG);
+        assert_eq!(punctuation_result, FALLBACK_SLUG);
+    }
+}
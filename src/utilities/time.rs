@@ -0,0 +1,203 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Cutoff time for `value`, either an RFC 3339 timestamp, such as `2024-05-01T09:30:00Z`, or a
+/// duration before `now`, such as `36h`, `7d` or `1h30m`. Duration units are `s`, `m`, `h`, `d`
+/// and `w`.
+///
+/// # Errors
+/// Errors with a message when `value` is neither a valid timestamp nor a valid duration
+pub fn parse_since(value: &str, now: SystemTime) -> Result<SystemTime, String> {
+    let value = value.trim();
+    if value.contains('-') {
+        return parse_rfc3339(value).ok_or_else(|| format!("Invalid RFC 3339 timestamp: {value}"));
+    }
+    let duration = parse_duration(value).ok_or_else(|| {
+        format!("Invalid time: {value}, expected an RFC 3339 timestamp or a duration such as 7d")
+    })?;
+    now.checked_sub(duration)
+        .ok_or_else(|| format!("Duration is too long: {value}"))
+}
+
+/// Duration from a sequence of whole numbers, each followed by a unit, such as `1h30m`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for character in value.chars() {
+        if character.is_ascii_digit() {
+            number.push(character);
+            continue;
+        }
+        let unit_seconds = match character {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+        let count: u64 = number.parse().ok()?;
+        seconds = seconds.checked_add(count.checked_mul(unit_seconds)?)?;
+        number.clear();
+    }
+    if !number.is_empty() || value.is_empty() {
+        return None;
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+/// Days from the Unix epoch to the civil date `year`-`month`-`day`, in the proleptic Gregorian
+/// calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Civil date, as `year`, `month` and `day`, `days` from the Unix epoch, in the proleptic
+/// Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// UTC date of `time`, such as `2024-05-01`. Times before the Unix epoch give its date.
+pub fn iso_date(time: SystemTime) -> String {
+    let days = time.duration_since(UNIX_EPOCH).map_or(0, |value| {
+        i64::try_from(value.as_secs() / 86_400).unwrap_or(0)
+    });
+    let (year, month, day) = civil_from_days(days);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Parses an RFC 3339 timestamp, such as `2024-05-01T09:30:00+02:00`, with optional fractional
+/// seconds
+fn parse_rfc3339(value: &str) -> Option<SystemTime> {
+    let number = |text: &str| -> Option<i64> {
+        if text.is_empty() || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        text.parse().ok()
+    };
+    let (date, time) = value.split_once(['T', 't', ' '])?;
+    let mut date_parts = date.splitn(3, '-');
+    let year = number(date_parts.next()?)?;
+    let month = number(date_parts.next()?)?;
+    let day = number(date_parts.next()?)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let (clock, offset_seconds) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else {
+        let sign_index = time.rfind(['+', '-'])?;
+        let (clock, offset) = time.split_at(sign_index);
+        let (offset_hours, offset_minutes) = offset[1..].split_once(':')?;
+        let offset_seconds = number(offset_hours)? * 3600 + number(offset_minutes)? * 60;
+        (
+            clock,
+            if offset.starts_with('-') {
+                -offset_seconds
+            } else {
+                offset_seconds
+            },
+        )
+    };
+    let (clock, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+    if !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let mut clock_parts = clock.splitn(3, ':');
+    let hour = number(clock_parts.next()?)?;
+    let minute = number(clock_parts.next()?)?;
+    let second = number(clock_parts.next()?)?;
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second
+        - offset_seconds;
+    let nanoseconds: u32 = format!("{fraction:0<9}").get(..9)?.parse().ok()?;
+    let since_epoch = Duration::new(seconds.unsigned_abs(), nanoseconds);
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(since_epoch)
+    } else {
+        UNIX_EPOCH.checked_sub(since_epoch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{iso_date, parse_since};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn parse_since_reads_rfc3339_timestamps() {
+        // arrange
+        let now = UNIX_EPOCH;
+
+        // act
+        let utc_result = parse_since("2024-05-01T09:30:00Z", now);
+        let offset_result = parse_since("2024-05-01T11:30:00.5+02:00", now);
+
+        // assert
+        let expected = UNIX_EPOCH + Duration::from_secs(1_714_555_800);
+        assert_eq!(utc_result, Ok(expected));
+        assert_eq!(offset_result, Ok(expected + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn parse_since_subtracts_durations_from_now() {
+        // arrange
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        // act
+        let result = parse_since("1d2h30m", now);
+
+        // assert
+        assert_eq!(result, Ok(now - Duration::from_secs(86_400 + 9_000)));
+    }
+
+    #[test]
+    fn iso_date_formats_utc_dates() {
+        // act
+        let epoch_result = iso_date(UNIX_EPOCH);
+        let leap_day_result = iso_date(UNIX_EPOCH + Duration::from_secs(1_709_208_000));
+        let result = iso_date(UNIX_EPOCH + Duration::from_secs(1_714_555_800));
+
+        // assert
+        assert_eq!(epoch_result, "1970-01-01");
+        assert_eq!(leap_day_result, "2024-02-29");
+        assert_eq!(result, "2024-05-01");
+    }
+
+    #[test]
+    fn parse_since_rejects_invalid_values() {
+        // arrange
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        // act
+        let unitless_result = parse_since("30", now);
+        let invalid_date_result = parse_since("2024-13-01T00:00:00Z", now);
+
+        // assert
+        assert!(unitless_result.is_err());
+        assert!(invalid_date_result.is_err());
+    }
+}
@@ -1,6 +1,65 @@
 use assert_cmd::prelude::*;
 use predicates::prelude::*;
-use std::process::Command;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+/// Starts a local grammar server, replying to every request with the LanguageTool response
+/// fixture. Returns the check endpoint URL and a count of requests received.
+fn start_grammar_server() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Error binding test server");
+    let url = format!(
+        "http://{}/v2/check",
+        listener.local_addr().expect("Expected test server address")
+    );
+    let request_count = Arc::new(AtomicUsize::new(0));
+    let server_request_count = Arc::clone(&request_count);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            server_request_count.fetch_add(1, Ordering::SeqCst);
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).expect("Error reading request") > 0 && line != "\r\n"
+            {
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().expect("Expected content length");
+                    }
+                }
+                line.clear();
+            }
+            let mut body = vec![0; content_length];
+            reader
+                .read_exact(&mut body)
+                .expect("Error reading request body");
+            let response_body = include_str!("../fixtures/languagetool_response.json");
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response_body}",
+                response_body.len()
+            )
+            .expect("Error writing response");
+        }
+    });
+    (url, request_count)
+}
+
+/// URL on a local port with nothing listening, so requests to it fail
+fn unused_grammar_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Error binding test server");
+    format!(
+        "http://{}/v2/check",
+        listener.local_addr().expect("Expected test server address")
+    )
+}
 
 #[test]
 fn it_returns_error_when_input_file_does_not_exist() -> Result<(), Box<dyn std::error::Error>> {
@@ -13,3 +72,200 @@ fn it_returns_error_when_input_file_does_not_exist() -> Result<(), Box<dyn std::
 
     Ok(())
 }
+
+#[test]
+fn it_returns_error_for_document_with_warnings_in_strict_mode(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("markwrite")?;
+
+    cmd.arg("--strict").arg("fixtures/duplicate_headings.md");
+    cmd.arg("--output")
+        .arg("fixtures/duplicate_headings_cli.html");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "[ WARN ] Duplicate heading id: section",
+        ))
+        .stderr(predicate::str::contains(
+            "Parse warnings found with strict mode enabled.",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn it_returns_error_for_output_file_with_multiple_inputs() -> Result<(), Box<dyn std::error::Error>>
+{
+    let mut cmd = Command::cargo_bin("markwrite")?;
+
+    cmd.arg("fixtures/file.md")
+        .arg("fixtures/duplicate_headings.md")
+        .arg("--output")
+        .arg("fixtures/multiple_inputs_cli.html");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--output takes a single input file, use --output-dir for multiple inputs",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn it_reports_output_error_in_strict_mode() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = assert_fs::TempDir::new()?;
+    let mut cmd = Command::cargo_bin("markwrite")?;
+
+    // A directory cannot be written as the output file
+    cmd.arg("--strict")
+        .arg("fixtures/file.md")
+        .arg("--output")
+        .arg(temp_dir.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unable to update fixtures/file.md: ",
+        ))
+        .stderr(predicate::str::contains("Parse warnings found").not());
+
+    Ok(())
+}
+
+#[test]
+fn it_returns_error_for_frontmatter_error_in_check_only_mode(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("markwrite")?;
+
+    cmd.arg("--check-only")
+        .arg("fixtures/invalid_frontmatter.md");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Unable to parse frontmatter"))
+        .stderr(predicate::str::contains("Check failed for 1 input(s)."));
+    assert!(!std::path::Path::new("fixtures/invalid_frontmatter.html").exists());
+
+    Ok(())
+}
+
+#[test]
+fn it_returns_error_for_markdown_parse_error_in_check_only_mode(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("markwrite")?;
+
+    cmd.arg("--check-only")
+        .arg("fixtures/unclosed_grammar_ignore.md");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unable to parse markdown to HTML.",
+        ))
+        .stderr(predicate::str::contains("Check failed for 1 input(s)."));
+    assert!(!std::path::Path::new("fixtures/unclosed_grammar_ignore.html").exists());
+
+    Ok(())
+}
+
+#[test]
+fn it_uses_grammar_server_url_from_environment_when_flag_is_absent(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (url, request_count) = start_grammar_server();
+    let mut cmd = Command::cargo_bin("markwrite")?;
+
+    cmd.env("MARKWRITE_LANGUAGETOOL_URL", &url)
+        .arg("--check-only")
+        .arg("--spelling")
+        .arg("--grammar-format")
+        .arg("json")
+        .arg("fixtures/file.md");
+    cmd.assert().success().stdout(predicate::str::contains(
+        r#""ruleId": "MORFOLOGIK_RULE_EN_GB""#,
+    ));
+    assert!(request_count.load(Ordering::SeqCst) > 0);
+
+    Ok(())
+}
+
+#[test]
+fn it_prefers_grammar_server_url_flag_over_environment() -> Result<(), Box<dyn std::error::Error>> {
+    let (url, request_count) = start_grammar_server();
+    let mut cmd = Command::cargo_bin("markwrite")?;
+
+    cmd.env("MARKWRITE_LANGUAGETOOL_URL", unused_grammar_url())
+        .arg("--check-only")
+        .arg("--spelling")
+        .arg("--grammar-format")
+        .arg("json")
+        .arg("--grammar-url")
+        .arg(&url)
+        .arg("fixtures/file.md");
+    cmd.assert().success().stdout(predicate::str::contains(
+        r#""ruleId": "MORFOLOGIK_RULE_EN_GB""#,
+    ));
+    assert!(request_count.load(Ordering::SeqCst) > 0);
+
+    Ok(())
+}
+
+#[test]
+fn it_prints_config_with_flag_value_overriding_environment(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("markwrite")?;
+
+    cmd.env("MARKWRITE_LANGUAGETOOL_URL", "http://127.0.0.1:1/env/check")
+        .env("MARKWRITE_LANGUAGETOOL_API_KEY", "secret-key")
+        .env("MARKWRITE_LANGUAGETOOL_USERNAME", "writer@example.com")
+        .arg("--print-config")
+        .arg("--grammar-url")
+        .arg("http://127.0.0.1:1/flag/check")
+        .arg("--max-replacements")
+        .arg("2");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""grammarUrl": "http://127.0.0.1:1/flag/check""#,
+        ))
+        .stdout(predicate::str::contains(r#""grammarReplacementLimit": 2"#))
+        .stdout(predicate::str::contains(
+            r#""grammarUsername": "writer@example.com""#,
+        ))
+        .stdout(predicate::str::contains("secret-key").not());
+
+    Ok(())
+}
+
+#[test]
+fn it_lists_nested_headings_indented_by_level_without_writing_output(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("markwrite")?;
+
+    cmd.arg("--list-headings")
+        .arg("--heading-slugs")
+        .arg("fixtures/nested_headings.md");
+    cmd.assert().success().stdout(
+        "- Guide (#guide)\n  - Getting Started (#getting-started)\n    - Install (#install)\n    - Configure (#configure)\n  - Next Steps (#next-steps)\n",
+    );
+    assert!(!std::path::Path::new("fixtures/nested_headings.html").exists());
+
+    Ok(())
+}
+
+#[test]
+fn grammar_subcommand_prints_results_without_writing_html() -> Result<(), Box<dyn std::error::Error>>
+{
+    let (url, request_count) = start_grammar_server();
+    let temp_dir = assert_fs::TempDir::new()?;
+    let markdown_path = temp_dir.path().join("file.md");
+    std::fs::copy("fixtures/file.md", &markdown_path)?;
+    let mut cmd = Command::cargo_bin("markwrite")?;
+
+    cmd.env("MARKWRITE_LANGUAGETOOL_URL", &url)
+        .arg("grammar")
+        .arg("--format")
+        .arg("json")
+        .arg(&markdown_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        r#""ruleId": "MORFOLOGIK_RULE_EN_GB""#,
+    ));
+    assert!(request_count.load(Ordering::SeqCst) > 0);
+    assert!(!temp_dir.path().join("file.html").exists());
+
+    Ok(())
+}